@@ -0,0 +1,54 @@
+//! Packs an asset directory into an archive for the `Archive` source.
+//!
+//! Usage: `amethyst_pack [--compress] <directory> <archive>`
+
+use std::{fs::File, io::BufWriter, path::Path, process};
+
+use amethyst_assets::ArchiveWriter;
+use amethyst_error::{format_err, Error, ResultExt};
+
+const USAGE: &str = "Usage: amethyst_pack [--compress] <directory> <archive>";
+
+fn main() {
+    if let Err(e) = run() {
+        for cause in e.causes() {
+            eprintln!("{}", cause);
+        }
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut compress = false;
+    let mut paths = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--compress" => compress = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => paths.push(arg),
+        }
+    }
+    let (directory, archive) = match paths.as_slice() {
+        [directory, archive] => (directory, archive),
+        _ => return Err(format_err!("{}", USAGE)),
+    };
+
+    // Checked first, so a wrong directory doesn't leave an empty archive behind.
+    if !Path::new(directory).is_dir() {
+        return Err(format_err!("{:?} is not a directory", directory));
+    }
+    let file = File::create(archive)
+        .with_context(|_| format_err!("Failed to create archive {:?}", archive))?;
+    let mut writer = ArchiveWriter::new(BufWriter::new(file))?;
+    let count = writer.add_directory(directory, compress)?;
+    writer.finish()?;
+
+    println!(
+        "Packed {} assets from {:?} into {:?}",
+        count, directory, archive
+    );
+    Ok(())
+}
@@ -0,0 +1,506 @@
+//! Records which assets were loaded as sub-assets of which others.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::Arc,
+};
+
+use parking_lot::RwLock;
+
+use amethyst_error::Error;
+
+use crate::{FormatValue, Reload, WatchedChanges};
+
+thread_local! {
+    /// The assets whose sub-assets are being loaded on this thread, innermost last.
+    static LOADING: RefCell<Vec<AssetKey>> = RefCell::new(Vec::new());
+}
+
+/// Identifies an asset in the `AssetDependencies` by the source and the name it was loaded
+/// with, like the `Loader` does.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssetKey {
+    /// The id of the source, empty for the default source.
+    pub source: String,
+    /// The name of the asset in the source.
+    pub name: String,
+}
+
+impl AssetKey {
+    /// Creates the key of the asset `name` loaded from the source `source`.
+    pub fn new<S: Into<String>, N: Into<String>>(source: S, name: N) -> Self {
+        AssetKey {
+            source: source.into(),
+            name: name.into(),
+        }
+    }
+}
+
+/// Graph of the dependencies between assets, recorded by the `Loader`.
+///
+/// Assets are identified by the source and name they were loaded with, see `AssetKey`. An asset
+/// depends on every asset loaded while its sub-assets were loaded, for example a prefab depends
+/// on the textures it references. When an asset is hot reloaded, the assets depending on it are
+/// reloaded after it, in topological order.
+///
+/// Dependencies are only recorded while hot reloading is enabled, and an asset is forgotten
+/// once all its handles were dropped.
+///
+/// Can be inspected with `Loader::dependencies`.
+#[derive(Debug, Default)]
+pub struct AssetDependencies {
+    dependencies: HashMap<AssetKey, BTreeSet<AssetKey>>,
+    dependents: HashMap<AssetKey, BTreeSet<AssetKey>>,
+    stale: HashSet<AssetKey>,
+    /// The number of loaded assets with each key, counted by their `TrackedAsset`.
+    tracked: HashMap<AssetKey, usize>,
+}
+
+impl AssetDependencies {
+    /// The assets directly used by `asset`.
+    pub fn dependencies(&self, asset: &AssetKey) -> impl Iterator<Item = &AssetKey> + '_ {
+        self.dependencies.get(asset).into_iter().flatten()
+    }
+
+    /// The assets directly using `asset`.
+    pub fn dependents(&self, asset: &AssetKey) -> impl Iterator<Item = &AssetKey> + '_ {
+        self.dependents.get(asset).into_iter().flatten()
+    }
+
+    /// All assets using `asset` directly or indirectly, each listed after the assets it uses.
+    /// This is the order they are reloaded in when `asset` changes.
+    pub fn all_dependents(&self, asset: &AssetKey) -> Vec<AssetKey> {
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        self.visit_dependents(asset, &mut visited, &mut order);
+        order.reverse();
+        order
+    }
+
+    fn visit_dependents<'a>(
+        &'a self,
+        asset: &AssetKey,
+        visited: &mut HashSet<&'a AssetKey>,
+        order: &mut Vec<AssetKey>,
+    ) {
+        for dependent in self.dependents(asset) {
+            if visited.insert(dependent) {
+                self.visit_dependents(dependent, visited, order);
+                order.push(dependent.clone());
+            }
+        }
+    }
+
+    /// Records that the asset `dependent` uses the asset `dependency`.
+    pub(crate) fn add(&mut self, dependent: &AssetKey, dependency: &AssetKey) {
+        self.dependencies
+            .entry(dependent.clone())
+            .or_default()
+            .insert(dependency.clone());
+        self.dependents
+            .entry(dependency.clone())
+            .or_default()
+            .insert(dependent.clone());
+    }
+
+    /// Forgets the assets used by `dependent`, before they are recorded again or when it is
+    /// unloaded. It doesn't have to reload because of them anymore.
+    pub(crate) fn clear(&mut self, dependent: &AssetKey) {
+        self.stale.remove(dependent);
+        for dependency in self.dependencies.remove(dependent).into_iter().flatten() {
+            if let Some(dependents) = self.dependents.get_mut(&dependency) {
+                dependents.remove(dependent);
+                if dependents.is_empty() {
+                    self.dependents.remove(&dependency);
+                }
+            }
+        }
+    }
+
+    fn track(&mut self, asset: &AssetKey) {
+        *self.tracked.entry(asset.clone()).or_default() += 1;
+    }
+
+    /// Forgets `asset` when the last asset loaded with its key was unloaded.
+    fn untrack(&mut self, asset: &AssetKey) {
+        if let Some(count) = self.tracked.get_mut(asset) {
+            *count -= 1;
+            if *count == 0 {
+                self.tracked.remove(asset);
+                self.clear(asset);
+            }
+        }
+    }
+
+    /// Called when `asset` starts reloading.
+    ///
+    /// If the reload wasn't caused by a dependency, everything using the asset is marked for
+    /// reloading. Assets reloading because of a dependency were already marked together with
+    /// their own dependents, so they don't mark them again.
+    pub(crate) fn reloading(&mut self, asset: &AssetKey) {
+        if !self.stale.remove(asset) {
+            let dependents = self.all_dependents(asset);
+            self.stale.extend(dependents);
+        }
+    }
+
+    /// Returns `true` if some assets still have to reload because of a dependency.
+    pub(crate) fn has_pending_reloads(&self) -> bool {
+        !self.stale.is_empty()
+    }
+
+    /// Returns `true` if `asset` has to reload because of a dependency, and none of the assets
+    /// it uses is still waiting to reload.
+    pub(crate) fn needs_reload(&self, asset: &AssetKey) -> bool {
+        self.stale.contains(asset)
+            && self
+                .dependencies(asset)
+                .all(|dependency| !self.stale.contains(dependency))
+    }
+}
+
+/// Returns the asset whose sub-assets are being loaded on this thread, if any.
+pub(crate) fn loading_dependent() -> Option<AssetKey> {
+    LOADING.with(|loading| loading.borrow().last().cloned())
+}
+
+/// Marks `asset` as the one loading sub-assets on this thread while alive.
+pub(crate) struct LoadingScope;
+
+impl LoadingScope {
+    pub(crate) fn enter(asset: AssetKey) -> Self {
+        LOADING.with(|loading| loading.borrow_mut().push(asset));
+        LoadingScope
+    }
+}
+
+impl Drop for LoadingScope {
+    fn drop(&mut self) {
+        LOADING.with(|loading| loading.borrow_mut().pop());
+    }
+}
+
+/// Wraps the reload object of `value`, so the asset is also reloaded with its dependencies.
+///
+/// The dependencies of the asset are forgotten when the reload object is dropped, which the
+/// `AssetStorage` does once the handles of the asset are gone.
+pub(crate) fn track<D: 'static>(
+    value: FormatValue<D>,
+    key: AssetKey,
+    graph: Arc<RwLock<AssetDependencies>>,
+) -> FormatValue<D> {
+    if value.reload.is_none() {
+        return value;
+    }
+    graph.write().track(&key);
+    track_asset(value, Arc::new(TrackedAsset { key, graph }))
+}
+
+fn track_asset<D: 'static>(value: FormatValue<D>, asset: Arc<TrackedAsset>) -> FormatValue<D> {
+    let FormatValue { data, reload } = value;
+    FormatValue {
+        data,
+        reload: reload
+            .map(|inner| Box::new(DependentReload { inner, asset }) as Box<dyn Reload<D>>),
+    }
+}
+
+/// An asset in the graph, forgotten when dropped. Shared by all reload objects of the asset.
+struct TrackedAsset {
+    key: AssetKey,
+    graph: Arc<RwLock<AssetDependencies>>,
+}
+
+impl Drop for TrackedAsset {
+    fn drop(&mut self) {
+        self.graph.write().untrack(&self.key);
+    }
+}
+
+/// A `Reload` which also reloads when a dependency of the asset was reloaded.
+struct DependentReload<D> {
+    inner: Box<dyn Reload<D>>,
+    asset: Arc<TrackedAsset>,
+}
+
+impl<D: 'static> Clone for DependentReload<D> {
+    fn clone(&self) -> Self {
+        DependentReload {
+            inner: self.inner.clone(),
+            asset: self.asset.clone(),
+        }
+    }
+}
+
+impl<D: 'static> DependentReload<D> {
+    fn dependency_reloaded(&self) -> bool {
+        self.asset.graph.read().needs_reload(&self.asset.key)
+    }
+}
+
+impl<D: 'static> Reload<D> for DependentReload<D> {
+    fn needs_reload(&self) -> bool {
+        self.inner.needs_reload() || self.dependency_reloaded()
+    }
+
+    fn needs_reload_watched(&self, changes: &WatchedChanges) -> bool {
+        self.inner.needs_reload_watched(changes) || self.dependency_reloaded()
+    }
+
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn format(&self) -> &'static str {
+        self.inner.format()
+    }
+
+    fn reload(self: Box<Self>) -> Result<FormatValue<D>, Error> {
+        let DependentReload { inner, asset } = *self;
+        asset.graph.write().reloading(&asset.key);
+        let value = inner.reload()?;
+        // Keeps the asset tracked, as the reloaded value replaces this one.
+        Ok(track_asset(value, asset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use rayon::ThreadPoolBuilder;
+
+    use amethyst_core::{
+        ecs::prelude::{Entity, Read, ReadExpect, RunNow, VecStorage, World, WorldExt},
+        SystemDesc, Time,
+    };
+
+    use crate::{
+        Asset, AssetStorage, Format, Handle, Loader, Prefab, PrefabData, PrefabLoaderSystemDesc,
+        ProgressCounter, Source,
+    };
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Texture;
+
+    impl Asset for Texture {
+        const NAME: &'static str = "Texture";
+        type Data = ();
+        type HandleStorage = VecStorage<Handle<Self>>;
+    }
+
+    #[derive(Clone, Debug)]
+    struct TextureFormat;
+
+    impl Format<()> for TextureFormat {
+        fn name(&self) -> &'static str {
+            "TEXTURE"
+        }
+
+        fn import_simple(&self, _bytes: Vec<u8>) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct LevelFormat;
+
+    impl Format<Prefab<Level>> for LevelFormat {
+        fn name(&self) -> &'static str {
+            "LEVEL"
+        }
+
+        fn import_simple(&self, _bytes: Vec<u8>) -> Result<Prefab<Level>, Error> {
+            Ok(Prefab::new_main(Level::default()))
+        }
+    }
+
+    #[derive(Debug)]
+    struct Files;
+
+    impl Source for Files {
+        fn modified(&self, _path: &str) -> Result<u64, Error> {
+            Ok(1)
+        }
+
+        fn load(&self, _path: &str) -> Result<Vec<u8>, Error> {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Prefab loading a texture as sub-asset.
+    #[derive(Default)]
+    struct Level {
+        texture: Option<Handle<Texture>>,
+    }
+
+    impl<'a> PrefabData<'a> for Level {
+        type SystemData = (ReadExpect<'a, Loader>, Read<'a, AssetStorage<Texture>>);
+        type Result = ();
+
+        fn add_to_entity(
+            &self,
+            _: Entity,
+            _: &mut Self::SystemData,
+            _: &[Entity],
+            _: &[Entity],
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn load_sub_assets(
+            &mut self,
+            _: &mut ProgressCounter,
+            (loader, storage): &mut Self::SystemData,
+        ) -> Result<bool, Error> {
+            self.texture = Some(loader.load_from("grass.png", TextureFormat, "files", (), storage));
+            Ok(false)
+        }
+    }
+
+    #[test]
+    fn unloaded_assets_are_forgotten() {
+        let mut world = World::new();
+        let pool = Arc::new(ThreadPoolBuilder::default().build().unwrap());
+        world.insert(pool.clone());
+        let mut loader = Loader::new(".", pool);
+        loader.add_source("files", Files);
+        loader.set_hot_reload(true);
+        world.insert(loader);
+        world.insert(Time::default());
+        let mut system = PrefabLoaderSystemDesc::<Level>::default().build(&mut world);
+        RunNow::setup(&mut system, &mut world);
+
+        let handle = world.read_resource::<Loader>().load_from(
+            "level.ron",
+            LevelFormat,
+            "files",
+            (),
+            &world.read_resource::<AssetStorage<Prefab<Level>>>(),
+        );
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while world
+            .read_resource::<AssetStorage<Prefab<Level>>>()
+            .get(&handle)
+            .is_none()
+        {
+            assert!(Instant::now() < deadline, "Prefab was not loaded");
+            std::thread::sleep(Duration::from_millis(1));
+            system.run_now(&world);
+        }
+        let level = AssetKey::new("files", "level.ron");
+        assert_eq!(
+            world
+                .read_resource::<Loader>()
+                .dependencies()
+                .dependencies(&level)
+                .collect::<Vec<_>>(),
+            vec![&AssetKey::new("files", "grass.png")]
+        );
+
+        drop(handle);
+        system.run_now(&world);
+        let loader = world.read_resource::<Loader>();
+        assert_eq!(loader.dependencies().dependencies(&level).count(), 0);
+        assert_eq!(
+            loader
+                .dependencies()
+                .dependents(&AssetKey::new("files", "grass.png"))
+                .count(),
+            0
+        );
+    }
+
+    fn key(name: &str) -> AssetKey {
+        AssetKey::new("", name)
+    }
+
+    fn keys(names: &[&str]) -> Vec<AssetKey> {
+        names.iter().map(|name| key(name)).collect()
+    }
+
+    fn graph() -> AssetDependencies {
+        // level.ron -> player.ron -> player.png
+        //           \-> grass.png
+        // player.ron -> material.ron -> player.png
+        let mut graph = AssetDependencies::default();
+        graph.add(&key("level.ron"), &key("player.ron"));
+        graph.add(&key("level.ron"), &key("grass.png"));
+        graph.add(&key("player.ron"), &key("player.png"));
+        graph.add(&key("player.ron"), &key("material.ron"));
+        graph.add(&key("material.ron"), &key("player.png"));
+        graph
+    }
+
+    #[test]
+    fn dependents_in_topological_order() {
+        let graph = graph();
+        let mut direct = graph
+            .dependents(&key("player.png"))
+            .cloned()
+            .collect::<Vec<_>>();
+        direct.sort();
+        assert_eq!(direct, keys(&["material.ron", "player.ron"]));
+        assert_eq!(
+            graph.all_dependents(&key("player.png")),
+            keys(&["material.ron", "player.ron", "level.ron"])
+        );
+        assert!(graph.all_dependents(&key("level.ron")).is_empty());
+    }
+
+    #[test]
+    fn reloads_dependents_after_dependencies() {
+        let mut graph = graph();
+        graph.reloading(&key("player.png"));
+        assert!(graph.needs_reload(&key("material.ron")));
+        assert!(!graph.needs_reload(&key("player.ron")));
+        assert!(!graph.needs_reload(&key("level.ron")));
+
+        graph.reloading(&key("material.ron"));
+        assert!(graph.needs_reload(&key("player.ron")));
+        graph.reloading(&key("player.ron"));
+        assert!(graph.needs_reload(&key("level.ron")));
+        graph.reloading(&key("level.ron"));
+        assert!(!graph.needs_reload(&key("level.ron")));
+        assert!(!graph.needs_reload(&key("player.ron")));
+    }
+
+    #[test]
+    fn clear_removes_edges() {
+        let mut graph = graph();
+        graph.clear(&key("player.ron"));
+        assert_eq!(graph.dependencies(&key("player.ron")).count(), 0);
+        assert_eq!(
+            graph.all_dependents(&key("player.png")),
+            keys(&["material.ron"])
+        );
+    }
+
+    #[test]
+    fn sources_are_kept_apart() {
+        let mut graph = AssetDependencies::default();
+        let (mod_level, mod_grass) = (
+            AssetKey::new("mod", "level.ron"),
+            AssetKey::new("mod", "grass.png"),
+        );
+        graph.add(&key("level.ron"), &key("grass.png"));
+        graph.add(&mod_level, &mod_grass);
+        assert_eq!(graph.all_dependents(&mod_grass), vec![mod_level.clone()]);
+
+        graph.track(&key("level.ron"));
+        graph.track(&mod_level);
+        graph.untrack(&mod_level);
+        assert_eq!(graph.dependencies(&mod_level).count(), 0);
+        assert_eq!(
+            graph.dependencies(&key("level.ron")).collect::<Vec<_>>(),
+            vec![&key("grass.png")]
+        );
+
+        graph.reloading(&key("grass.png"));
+        assert!(graph.needs_reload(&key("level.ron")));
+        assert!(!graph.needs_reload(&mod_level));
+    }
+}
@@ -0,0 +1,349 @@
+use std::{
+    fs,
+    hash::Hasher,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use fnv::FnvHasher;
+use log::{debug, warn};
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+use amethyst_error::{format_err, Error, ResultExt};
+
+use crate::{Format, FormatValue, Reload, SingleFile, Source};
+
+/// Directory caching imported asset data, so expensive imports like glTF scenes and images
+/// only run again when their files change.
+///
+/// Formats opt in to caching by being wrapped with `cached`. Entries are keyed by the format
+/// name, the format options, the asset name and the contents of every file read by the
+/// import, and re-imported when any of them changes.
+///
+/// The asset data has to be serializable, like the data of `GltfSceneFormat`, `ImageFormat`
+/// and `TtfFormat`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use amethyst_assets::{AssetStorage, ImportCache, Loader, Prefab, RonFormat};
+/// use amethyst_core::Transform;
+///
+/// # let loader: Loader = unimplemented!();
+/// # let storage: AssetStorage<Prefab<Transform>> = unimplemented!();
+/// let cache = ImportCache::new("cache/assets");
+/// let handle = loader.load("prefab/level.ron", cache.cached(RonFormat), (), &storage);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ImportCache {
+    directory: PathBuf,
+}
+
+impl ImportCache {
+    /// Creates an import cache storing its entries in `directory`, which is created when needed.
+    pub fn new<P>(directory: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        ImportCache {
+            directory: directory.into(),
+        }
+    }
+
+    /// The directory entries are stored in.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Wraps `format` so its imports go through this cache.
+    pub fn cached<F>(&self, format: F) -> CachedFormat<F> {
+        CachedFormat {
+            format,
+            cache: self.clone(),
+        }
+    }
+
+    /// Removes all entries.
+    pub fn clear(&self) -> Result<(), Error> {
+        if self.directory.exists() {
+            fs::remove_dir_all(&self.directory).with_context(|_| {
+                format_err!("Failed to remove import cache {:?}", self.directory)
+            })?;
+        }
+        Ok(())
+    }
+
+    fn entry_path(&self, format: &str, options: &[u8], name: &str) -> PathBuf {
+        let mut hasher = FnvHasher::default();
+        for part in &[format.as_bytes(), options, name.as_bytes()] {
+            hasher.write_usize(part.len());
+            hasher.write(part);
+        }
+        self.directory.join(format!("{:016x}.bin", hasher.finish()))
+    }
+
+    fn read(&self, path: &Path) -> Option<CacheEntry> {
+        let bytes = fs::read(path).ok()?;
+        bincode::deserialize(&bytes)
+            .map_err(|e| warn!("Ignoring invalid import cache entry {:?}: {}", path, e))
+            .ok()
+    }
+
+    fn write(&self, path: &Path, entry: &CacheEntry) -> Result<(), Error> {
+        static NEXT_TEMP: AtomicUsize = AtomicUsize::new(0);
+
+        let bytes = bincode::serialize(entry)?;
+        fs::create_dir_all(&self.directory)?;
+        // Written next to the entry and renamed, so other threads never read a partial entry.
+        let temp = path.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp, bytes)?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+}
+
+/// A cached import, stored with everything it was imported from.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    format: String,
+    options: Vec<u8>,
+    name: String,
+    /// Every file read by the import, with a hash of its contents.
+    files: Vec<(String, u64)>,
+    /// The imported data, serialized with `bincode`.
+    data: Vec<u8>,
+}
+
+impl CacheEntry {
+    fn is_valid(&self, format: &str, options: &[u8], name: &str, source: &dyn Source) -> bool {
+        self.format == format
+            && self.options.as_slice() == options
+            && self.name == name
+            && self.files.iter().all(|(path, hash)| {
+                source
+                    .load(path)
+                    .map(|bytes| hash_bytes(&bytes) == *hash)
+                    .unwrap_or(false)
+            })
+    }
+}
+
+/// `Format` importing through an `ImportCache`, created by `ImportCache::cached`.
+///
+/// The asset data and the wrapped format have to be serializable. Reload objects created by
+/// the wrapped format are replaced by a `SingleFile` watching the asset file.
+#[derive(Debug, Clone)]
+pub struct CachedFormat<F> {
+    format: F,
+    cache: ImportCache,
+}
+
+impl<F> CachedFormat<F> {
+    /// The wrapped format.
+    pub fn format(&self) -> &F {
+        &self.format
+    }
+}
+
+impl<D, F> Format<D> for CachedFormat<F>
+where
+    D: Serialize + DeserializeOwned + 'static,
+    F: Format<D> + Serialize + Clone,
+{
+    fn name(&self) -> &'static str {
+        self.format.name()
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<D, Error> {
+        self.format.import_simple(bytes)
+    }
+
+    fn import(
+        &self,
+        name: String,
+        source: Arc<dyn Source>,
+        create_reload: Option<Box<dyn Format<D>>>,
+    ) -> Result<FormatValue<D>, Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("import_cached_asset");
+
+        let format = self.format.name();
+        let options = bincode::serialize(&self.format)
+            .with_context(|_| format_err!("Failed to serialize options of format {}", format))?;
+        let path = self.cache.entry_path(format, &options, &name);
+
+        let cached = self
+            .cache
+            .read(&path)
+            .filter(|entry| entry.is_valid(format, &options, &name, &*source))
+            .and_then(|entry| {
+                bincode::deserialize(&entry.data)
+                    .map_err(|e| warn!("Ignoring invalid cached data of {:?}: {}", name, e))
+                    .ok()
+            });
+        let data = match cached {
+            Some(data) => {
+                debug!("Loaded {:?} with format {} from import cache", name, format);
+                data
+            }
+            None => {
+                let recording = Arc::new(RecordingSource {
+                    source: source.clone(),
+                    files: Mutex::new(Vec::new()),
+                });
+                let data = self
+                    .format
+                    .import(name.clone(), recording.clone(), None)?
+                    .data;
+                let entry = bincode::serialize(&data).map(|data| CacheEntry {
+                    format: format.to_owned(),
+                    options,
+                    name: name.clone(),
+                    files: recording.files.lock().clone(),
+                    data,
+                });
+                if let Err(e) = entry
+                    .map_err(Error::from)
+                    .and_then(|entry| self.cache.write(&path, &entry))
+                {
+                    warn!("Failed to cache import of {:?}: {}", name, e);
+                }
+                data
+            }
+        };
+
+        let reload = match create_reload {
+            Some(format) => {
+                let modified = source.modified(&name)?;
+                let reload: Box<dyn Reload<D>> =
+                    Box::new(SingleFile::new(format, modified, name, source));
+                Some(reload)
+            }
+            None => None,
+        };
+        Ok(FormatValue { data, reload })
+    }
+}
+
+/// Source recording the files loaded through it, to validate cache entries later.
+struct RecordingSource {
+    source: Arc<dyn Source>,
+    files: Mutex<Vec<(String, u64)>>,
+}
+
+impl Source for RecordingSource {
+    fn modified(&self, path: &str) -> Result<u64, Error> {
+        self.source.modified(path)
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+        let bytes = self.source.load(path)?;
+        self.files
+            .lock()
+            .push((path.to_owned(), hash_bytes(&bytes)));
+        Ok(bytes)
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.source.contains(path)
+    }
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Files(Mutex<HashMap<String, String>>);
+
+    impl Source for Files {
+        fn modified(&self, _: &str) -> Result<u64, Error> {
+            Ok(0)
+        }
+
+        fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+            self.0
+                .lock()
+                .get(path)
+                .map(|data| data.as_bytes().to_vec())
+                .ok_or_else(|| format_err!("Missing {:?}", path))
+        }
+    }
+
+    #[derive(Clone, Debug, Serialize)]
+    struct SuffixFormat {
+        suffix: String,
+        #[serde(skip)]
+        imports: Arc<AtomicUsize>,
+    }
+
+    impl Format<String> for SuffixFormat {
+        fn name(&self) -> &'static str {
+            "Suffix"
+        }
+
+        fn import_simple(&self, bytes: Vec<u8>) -> Result<String, Error> {
+            self.imports.fetch_add(1, Ordering::Relaxed);
+            Ok(String::from_utf8(bytes)? + &self.suffix)
+        }
+    }
+
+    #[test]
+    fn reimports_only_when_key_changes() {
+        let directory = std::env::temp_dir().join(format!(
+            "amethyst_assets_import_cache_{}",
+            std::process::id()
+        ));
+        let cache = ImportCache::new(&directory);
+        cache.clear().unwrap();
+
+        let files = Arc::new(Files::default());
+        let source = files.clone() as Arc<dyn Source>;
+        files.0.lock().insert("a.txt".into(), "a".into());
+        let imports = Arc::new(AtomicUsize::new(0));
+        let format = |suffix: &str| {
+            cache.cached(SuffixFormat {
+                suffix: suffix.into(),
+                imports: imports.clone(),
+            })
+        };
+        let import = |suffix: &str| {
+            Format::<String>::import(&format(suffix), "a.txt".into(), source.clone(), None)
+                .unwrap()
+                .data
+        };
+
+        assert_eq!(import("!"), "a!");
+        assert_eq!(import("!"), "a!");
+        assert_eq!(imports.load(Ordering::Relaxed), 1);
+
+        assert_eq!(import("?"), "a?");
+        assert_eq!(imports.load(Ordering::Relaxed), 2);
+
+        files.0.lock().insert("a.txt".into(), "b".into());
+        assert_eq!(import("!"), "b!");
+        assert_eq!(import("!"), "b!");
+        assert_eq!(imports.load(Ordering::Relaxed), 3);
+
+        cache.clear().unwrap();
+    }
+}
@@ -14,27 +14,34 @@ pub use crate::formats::JsonFormat;
 pub use crate::{
     asset::{Asset, Format, FormatValue, ProcessableAsset, SerializableFormat},
     cache::Cache,
+    dependencies::{AssetDependencies, AssetKey},
     dyn_format::FormatRegisteredData,
     formats::RonFormat,
     helper::AssetLoaderSystemData,
+    import_cache::{CachedFormat, ImportCache},
     loader::Loader,
     prefab::{
-        AssetPrefab, Prefab, PrefabData, PrefabLoader, PrefabLoaderSystem, PrefabLoaderSystemDesc,
+        AssetPrefab, ComponentRegistry, DynamicComponents, Prefab, PrefabData, PrefabLoader,
+        PrefabLoaderSystem, PrefabLoaderSystemDesc,
     },
     progress::{Completion, Progress, ProgressCounter, Tracker},
-    reload::{HotReloadBundle, HotReloadStrategy, HotReloadSystem, Reload, SingleFile},
-    source::{Directory, Source},
-    storage::{AssetStorage, Handle, ProcessingState, Processor, WeakHandle},
+    reload::{
+        HotReloadBundle, HotReloadStrategy, HotReloadSystem, Reload, SingleFile, WatchedChanges,
+    },
+    source::{Archive, ArchiveWriter, Directory, OverlaySource, Source},
+    storage::{AssetCounts, AssetStorage, Handle, ProcessingState, Processor, WeakHandle},
 };
 
 pub use rayon::ThreadPool;
 
 mod asset;
 mod cache;
+mod dependencies;
 mod dyn_format;
 mod error;
 mod formats;
 mod helper;
+mod import_cache;
 mod loader;
 mod prefab;
 mod progress;
@@ -1,7 +1,8 @@
-use std::{borrow::Borrow, hash::Hash, path::PathBuf, sync::Arc};
+use std::{borrow::Borrow, hash::Hash, ops::Deref, path::PathBuf, sync::Arc};
 
 use fnv::FnvHashMap;
 use log::debug;
+use parking_lot::RwLock;
 use rayon::ThreadPool;
 
 use amethyst_error::ResultExt;
@@ -9,6 +10,7 @@ use amethyst_error::ResultExt;
 use thread_profiler::profile_scope;
 
 use crate::{
+    dependencies::{self, AssetDependencies, AssetKey, LoadingScope},
     error::Error,
     storage::{AssetStorage, Handle, Processed},
     Asset, Directory, Format, FormatValue, Progress, Source,
@@ -16,6 +18,7 @@ use crate::{
 
 /// The asset loader, holding the sources and a reference to the `ThreadPool`.
 pub struct Loader {
+    dependencies: Arc<RwLock<AssetDependencies>>,
     hot_reload: bool,
     pool: Arc<ThreadPool>,
     sources: FnvHashMap<String, Arc<dyn Source>>,
@@ -37,6 +40,7 @@ impl Loader {
         S: Source,
     {
         let mut loader = Loader {
+            dependencies: Default::default(),
             hot_reload: true,
             pool,
             sources: Default::default(),
@@ -73,6 +77,38 @@ impl Loader {
         self.hot_reload = value;
     }
 
+    /// Returns the `ThreadPool` assets are loaded on.
+    pub fn pool(&self) -> &Arc<ThreadPool> {
+        &self.pool
+    }
+
+    /// Returns the dependencies recorded between the assets loaded so far.
+    pub fn dependencies(&self) -> impl Deref<Target = AssetDependencies> + '_ {
+        self.dependencies.read()
+    }
+
+    /// Calls `f`, recording every asset it loads on this thread as a dependency of the asset
+    /// `name` loaded from the source `source`. The dependencies recorded for the asset before
+    /// are replaced.
+    ///
+    /// Nothing is recorded unless hot reloading is enabled.
+    ///
+    /// The `PrefabLoaderSystem` does this around `PrefabData::load_sub_assets`. Systems
+    /// loading sub-assets of their own assets should do the same, so the assets are reloaded
+    /// when their sub-assets are.
+    pub fn record_dependencies<F, R>(&self, source: &str, name: &str, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        if !self.hot_reload {
+            return f();
+        }
+        let key = AssetKey::new(source, name);
+        self.dependencies.write().clear(&key);
+        let _scope = LoadingScope::enter(key);
+        f()
+    }
+
     /// Loads an asset with a given format from the default (directory) source.
     /// If you want to load from a custom source instead, use `load_from`.
     ///
@@ -151,9 +187,16 @@ impl Loader {
         progress.add_assets(1);
         let tracker = progress.create_tracker();
 
+        let key = AssetKey::new(source, name.clone());
+        if let Some(dependent) = dependencies::loading_dependent() {
+            self.dependencies.write().add(&dependent, &key);
+        }
+
+        let source_id = key.source.clone();
         let source = self.source(source);
         let handle_clone = handle.clone();
         let processed = storage.processed.clone();
+        let graph = self.dependencies.clone();
 
         let hot_reload = if self.hot_reload {
             Some(objekt::clone_box(&format) as Box<dyn Format<A::Data>>)
@@ -166,12 +209,14 @@ impl Loader {
             profile_scope!("load_asset_from_worker");
             let data = format
                 .import(name.clone(), source, hot_reload)
+                .map(|value| dependencies::track(value, key, graph))
                 .with_context(|_| Error::Format(format_name));
             let tracker = Box::new(tracker) as Box<dyn Tracker>;
 
             processed.push(Processed::NewAsset {
                 data,
                 handle,
+                source: source_id,
                 name,
                 tracker,
             });
@@ -199,6 +244,7 @@ impl Loader {
         storage.processed.push(Processed::NewAsset {
             data: Ok(FormatValue::data(data)),
             handle: handle.clone(),
+            source: String::new(),
             name: "<Data>".into(),
             tracker,
         });
@@ -230,6 +276,7 @@ impl Loader {
                 processed.push(Processed::NewAsset {
                     data: Ok(FormatValue::data(data())),
                     handle: handle.clone(),
+                    source: String::new(),
                     name: "<Data>".into(),
                     tracker,
                 });
@@ -12,9 +12,13 @@ use crate::{
     Asset, AssetStorage, Format, Handle, Loader, Progress, ProgressCounter, SerializableFormat,
 };
 
-pub use self::system::{PrefabLoaderSystem, PrefabLoaderSystemDesc};
+pub use self::{
+    registry::{ComponentRegistry, DynamicComponents},
+    system::{PrefabLoaderSystem, PrefabLoaderSystemDesc},
+};
 
 mod impls;
+mod registry;
 mod system;
 
 /// Trait for loading a prefabs data for a single entity
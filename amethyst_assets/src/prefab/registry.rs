@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, HashMap};
+
+use log::warn;
+use ron::Value;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use amethyst_core::ecs::prelude::{Component, Entity, LazyUpdate, Read, World, WorldExt};
+use amethyst_error::{format_err, Error, ResultExt};
+
+use crate::PrefabData;
+
+type InsertFn = fn(&LazyUpdate, Entity, Value) -> Result<(), ron::de::Error>;
+
+/// Resource mapping component names to functions deserializing and inserting them.
+///
+/// Used by `DynamicComponents` to build entities from components listed by name, so new
+/// components can be used in prefabs without changing the prefab type.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut registry = ComponentRegistry::default();
+/// registry
+///     .register::<Transform>("Transform")
+///     .register::<Health>("Health");
+/// world.insert(registry);
+/// ```
+#[derive(Default)]
+pub struct ComponentRegistry {
+    components: HashMap<String, InsertFn>,
+}
+
+impl std::fmt::Debug for ComponentRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ComponentRegistry")
+            .field("components", &self.components.keys())
+            .finish()
+    }
+}
+
+impl ComponentRegistry {
+    /// Registers the component `C` under `name`, replacing any component registered under the
+    /// same name before.
+    pub fn register<C>(&mut self, name: impl Into<String>) -> &mut Self
+    where
+        C: Component + DeserializeOwned + Send + Sync,
+        C::Storage: Default,
+    {
+        self.components.insert(name.into(), insert_component::<C>);
+        self
+    }
+
+    /// Returns `true` if a component is registered under `name`.
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.components.contains_key(name)
+    }
+
+    /// Iterates over the names of all registered components.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.components.keys().map(String::as_str)
+    }
+
+    /// Deserializes the component registered under `name` from `value` and inserts it into
+    /// `entity` when the world is next maintained.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no component is registered under `name` or `value` doesn't describe
+    /// it.
+    pub fn insert(
+        &self,
+        lazy: &LazyUpdate,
+        entity: Entity,
+        name: &str,
+        value: Value,
+    ) -> Result<(), Error> {
+        let insert = self
+            .components
+            .get(name)
+            .ok_or_else(|| format_err!("No component registered as {:?}", name))?;
+        insert(lazy, entity, value)
+            .with_context(|_| format_err!("Failed deserializing component {:?}", name))
+    }
+}
+
+fn insert_component<C>(
+    lazy: &LazyUpdate,
+    entity: Entity,
+    value: Value,
+) -> Result<(), ron::de::Error>
+where
+    C: Component + DeserializeOwned + Send + Sync,
+    C::Storage: Default,
+{
+    let component = C::deserialize(value)?;
+    lazy.exec_mut(move |world: &mut World| {
+        world.register::<C>();
+        if world
+            .write_storage::<C>()
+            .insert(entity, component)
+            .is_err()
+        {
+            warn!(
+                "Cannot insert a prefab component into dead entity {:?}",
+                entity
+            );
+        }
+    });
+    Ok(())
+}
+
+/// `PrefabData` listing components by the names they are registered with in the
+/// `ComponentRegistry`.
+///
+/// Loaded from a map of names to component data, in RON for example:
+///
+/// ```ron
+/// #![enable(implicit_some)]
+/// Prefab(
+///     entities: [
+///         PrefabEntity(
+///             data: {
+///                 "Transform": (translation: (0.0, 1.0, 0.0)),
+///                 "Health": (hp: 100.0),
+///             },
+///         ),
+///     ],
+/// )
+/// ```
+///
+/// The components are inserted when the world is next maintained, after the prefab is applied.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DynamicComponents {
+    /// The data of each component, by registered name.
+    pub components: BTreeMap<String, Value>,
+}
+
+impl<'a> PrefabData<'a> for DynamicComponents {
+    type SystemData = (Read<'a, ComponentRegistry>, Read<'a, LazyUpdate>);
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (registry, lazy): &mut Self::SystemData,
+        _: &[Entity],
+        _: &[Entity],
+    ) -> Result<(), Error> {
+        for (name, value) in &self.components {
+            registry.insert(lazy, entity, name, value.clone())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst_core::ecs::prelude::{Builder, DenseVecStorage};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Health {
+        hp: f32,
+    }
+
+    impl Component for Health {
+        type Storage = DenseVecStorage<Self>;
+    }
+
+    #[test]
+    fn builds_registered_components() {
+        let mut world = World::new();
+        let mut registry = ComponentRegistry::default();
+        registry.register::<Health>("Health");
+        world.insert(registry);
+        let entity = world.create_entity().build();
+
+        let data: DynamicComponents = ron::de::from_str(r#"{ "Health": (hp: 10.0) }"#).unwrap();
+        {
+            let mut system_data =
+                world.system_data::<<DynamicComponents as PrefabData<'_>>::SystemData>();
+            data.add_to_entity(entity, &mut system_data, &[], &[])
+                .unwrap();
+        }
+        world.maintain();
+        assert_eq!(
+            world.read_storage::<Health>().get(entity),
+            Some(&Health { hp: 10.0 })
+        );
+
+        let unknown: DynamicComponents = ron::de::from_str(r#"{ "Mana": (mp: 1.0) }"#).unwrap();
+        let invalid: DynamicComponents = ron::de::from_str(r#"{ "Health": (mp: 1.0) }"#).unwrap();
+        let mut system_data =
+            world.system_data::<<DynamicComponents as PrefabData<'_>>::SystemData>();
+        assert!(unknown
+            .add_to_entity(entity, &mut system_data, &[], &[])
+            .is_err());
+        assert!(invalid
+            .add_to_entity(entity, &mut system_data, &[], &[])
+            .is_err());
+    }
+}
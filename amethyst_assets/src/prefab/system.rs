@@ -15,7 +15,7 @@ use amethyst_error::{format_err, Error, ResultExt};
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
-use crate::{AssetStorage, Completion, Handle, HotReloadStrategy, ProcessingState};
+use crate::{AssetStorage, Completion, Handle, HotReloadStrategy, Loader, ProcessingState};
 
 use super::{Prefab, PrefabData, PrefabTag};
 
@@ -82,6 +82,7 @@ where
         Read<'a, Time>,
         ReadExpect<'a, ArcThreadPool>,
         Option<Read<'a, HotReloadStrategy>>,
+        Option<Read<'a, Loader>>,
         WriteStorage<'a, Parent>,
         WriteStorage<'a, PrefabTag<T>>,
         T::SystemData,
@@ -98,21 +99,28 @@ where
             time,
             pool,
             strategy,
+            loader,
             mut parents,
             mut tags,
             mut prefab_system_data,
         ) = data;
         let strategy = strategy.as_ref().map(Deref::deref);
-        prefab_storage.process(
-            |mut d| {
+        prefab_storage.process_named(
+            |source, name, mut d| {
                 d.tag = Some(self.next_tag);
                 self.next_tag += 1;
-                if !d.loading()
-                    && !d
-                        .load_sub_assets(&mut prefab_system_data)
-                        .with_context(|_| format_err!("Failed starting sub asset loading"))?
-                {
-                    return Ok(ProcessingState::Loaded(d));
+                if !d.loading() {
+                    let mut load_sub_assets = || d.load_sub_assets(&mut prefab_system_data);
+                    let loading = match loader {
+                        Some(ref loader) => {
+                            loader.record_dependencies(source, name, load_sub_assets)
+                        }
+                        None => load_sub_assets(),
+                    }
+                    .with_context(|_| format_err!("Failed starting sub asset loading"))?;
+                    if !loading {
+                        return Ok(ProcessingState::Loaded(d));
+                    }
                 }
                 match d.progress().complete() {
                     Completion::Complete => Ok(ProcessingState::Loaded(d)),
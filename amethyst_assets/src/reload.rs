@@ -1,14 +1,26 @@
 //! Defines the `Reload` trait.
 
-use std::{sync::Arc, time::Instant};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
+use derivative::Derivative;
 use derive_new::new;
+use log::warn;
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
 
 use amethyst_core::{
     ecs::prelude::{DispatcherBuilder, Read, System, SystemData, World, Write},
     SystemBundle, SystemDesc, Time,
 };
-use amethyst_error::Error;
+use amethyst_error::{format_err, Error, ResultExt};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
@@ -89,6 +101,33 @@ impl HotReloadStrategy {
         }
     }
 
+    /// Watches `directory` for changes and reloads only the assets whose files changed, instead
+    /// of polling every asset like `every`.
+    ///
+    /// Changes are collected until no file changed for 200 milliseconds, so an editor saving a
+    /// file in several steps causes only one reload. Only assets loaded from a `Directory`
+    /// source at `directory` are reloaded; use `every` or `when_triggered` for other sources.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `directory` doesn't exist or can't be watched.
+    pub fn watch<P: AsRef<Path>>(directory: P) -> Result<Self, Error> {
+        Self::watch_with_delay(directory, Duration::from_millis(200))
+    }
+
+    /// Like `watch`, but waits for `delay` without changes before reloading.
+    pub fn watch_with_delay<P: AsRef<Path>>(directory: P, delay: Duration) -> Result<Self, Error> {
+        Ok(HotReloadStrategy {
+            inner: HotReloadStrategyInner::Watch {
+                watcher: Arc::new(Mutex::new(DirectoryWatcher::new(
+                    directory.as_ref(),
+                    delay,
+                )?)),
+                batches: Vec::new(),
+            },
+        })
+    }
+
     /// Never do any hot-reloading.
     pub fn never() -> Self {
         HotReloadStrategy {
@@ -114,9 +153,31 @@ impl HotReloadStrategy {
         match self.inner {
             HotReloadStrategyInner::Every { frame_number, .. } => frame_number == current_frame,
             HotReloadStrategyInner::Trigger { frame_number, .. } => frame_number == current_frame,
+            HotReloadStrategyInner::Watch { ref batches, .. } => batches
+                .iter()
+                .any(|batch| batch.frame_number == current_frame),
             HotReloadStrategyInner::Never => false,
         }
     }
+
+    /// Crate-internal method to check if a single asset needs to be reloaded,
+    /// given that `needs_reload` returned `true` for `current_frame`.
+    pub(crate) fn needs_reload_asset<D: 'static>(
+        &self,
+        reload: &dyn Reload<D>,
+        current_frame: u64,
+    ) -> bool {
+        match self.inner {
+            HotReloadStrategyInner::Watch { ref batches, .. } => batches
+                .iter()
+                .filter(|batch| batch.frame_number == current_frame)
+                .any(|batch| {
+                    reload.needs_reload_watched(&batch.changes)
+                        || (batch.rescan && reload.needs_reload())
+                }),
+            _ => reload.needs_reload(),
+        }
+    }
 }
 
 impl Default for HotReloadStrategy {
@@ -136,9 +197,129 @@ enum HotReloadStrategyInner {
         triggered: bool,
         frame_number: u64,
     },
+    Watch {
+        watcher: Arc<Mutex<DirectoryWatcher>>,
+        batches: Vec<ChangeBatch>,
+    },
     Never,
 }
 
+/// Receives the file changes of an asset directory from `notify`.
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct DirectoryWatcher {
+    root: PathBuf,
+    #[derivative(Debug = "ignore")]
+    events: Receiver<DebouncedEvent>,
+    #[derivative(Debug = "ignore")]
+    _watcher: RecommendedWatcher,
+}
+
+impl DirectoryWatcher {
+    fn new(directory: &Path, delay: Duration) -> Result<Self, Error> {
+        // Events are reported with canonical paths on some platforms.
+        let root = directory
+            .canonicalize()
+            .with_context(|_| format_err!("Failed to find asset directory {:?}", directory))?;
+        let (sender, events) = channel();
+        let mut watcher = notify::watcher(sender, delay)
+            .with_context(|_| format_err!("Failed to create file watcher"))?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .with_context(|_| format_err!("Failed to watch asset directory {:?}", root))?;
+
+        Ok(DirectoryWatcher {
+            root,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// Collects the changes received since the last call, to be reloaded at `frame_number`.
+    fn changes(&self, frame_number: u64) -> ChangeBatch {
+        let mut batch = ChangeBatch {
+            frame_number,
+            changes: WatchedChanges {
+                root: self.root.clone(),
+                names: HashSet::new(),
+            },
+            rescan: false,
+        };
+        for event in self.events.try_iter() {
+            match event {
+                DebouncedEvent::Create(path)
+                | DebouncedEvent::Write(path)
+                | DebouncedEvent::Rename(_, path) => {
+                    if let Some(name) = self.asset_name(&path) {
+                        batch.changes.names.insert(name);
+                    }
+                }
+                DebouncedEvent::Rescan => batch.rescan = true,
+                DebouncedEvent::Error(e, path) => {
+                    warn!("Failed watching asset {:?}: {}", path, e);
+                    batch.rescan = true;
+                }
+                _ => {}
+            }
+        }
+        batch
+    }
+
+    /// Converts an absolute path to the name the asset was loaded with.
+    fn asset_name(&self, path: &Path) -> Option<String> {
+        relative_name(&self.root, path)
+    }
+}
+
+/// Converts `path` to a `/` separated name relative to `root`.
+fn relative_name(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let components = relative
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()?;
+    Some(components.join("/"))
+}
+
+/// The files which changed in the directory watched by `HotReloadStrategy::watch`.
+#[derive(Clone, Debug)]
+pub struct WatchedChanges {
+    /// The canonical path of the watched directory.
+    root: PathBuf,
+    /// The changed files, relative to `root`.
+    names: HashSet<String>,
+}
+
+impl WatchedChanges {
+    /// Returns `true` if the file the asset `name` is loaded from by `source` changed.
+    ///
+    /// Assets with the same name loaded from other sources, like an `Archive` or a `Directory`
+    /// at another path, didn't change.
+    pub fn contains(&self, name: &str, source: &dyn Source) -> bool {
+        self.names.contains(name)
+            && source
+                .local_path(name)
+                .and_then(|path| path.canonicalize().ok())
+                .and_then(|path| relative_name(&self.root, &path))
+                .map_or(false, |relative| relative == name)
+    }
+}
+
+/// The assets to reload at a frame, for `HotReloadStrategy::watch`.
+#[derive(Clone, Debug)]
+struct ChangeBatch {
+    frame_number: u64,
+    changes: WatchedChanges,
+    /// Set if changes were missed, so all assets have to be checked.
+    rescan: bool,
+}
+
+impl ChangeBatch {
+    fn is_empty(&self) -> bool {
+        self.changes.names.is_empty() && !self.rescan
+    }
+}
+
 /// Builds a `HotReloadSystem`.
 #[derive(Debug, new)]
 pub struct HotReloadSystemDesc {
@@ -162,9 +343,13 @@ impl<'a, 'b> SystemDesc<'a, 'b, HotReloadSystem> for HotReloadSystemDesc {
 pub struct HotReloadSystem;
 
 impl<'a> System<'a> for HotReloadSystem {
-    type SystemData = (Read<'a, Time>, Write<'a, HotReloadStrategy>);
+    type SystemData = (
+        Read<'a, Time>,
+        Write<'a, HotReloadStrategy>,
+        Option<Read<'a, Loader>>,
+    );
 
-    fn run(&mut self, (time, mut strategy): Self::SystemData) {
+    fn run(&mut self, (time, mut strategy, loader): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("hot_reload_system");
 
@@ -188,6 +373,22 @@ impl<'a> System<'a> for HotReloadSystem {
                     *last = Instant::now();
                 }
             }
+            HotReloadStrategyInner::Watch {
+                ref watcher,
+                ref mut batches,
+            } => {
+                // The batch for this frame may not have been processed by all storages yet.
+                batches.retain(|batch| batch.frame_number >= time.frame_number());
+                let batch = watcher.lock().changes(time.frame_number() + 1);
+                // Assets depending on reloaded ones are reloaded in later frames, without
+                // changes of their own.
+                let dependents_pending = loader
+                    .map(|loader| loader.dependencies().has_pending_reloads())
+                    .unwrap_or(false);
+                if !batch.is_empty() || dependents_pending {
+                    batches.push(batch);
+                }
+            }
             HotReloadStrategyInner::Never => {}
         }
     }
@@ -197,6 +398,13 @@ impl<'a> System<'a> for HotReloadSystem {
 pub trait Reload<D>: ReloadClone<D> + Send + Sync + 'static {
     /// Checks if a reload is necessary.
     fn needs_reload(&self) -> bool;
+    /// Checks if a reload is necessary, given the files which changed.
+    ///
+    /// Used instead of `needs_reload` by `HotReloadStrategy::watch`. The default implementation
+    /// calls `needs_reload`, as the source of the asset is unknown.
+    fn needs_reload_watched(&self, _changes: &WatchedChanges) -> bool {
+        self.needs_reload()
+    }
     /// Returns the asset name.
     fn name(&self) -> String;
     /// Returns the format name.
@@ -266,6 +474,10 @@ impl<D: 'static> Reload<D> for SingleFile<D> {
         self.modified != 0 && (self.source.modified(&self.path).unwrap_or(0) > self.modified)
     }
 
+    fn needs_reload_watched(&self, changes: &WatchedChanges) -> bool {
+        changes.contains(&self.path, &*self.source)
+    }
+
     fn name(&self) -> String {
         self.path.clone()
     }
@@ -289,3 +501,117 @@ impl<D: 'static> Reload<D> for SingleFile<D> {
         format.import(path, source, Some(objekt::clone(&format)))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashSet, fs, path::PathBuf, time::Duration};
+
+    use amethyst_core::{
+        ecs::prelude::{RunNow, World, WorldExt},
+        Time,
+    };
+    use amethyst_error::{format_err, Error};
+
+    use crate::source::{Directory, Source};
+
+    use super::{
+        ChangeBatch, DirectoryWatcher, HotReloadStrategy, HotReloadStrategyInner, HotReloadSystem,
+        WatchedChanges,
+    };
+
+    /// Creates an empty directory unique to this process and `name`.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("amethyst_reload_{}_{}", std::process::id(), name));
+        fs::create_dir_all(dir.join("texture")).unwrap();
+        dir
+    }
+
+    struct NoFiles;
+
+    impl Source for NoFiles {
+        fn modified(&self, _path: &str) -> Result<u64, Error> {
+            Ok(0)
+        }
+
+        fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+            Err(format_err!("No file {:?}", path))
+        }
+    }
+
+    #[test]
+    fn asset_names_are_relative_to_watched_directory() {
+        let dir = temp_dir("names");
+        let watcher = DirectoryWatcher::new(&dir, Duration::from_millis(10)).unwrap();
+        let root = dir.canonicalize().unwrap();
+
+        assert_eq!(
+            watcher.asset_name(&root.join("texture").join("logo.png")),
+            Some("texture/logo.png".to_owned())
+        );
+        assert_eq!(
+            watcher.asset_name(&root.join("level.ron")),
+            Some("level.ron".to_owned())
+        );
+        assert_eq!(watcher.asset_name(&root.with_extension("ron")), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn changes_match_only_assets_from_watched_directory() {
+        let dir = temp_dir("changes");
+        let other = temp_dir("changes_other");
+        for root in &[&dir, &other] {
+            fs::write(root.join("texture").join("logo.png"), b"logo").unwrap();
+        }
+        let changes = WatchedChanges {
+            root: dir.canonicalize().unwrap(),
+            names: vec!["texture/logo.png".to_owned()].into_iter().collect(),
+        };
+
+        assert!(changes.contains("texture/logo.png", &Directory::new(&dir)));
+        assert!(!changes.contains("texture/logo.png", &Directory::new(&other)));
+        assert!(!changes.contains("texture/logo.png", &NoFiles));
+        assert!(!changes.contains("level.ron", &Directory::new(&dir)));
+
+        fs::remove_dir_all(dir).unwrap();
+        fs::remove_dir_all(other).unwrap();
+    }
+
+    #[test]
+    fn batches_are_kept_until_their_frame_passed() {
+        let dir = temp_dir("batches");
+        let mut strategy =
+            HotReloadStrategy::watch_with_delay(&dir, Duration::from_secs(60)).unwrap();
+        let root = dir.canonicalize().unwrap();
+        if let HotReloadStrategyInner::Watch {
+            ref mut batches, ..
+        } = strategy.inner
+        {
+            for frame_number in 1..4 {
+                batches.push(ChangeBatch {
+                    frame_number,
+                    changes: WatchedChanges {
+                        root: root.clone(),
+                        names: HashSet::new(),
+                    },
+                    rescan: true,
+                });
+            }
+        }
+        let mut world = World::new();
+        world.insert(strategy);
+        world.insert(Time::default());
+        world.write_resource::<Time>().increment_frame_number();
+        world.write_resource::<Time>().increment_frame_number();
+
+        HotReloadSystem.run_now(&world);
+        let strategy = world.read_resource::<HotReloadStrategy>();
+        assert!(!strategy.needs_reload(1));
+        assert!(strategy.needs_reload(2));
+        assert!(strategy.needs_reload(3));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
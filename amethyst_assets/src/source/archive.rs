@@ -0,0 +1,463 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+use amethyst_error::{format_err, Error, ResultExt};
+
+use crate::{error, source::Source};
+
+const MAGIC: &[u8; 8] = b"AMETHPAK";
+const VERSION: u32 = 1;
+/// Position of the index offset in the header, after the magic and the version.
+const INDEX_OFFSET_POSITION: u64 = 12;
+/// Size of the header, where the first entry starts.
+const HEADER_LEN: u64 = INDEX_OFFSET_POSITION + 8;
+/// Size of an index entry without its name.
+const INDEX_ENTRY_LEN: u64 = 4 + 8 * 4 + 1;
+
+/// Archive source, reading assets from a single pak file.
+///
+/// Assets are looked up with the same names as with a `Directory` source containing the
+/// packed files, so an archive can replace the asset directory of a shipped game. Archives
+/// are created with `ArchiveWriter` or the `amethyst_pack` binary.
+///
+/// The file starts with a header pointing to an index at its end, which lists the position,
+/// size, compression and modification time of every entry. Entries are stored as they are or
+/// compressed with deflate.
+///
+/// Assets are read with positioned reads, so several threads can load from the same archive at
+/// once.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use amethyst_assets::{Archive, Loader};
+///
+/// # fn main() -> Result<(), amethyst_error::Error> {
+/// # let mut loader: Loader = unimplemented!();
+/// loader.add_source("pak", Archive::open("assets.pak")?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Archive {
+    file: File,
+    index: HashMap<String, ArchiveEntry>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ArchiveEntry {
+    offset: u64,
+    stored_len: u64,
+    len: u64,
+    modified: u64,
+    compressed: bool,
+}
+
+impl Archive {
+    /// Opens the archive at `path` and reads its index.
+    pub fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        Self::read_index(path).with_context(|_| format_err!("Failed to open archive {:?}", path))
+    }
+
+    fn read_index(path: &Path) -> Result<Self, Error> {
+        let mut file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(&mut file);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(format_err!("Not an archive"));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(format_err!("Unsupported archive version {}", version));
+        }
+        let index_offset = read_u64(&mut reader)?;
+        if index_offset < HEADER_LEN || index_offset > file_len {
+            return Err(format_err!("Index offset {} out of bounds", index_offset));
+        }
+        reader.seek(SeekFrom::Start(index_offset))?;
+
+        // Bytes of the index left to read, checked before every allocation.
+        let mut remaining = file_len - index_offset;
+        let count = read_u32(&mut reader)?;
+        remaining = remaining.saturating_sub(4);
+        if u64::from(count) > remaining / INDEX_ENTRY_LEN {
+            return Err(format_err!("Index entry count {} out of bounds", count));
+        }
+        let mut index = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            let name_len = u64::from(read_u32(&mut reader)?);
+            if name_len > remaining.saturating_sub(INDEX_ENTRY_LEN) {
+                return Err(format_err!("Asset name length {} out of bounds", name_len));
+            }
+            remaining -= INDEX_ENTRY_LEN + name_len;
+            let mut name = vec![0; name_len as usize];
+            reader.read_exact(&mut name)?;
+            let name = String::from_utf8(name)?;
+            let entry = ArchiveEntry {
+                offset: read_u64(&mut reader)?,
+                stored_len: read_u64(&mut reader)?,
+                len: read_u64(&mut reader)?,
+                modified: read_u64(&mut reader)?,
+                compressed: read_u8(&mut reader)? != 0,
+            };
+            let in_bounds = entry.offset >= HEADER_LEN
+                && entry
+                    .offset
+                    .checked_add(entry.stored_len)
+                    .map_or(false, |end| end <= index_offset)
+                && (entry.compressed || entry.len == entry.stored_len);
+            if !in_bounds {
+                return Err(format_err!("Asset {:?} out of bounds", name));
+            }
+            index.insert(name, entry);
+        }
+
+        Ok(Archive { file, index })
+    }
+
+    /// Iterates over the names of all assets in the archive.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.index.keys().map(String::as_str)
+    }
+
+    fn entry(&self, path: &str) -> Result<&ArchiveEntry, Error> {
+        self.index
+            .get(path)
+            .ok_or_else(|| format_err!("No asset {:?} in archive", path))
+    }
+}
+
+impl Source for Archive {
+    fn modified(&self, path: &str) -> Result<u64, Error> {
+        Ok(self.entry(path)?.modified)
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.index.contains_key(path)
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("archive_load_asset");
+
+        let entry = *self.entry(path).with_context(|_| error::Error::Source)?;
+
+        let mut stored = vec![0; entry.stored_len as usize];
+        read_exact_at(&self.file, &mut stored, entry.offset)
+            .with_context(|_| format_err!("Failed to read {:?} from archive", path))
+            .with_context(|_| error::Error::Source)?;
+        if !entry.compressed {
+            return Ok(stored);
+        }
+
+        // The decompressed length can't be checked when opening the archive, so it isn't used
+        // to allocate. Reading one byte more than it detects entries decompressing to more.
+        let mut data = Vec::new();
+        DeflateDecoder::new(&stored[..])
+            .take(entry.len.saturating_add(1))
+            .read_to_end(&mut data)
+            .with_context(|_| format_err!("Failed to decompress {:?} from archive", path))
+            .with_context(|_| error::Error::Source)?;
+        if data.len() as u64 != entry.len {
+            return Err(format_err!(
+                "Decompressed {:?} has {} bytes instead of {}",
+                path,
+                data.len(),
+                entry.len
+            ))
+            .with_context(|_| error::Error::Source);
+        }
+        Ok(data)
+    }
+}
+
+/// Writes archives read by the `Archive` source.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::fs::File;
+///
+/// use amethyst_assets::ArchiveWriter;
+///
+/// # fn main() -> Result<(), amethyst_error::Error> {
+/// let mut writer = ArchiveWriter::new(File::create("assets.pak")?)?;
+/// writer.add_directory("assets", true)?;
+/// writer.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ArchiveWriter<W> {
+    writer: W,
+    entries: Vec<(String, ArchiveEntry)>,
+    names: HashSet<String>,
+    offset: u64,
+}
+
+impl<W> ArchiveWriter<W>
+where
+    W: Write + Seek,
+{
+    /// Starts writing an archive to `writer`.
+    pub fn new(mut writer: W) -> Result<Self, Error> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        // The index offset is filled in by `finish`.
+        writer.write_all(&0u64.to_le_bytes())?;
+
+        Ok(ArchiveWriter {
+            writer,
+            entries: Vec::new(),
+            names: HashSet::new(),
+            offset: HEADER_LEN,
+        })
+    }
+
+    /// Adds the asset `name` with the given content and modification time, in seconds since
+    /// `UNIX_EPOCH`.
+    ///
+    /// If `compress` is `true`, the content is stored compressed unless that doesn't make it
+    /// smaller, like for most image and audio files.
+    pub fn add(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        modified: u64,
+        compress: bool,
+    ) -> Result<(), Error> {
+        if !self.names.insert(name.to_owned()) {
+            return Err(format_err!("Archive already contains {:?}", name));
+        }
+
+        let compressed = if compress {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            Some(encoder.finish()?).filter(|compressed| compressed.len() < data.len())
+        } else {
+            None
+        };
+        let stored = compressed.as_ref().map_or(data, Vec::as_slice);
+        self.writer
+            .write_all(stored)
+            .with_context(|_| format_err!("Failed to write {:?} to archive", name))?;
+
+        self.entries.push((
+            name.to_owned(),
+            ArchiveEntry {
+                offset: self.offset,
+                stored_len: stored.len() as u64,
+                len: data.len() as u64,
+                modified,
+                compressed: compressed.is_some(),
+            },
+        ));
+        self.offset += stored.len() as u64;
+        Ok(())
+    }
+
+    /// Adds all files in `directory` and its subdirectories, named by their path relative to
+    /// `directory` like with a `Directory` source. Returns the number of files added.
+    pub fn add_directory<P>(&mut self, directory: P, compress: bool) -> Result<usize, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let directory = directory.as_ref();
+        let mut files = Vec::new();
+        collect_files(directory, &mut files)
+            .with_context(|_| format_err!("Failed to list files in {:?}", directory))?;
+        // Sorted so packing the same files always gives the same archive.
+        files.sort();
+
+        for path in &files {
+            let name = path
+                .strip_prefix(directory)
+                .ok()
+                .and_then(|relative| {
+                    relative
+                        .iter()
+                        .map(|component| component.to_str())
+                        .collect::<Option<Vec<_>>>()
+                })
+                .ok_or_else(|| format_err!("Invalid asset path {:?}", path))?
+                .join("/");
+            let data =
+                fs::read(path).with_context(|_| format_err!("Failed to read file {:?}", path))?;
+            let modified = fs::metadata(path)?
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            self.add(&name, &data, modified, compress)?;
+        }
+        Ok(files.len())
+    }
+
+    /// Writes the index and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W, Error> {
+        let index_offset = self.offset;
+        let writer = &mut self.writer;
+        writer.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        for (name, entry) in &self.entries {
+            writer.write_all(&(name.len() as u32).to_le_bytes())?;
+            writer.write_all(name.as_bytes())?;
+            writer.write_all(&entry.offset.to_le_bytes())?;
+            writer.write_all(&entry.stored_len.to_le_bytes())?;
+            writer.write_all(&entry.len.to_le_bytes())?;
+            writer.write_all(&entry.modified.to_le_bytes())?;
+            writer.write_all(&[entry.compressed as u8])?;
+        }
+
+        writer.seek(SeekFrom::Start(INDEX_OFFSET_POSITION))?;
+        writer.write_all(&index_offset.to_le_bytes())?;
+        writer.seek(SeekFrom::End(0))?;
+        writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn collect_files(directory: &Path, files: &mut Vec<std::path::PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> std::io::Result<()> {
+    use std::{io::ErrorKind, os::windows::fs::FileExt};
+
+    while !buf.is_empty() {
+        match file.seek_read(buf, offset) {
+            Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+            Ok(read) => {
+                buf = &mut buf[read..];
+                offset += read as u64;
+            }
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> std::io::Result<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> std::io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> std::io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs::{self, File},
+        path::Path,
+    };
+
+    use crate::source::{Directory, Source};
+
+    use super::{Archive, ArchiveWriter};
+
+    #[test]
+    fn loads_packed_assets_like_directory() {
+        let test_assets_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets");
+        let archive_path = std::env::temp_dir().join(format!(
+            "amethyst_assets_archive_{}.pak",
+            std::process::id()
+        ));
+
+        let mut writer = ArchiveWriter::new(File::create(&archive_path).unwrap()).unwrap();
+        writer.add_directory(&test_assets_dir, true).unwrap();
+        writer
+            .add("repeated", &[b'a'; 1024], 7, true)
+            .expect("Failed to add repeated");
+        assert!(writer.add("repeated", b"", 0, false).is_err());
+        writer.finish().unwrap();
+
+        let archive = Archive::open(&archive_path).expect("Failed to open archive");
+        let directory = Directory::new(test_assets_dir);
+        assert_eq!(
+            archive
+                .load("subdir/asset")
+                .expect("Failed to load subdir/asset"),
+            directory.load("subdir/asset").unwrap()
+        );
+        assert_eq!(archive.load("repeated").unwrap(), vec![b'a'; 1024]);
+        assert_eq!(archive.modified("repeated").unwrap(), 7);
+        assert!(archive.load("missing").is_err());
+
+        fs::remove_file(archive_path).unwrap();
+    }
+
+    #[test]
+    fn rejects_out_of_bounds_entries() {
+        let archive_path = std::env::temp_dir().join(format!(
+            "amethyst_assets_archive_bounds_{}.pak",
+            std::process::id()
+        ));
+        let mut writer = ArchiveWriter::new(File::create(&archive_path).unwrap()).unwrap();
+        writer.add("asset", b"data", 0, false).unwrap();
+        writer.finish().unwrap();
+        let valid = fs::read(&archive_path).unwrap();
+        // The entry offset follows the count, the name length and the name.
+        let entry_offset = 20 + 4 + 4 + "asset".len() + 4;
+
+        let mut corrupt = valid.clone();
+        corrupt[entry_offset..entry_offset + 8].copy_from_slice(&u64::max_value().to_le_bytes());
+        fs::write(&archive_path, &corrupt).unwrap();
+        assert!(Archive::open(&archive_path).is_err());
+
+        let mut corrupt = valid.clone();
+        // Index entry count.
+        corrupt[24..28].copy_from_slice(&u32::max_value().to_le_bytes());
+        fs::write(&archive_path, &corrupt).unwrap();
+        assert!(Archive::open(&archive_path).is_err());
+
+        let mut corrupt = valid;
+        // Index offset.
+        corrupt[12..20].copy_from_slice(&u64::max_value().to_le_bytes());
+        fs::write(&archive_path, &corrupt).unwrap();
+        assert!(Archive::open(&archive_path).is_err());
+
+        fs::remove_file(archive_path).unwrap();
+    }
+}
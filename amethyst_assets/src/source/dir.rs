@@ -75,6 +75,10 @@ impl Source for Directory {
 
         Ok(v)
     }
+
+    fn local_path(&self, path: &str) -> Option<PathBuf> {
+        Some(self.path(path))
+    }
 }
 
 #[cfg(test)]
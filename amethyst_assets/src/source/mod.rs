@@ -1,11 +1,19 @@
+use std::{path::PathBuf, sync::Arc};
+
 use amethyst_error::Error;
 
-pub use self::dir::Directory;
+pub use self::{
+    archive::{Archive, ArchiveWriter},
+    dir::Directory,
+    overlay::OverlaySource,
+};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
+mod archive;
 mod dir;
+mod overlay;
 
 /// A trait for asset sources, which provides
 /// methods for loading bytes.
@@ -20,6 +28,22 @@ pub trait Source: Send + Sync + 'static {
     /// The id should always use `/` as separator in paths.
     fn load(&self, path: &str) -> Result<Vec<u8>, Error>;
 
+    /// Returns `true` if the source contains the asset `path`.
+    ///
+    /// The default implementation checks if `modified` succeeds.
+    fn contains(&self, path: &str) -> bool {
+        self.modified(path).is_ok()
+    }
+
+    /// Returns the file the asset `path` is loaded from, if it is a file on the local file
+    /// system.
+    ///
+    /// Used by `HotReloadStrategy::watch` to find the assets whose files changed. The default
+    /// implementation returns `None`.
+    fn local_path(&self, _path: &str) -> Option<PathBuf> {
+        None
+    }
+
     /// Returns both the result of `load` and `modified` as a tuple.
     /// There's a default implementation which just calls both methods,
     /// but you may be able to provide a more optimized version yourself.
@@ -33,3 +57,28 @@ pub trait Source: Send + Sync + 'static {
         Ok((b, m))
     }
 }
+
+impl<S> Source for Arc<S>
+where
+    S: Source + ?Sized,
+{
+    fn modified(&self, path: &str) -> Result<u64, Error> {
+        (**self).modified(path)
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+        (**self).load(path)
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        (**self).contains(path)
+    }
+
+    fn local_path(&self, path: &str) -> Option<PathBuf> {
+        (**self).local_path(path)
+    }
+
+    fn load_with_metadata(&self, path: &str) -> Result<(Vec<u8>, u64), Error> {
+        (**self).load_with_metadata(path)
+    }
+}
@@ -0,0 +1,248 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use parking_lot::RwLock;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+use amethyst_error::{format_err, Error, ResultExt};
+
+use crate::{error, source::Source};
+
+/// Source stacking several named layers of sources, for example the base game, DLCs and mods.
+///
+/// Every asset is loaded from the layer with the highest priority containing it, so a layer
+/// can override single files of the layers below it. Layers added later have a higher
+/// priority.
+///
+/// The layer each asset was loaded from is recorded and can be queried with `served_by`. To
+/// keep access to the `OverlaySource` after adding it to the `Loader`, wrap it in an `Arc`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::sync::Arc;
+///
+/// use amethyst_assets::{Archive, Directory, Loader, OverlaySource};
+///
+/// # fn main() -> Result<(), amethyst_error::Error> {
+/// # let mut loader: Loader = unimplemented!();
+/// let overlay = Arc::new(
+///     OverlaySource::new()
+///         .with_layer("base", Archive::open("base.pak")?)
+///         .with_layer("dlc", Archive::open("dlc.pak")?)
+///         .with_layer("mods", Directory::new("mods")),
+/// );
+/// loader.set_default_source(overlay.clone());
+/// // After loading:
+/// println!("{:?}", overlay.served_by("texture/logo.png"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct OverlaySource {
+    /// Lowest priority first.
+    layers: Vec<(String, Box<dyn Source>)>,
+    served: RwLock<HashMap<String, usize>>,
+}
+
+impl std::fmt::Debug for OverlaySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OverlaySource")
+            .field("layers", &self.layers().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl OverlaySource {
+    /// Creates an `OverlaySource` without layers.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a layer with a higher priority than all layers before.
+    pub fn with_layer<N, S>(mut self, name: N, source: S) -> Self
+    where
+        N: Into<String>,
+        S: Source,
+    {
+        self.add_layer(name, source);
+        self
+    }
+
+    /// Adds a layer with a higher priority than all layers before.
+    pub fn add_layer<N, S>(&mut self, name: N, source: S)
+    where
+        N: Into<String>,
+        S: Source,
+    {
+        self.layers
+            .push((name.into(), Box::new(source) as Box<dyn Source>));
+    }
+
+    /// Iterates over the names of the layers, highest priority first.
+    pub fn layers(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().rev().map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the name of the layer `path` would be loaded from, if any layer contains it.
+    pub fn resolve(&self, path: &str) -> Option<&str> {
+        self.find(path).map(|index| self.layers[index].0.as_str())
+    }
+
+    /// Returns the name of the layer `path` was last loaded from.
+    pub fn served_by(&self, path: &str) -> Option<&str> {
+        let index = *self.served.read().get(path)?;
+        Some(self.layers[index].0.as_str())
+    }
+
+    /// Returns every asset loaded so far with the name of the layer it was loaded from.
+    pub fn served(&self) -> Vec<(String, &str)> {
+        let mut served = self
+            .served
+            .read()
+            .iter()
+            .map(|(path, &index)| (path.clone(), self.layers[index].0.as_str()))
+            .collect::<Vec<_>>();
+        served.sort();
+        served
+    }
+
+    fn find(&self, path: &str) -> Option<usize> {
+        self.layers
+            .iter()
+            .rposition(|(_, source)| source.contains(path))
+    }
+
+    /// Loads `path` from the layer serving it, recording the layer only if loading succeeds.
+    fn load_from_layer<F, T>(&self, path: &str, load: F) -> Result<T, Error>
+    where
+        F: FnOnce(&dyn Source) -> Result<T, Error>,
+    {
+        let index = self
+            .find(path)
+            .ok_or_else(|| format_err!("No layer contains asset {:?}", path))
+            .with_context(|_| error::Error::Source)?;
+        let loaded = load(&*self.layers[index].1)?;
+        self.served.write().insert(path.to_owned(), index);
+        Ok(loaded)
+    }
+}
+
+impl Source for OverlaySource {
+    fn modified(&self, path: &str) -> Result<u64, Error> {
+        let index = self
+            .find(path)
+            .ok_or_else(|| format_err!("No layer contains asset {:?}", path))?;
+        self.layers[index].1.modified(path)
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("overlay_load_asset");
+
+        self.load_from_layer(path, |source| source.load(path))
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.find(path).is_some()
+    }
+
+    fn local_path(&self, path: &str) -> Option<PathBuf> {
+        self.layers[self.find(path)?].1.local_path(path)
+    }
+
+    fn load_with_metadata(&self, path: &str) -> Result<(Vec<u8>, u64), Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("overlay_load_asset_with_metadata");
+
+        self.load_from_layer(path, |source| source.load_with_metadata(path))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use amethyst_error::{format_err, Error};
+
+    use crate::source::Source;
+
+    use super::OverlaySource;
+
+    struct Files(HashMap<&'static str, &'static str>);
+
+    impl Files {
+        fn new(files: &[(&'static str, &'static str)]) -> Self {
+            Files(files.iter().cloned().collect())
+        }
+    }
+
+    impl Source for Files {
+        fn modified(&self, path: &str) -> Result<u64, Error> {
+            self.0
+                .get(path)
+                .map(|_| 0)
+                .ok_or_else(|| format_err!("Missing {:?}", path))
+        }
+
+        fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+            self.0
+                .get(path)
+                .map(|data| data.as_bytes().to_vec())
+                .ok_or_else(|| format_err!("Missing {:?}", path))
+        }
+    }
+
+    /// Contains every asset, but fails loading them.
+    struct Broken;
+
+    impl Source for Broken {
+        fn modified(&self, _path: &str) -> Result<u64, Error> {
+            Ok(0)
+        }
+
+        fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+            Err(format_err!("Broken {:?}", path))
+        }
+    }
+
+    #[test]
+    fn loads_from_highest_layer_containing_asset() {
+        let overlay = OverlaySource::new()
+            .with_layer(
+                "base",
+                Files::new(&[("logo.png", "base"), ("level.ron", "base")]),
+            )
+            .with_layer("mod", Files::new(&[("logo.png", "mod")]));
+
+        assert_eq!(overlay.layers().collect::<Vec<_>>(), vec!["mod", "base"]);
+        assert_eq!(overlay.resolve("level.ron"), Some("base"));
+        assert_eq!(overlay.served_by("logo.png"), None);
+
+        assert_eq!(overlay.load("logo.png").unwrap(), b"mod");
+        assert_eq!(overlay.load("level.ron").unwrap(), b"base");
+        assert!(overlay.load("missing.png").is_err());
+        assert_eq!(overlay.served_by("logo.png"), Some("mod"));
+        assert_eq!(
+            overlay.served(),
+            vec![
+                ("level.ron".to_owned(), "base"),
+                ("logo.png".to_owned(), "mod")
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_load_is_not_served() {
+        let mut overlay =
+            OverlaySource::new().with_layer("base", Files::new(&[("logo.png", "base")]));
+        assert_eq!(overlay.load("logo.png").unwrap(), b"base");
+
+        overlay.add_layer("broken", Broken);
+        assert!(overlay.load("logo.png").is_err());
+        assert!(overlay.load_with_metadata("level.ron").is_err());
+        assert_eq!(overlay.served_by("logo.png"), Some("base"));
+        assert_eq!(overlay.served_by("level.ron"), None);
+    }
+}
@@ -13,7 +13,7 @@ use rayon::ThreadPool;
 
 use amethyst_core::{
     ecs::{
-        hibitset::BitSet,
+        hibitset::{BitSet, BitSetLike},
         prelude::{Component, Read, ReadExpect, System, SystemData, VecStorage, World, Write},
         storage::UnprotectedStorage,
     },
@@ -49,10 +49,13 @@ impl Allocator {
 pub struct AssetStorage<A: Asset> {
     assets: VecStorage<(A, u32)>,
     bitset: BitSet,
+    /// The number of assets in `bitset`, which is expensive to count.
+    len: usize,
     handles: Vec<Handle<A>>,
     handle_alloc: Allocator,
     pub(crate) processed: Arc<SegQueue<Processed<A>>>,
-    reloads: Vec<(WeakHandle<A>, Box<dyn Reload<A::Data>>)>,
+    /// Reload objects with the id of the source their asset was loaded from.
+    reloads: Vec<(WeakHandle<A>, String, Box<dyn Reload<A::Data>>)>,
     unused_handles: SegQueue<Handle<A>>,
 }
 
@@ -93,6 +96,7 @@ impl<A: Asset> AssetStorage<A> {
     pub fn unload_all(&mut self) {
         unsafe { self.assets.clean(&self.bitset) }
         self.bitset.clear();
+        self.len = 0;
     }
 
     /// When cloning an asset handle, you'll get another handle,
@@ -108,7 +112,9 @@ impl<A: Asset> AssetStorage<A> {
             let h = self.allocate();
 
             let id = h.id();
-            self.bitset.add(id);
+            if !self.bitset.add(id) {
+                self.len += 1;
+            }
             self.handles.push(h.clone());
 
             unsafe {
@@ -178,7 +184,9 @@ impl<A: Asset> AssetStorage<A> {
     pub fn insert(&mut self, asset: A) -> Handle<A> {
         let handle = self.allocate();
         let id = handle.id();
-        self.bitset.add(id);
+        if !self.bitset.add(id) {
+            self.len += 1;
+        }
         self.handles.push(handle.clone());
         unsafe {
             self.assets.insert(id, (asset, 0));
@@ -196,6 +204,16 @@ impl<A: Asset> AssetStorage<A> {
         self.bitset.contains(id)
     }
 
+    /// Returns the number of assets in the storage.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the storage contains no assets.
+    pub fn is_empty(&self) -> bool {
+        self.bitset.is_empty()
+    }
+
     /// Get an asset by it's handle id without checking the internal bitset.
     /// Use `contains_id` to manually check it's status before access.
     ///
@@ -234,33 +252,63 @@ impl<A: Asset> AssetStorage<A> {
     pub fn process_custom_drop<F, D>(
         &mut self,
         mut f: F,
-        mut drop_fn: D,
+        drop_fn: D,
         frame_number: u64,
         pool: &ThreadPool,
         strategy: Option<&HotReloadStrategy>,
     ) where
         D: FnMut(A),
         F: FnMut(A::Data) -> Result<ProcessingState<A>, Error>,
+    {
+        self.process_named_custom_drop(|_, _, data| f(data), drop_fn, frame_number, pool, strategy);
+    }
+
+    /// Process finished asset data and maintain the storage, passing the id of the source and
+    /// the name each asset was loaded with to `f`.
+    pub(crate) fn process_named<F>(
+        &mut self,
+        f: F,
+        frame_number: u64,
+        pool: &ThreadPool,
+        strategy: Option<&HotReloadStrategy>,
+    ) where
+        F: FnMut(&str, &str, A::Data) -> Result<ProcessingState<A>, Error>,
+    {
+        self.process_named_custom_drop(f, |_| {}, frame_number, pool, strategy);
+    }
+
+    fn process_named_custom_drop<F, D>(
+        &mut self,
+        mut f: F,
+        mut drop_fn: D,
+        frame_number: u64,
+        pool: &ThreadPool,
+        strategy: Option<&HotReloadStrategy>,
+    ) where
+        D: FnMut(A),
+        F: FnMut(&str, &str, A::Data) -> Result<ProcessingState<A>, Error>,
     {
         {
             let mut requeue = Vec::new();
             while let Ok(processed) = self.processed.pop() {
                 let assets = &mut self.assets;
                 let bitset = &mut self.bitset;
+                let len = &mut self.len;
                 let handles = &mut self.handles;
                 let reloads = &mut self.reloads;
 
                 let f = &mut f;
-                let (reload_obj, handle) = match processed {
+                let (reload_obj, handle, source) = match processed {
                     Processed::NewAsset {
                         data,
                         handle,
+                        source,
                         name,
                         tracker,
                     } => {
                         let (asset, reload_obj) = match data
                             .map(|FormatValue { data, reload }| (data, reload))
-                            .and_then(|(d, rel)| f(d).map(|a| (a, rel)))
+                            .and_then(|(d, rel)| f(&source, &name, d).map(|a| (a, rel)))
                             .with_context(|_| error::Error::Asset(name.clone()))
                         {
                             Ok((ProcessingState::Loaded(x), r)) => {
@@ -300,6 +348,7 @@ impl<A: Asset> AssetStorage<A> {
                                 requeue.push(Processed::NewAsset {
                                     data: Ok(FormatValue { data: x, reload: r }),
                                     handle,
+                                    source,
                                     name,
                                     tracker,
                                 });
@@ -320,7 +369,9 @@ impl<A: Asset> AssetStorage<A> {
                         };
 
                         let id = handle.id();
-                        bitset.add(id);
+                        if !bitset.add(id) {
+                            *len += 1;
+                        }
                         handles.push(handle.clone());
 
                         // NOTE: the loader has to ensure that a handle will be used
@@ -329,17 +380,18 @@ impl<A: Asset> AssetStorage<A> {
                             assets.insert(id, (asset, 0));
                         }
 
-                        (reload_obj, handle)
+                        (reload_obj, handle, source)
                     }
                     Processed::HotReload {
                         data,
                         handle,
+                        source,
                         name,
                         old_reload,
                     } => {
                         let (asset, reload_obj) = match data
                             .map(|FormatValue { data, reload }| (data, reload))
-                            .and_then(|(d, rel)| f(d).map(|a| (a, rel)))
+                            .and_then(|(d, rel)| f(&source, &name, d).map(|a| (a, rel)))
                             .with_context(|_| error::Error::Asset(name.clone()))
                         {
                             Ok((ProcessingState::Loaded(x), r)) => (x, r),
@@ -353,6 +405,7 @@ impl<A: Asset> AssetStorage<A> {
                                 requeue.push(Processed::HotReload {
                                     data: Ok(FormatValue { data: x, reload: r }),
                                     handle,
+                                    source,
                                     name,
                                     old_reload,
                                 });
@@ -368,7 +421,7 @@ impl<A: Asset> AssetStorage<A> {
                                     e,
                                 );
 
-                                reloads.push((handle.downgrade(), old_reload));
+                                reloads.push((handle.downgrade(), source, old_reload));
 
                                 continue;
                             }
@@ -384,13 +437,13 @@ impl<A: Asset> AssetStorage<A> {
                         data.1 += 1;
                         drop_fn(std::mem::replace(&mut data.0, asset));
 
-                        (reload_obj, handle)
+                        (reload_obj, handle, source)
                     }
                 };
 
                 // Add the reload obj if it is `Some`.
                 if let Some(reload_obj) = reload_obj {
-                    reloads.push((handle.downgrade(), reload_obj));
+                    reloads.push((handle.downgrade(), source, reload_obj));
                 }
             }
 
@@ -412,7 +465,9 @@ impl<A: Asset> AssetStorage<A> {
                 let (asset, _) = self.assets.remove(id);
                 drop_fn(asset);
             }
-            self.bitset.remove(id);
+            if self.bitset.remove(id) {
+                self.len -= 1;
+            }
 
             // Can't reuse old handle here, because otherwise weak handles would still be valid.
             // TODO: maybe just store u32?
@@ -423,25 +478,25 @@ impl<A: Asset> AssetStorage<A> {
         }
         if count != 0 {
             debug!("{:?}: Freed {} handle ids", A::NAME, count,);
+            // Dropping the reload objects also forgets the dependencies of the assets.
+            self.reloads.retain(|&(ref handle, ..)| !handle.is_dead());
         }
 
-        if strategy
-            .map(|s| s.needs_reload(frame_number))
-            .unwrap_or(false)
-        {
+        if let Some(strategy) = strategy.filter(|s| s.needs_reload(frame_number)) {
             trace!("{:?}: Testing for asset reloads..", A::NAME);
-            self.hot_reload(pool);
+            self.hot_reload(pool, strategy, frame_number);
         }
     }
 
-    fn hot_reload(&mut self, pool: &ThreadPool) {
-        self.reloads.retain(|&(ref handle, _)| !handle.is_dead());
+    fn hot_reload(&mut self, pool: &ThreadPool, strategy: &HotReloadStrategy, frame_number: u64) {
+        self.reloads.retain(|&(ref handle, ..)| !handle.is_dead());
         while let Some(p) = self
             .reloads
             .iter()
-            .position(|&(_, ref rel)| rel.needs_reload())
+            .position(|&(_, _, ref rel)| strategy.needs_reload_asset(&**rel, frame_number))
         {
-            let (handle, rel): (WeakHandle<_>, Box<dyn Reload<_>>) = self.reloads.swap_remove(p);
+            let (handle, source, rel): (WeakHandle<_>, String, Box<dyn Reload<_>>) =
+                self.reloads.swap_remove(p);
 
             let name = rel.name();
             let format = rel.format();
@@ -463,6 +518,7 @@ impl<A: Asset> AssetStorage<A> {
 
                     let p = Processed::HotReload {
                         data,
+                        source,
                         name,
                         handle,
                         old_reload,
@@ -479,6 +535,7 @@ impl<A: Asset> Default for AssetStorage<A> {
         AssetStorage {
             assets: Default::default(),
             bitset: Default::default(),
+            len: 0,
             handles: Default::default(),
             handle_alloc: Default::default(),
             processed: Arc::new(SegQueue::new()),
@@ -550,6 +607,59 @@ where
             strategy.as_ref().map(Deref::deref),
         );
     }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        world
+            .entry::<AssetCounts>()
+            .or_insert_with(AssetCounts::default)
+            .register::<A>();
+    }
+}
+
+/// Counts the assets of every `AssetStorage` a `Processor` was set up for.
+///
+/// Used for diagnostics, like crash reports.
+#[derive(Default)]
+pub struct AssetCounts {
+    counters: Vec<(&'static str, fn(&World) -> Option<usize>)>,
+}
+
+impl AssetCounts {
+    /// Counts the assets in `AssetStorage<A>` as well. Registering a type again has no effect.
+    pub fn register<A: Asset>(&mut self) {
+        let name = std::any::type_name::<A>();
+        if self
+            .counters
+            .iter()
+            .all(|(registered, _)| *registered != name)
+        {
+            self.counters.push((name, count_assets::<A>));
+        }
+    }
+
+    /// Returns the type name of every registered asset with the number of assets in its
+    /// storage, or `None` if the storage is missing from `world`.
+    pub fn counts(&self, world: &World) -> Vec<(&'static str, Option<usize>)> {
+        self.counters
+            .iter()
+            .map(|(name, count)| (*name, count(world)))
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for AssetCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.counters.iter().map(|(name, _)| name))
+            .finish()
+    }
+}
+
+fn count_assets<A: Asset>(world: &World) -> Option<usize> {
+    world
+        .try_fetch::<AssetStorage<A>>()
+        .map(|storage| storage.len())
 }
 
 /// A handle to an asset. This is usually what the
@@ -602,12 +712,15 @@ pub(crate) enum Processed<A: Asset> {
     NewAsset {
         data: Result<FormatValue<A::Data>, Error>,
         handle: Handle<A>,
+        /// Id of the source the asset was loaded from, empty for the default source and data.
+        source: String,
         name: String,
         tracker: Box<dyn Tracker>,
     },
     HotReload {
         data: Result<FormatValue<A::Data>, Error>,
         handle: Handle<A>,
+        source: String,
         name: String,
         old_reload: Box<dyn Reload<A::Data>>,
     },
@@ -0,0 +1,602 @@
+//! Configuration merged from several layers.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use log::debug;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{overlay, ConfigError, ConfigFormat};
+
+/// Prefix of the environment variables read by `LayeredConfig::with_env`.
+const ENV_PREFIX: &str = "AMETHYST_";
+
+/// Loads a configuration by merging several layers on top of each other.
+///
+/// Layers are applied in the order they are added, on top of the defaults. Each layer is merged
+/// field by field, so a layer only needs to contain the values it changes. Typically the layers
+/// are the shipped file, a user override file, environment variables and command line
+/// arguments:
+///
+/// ```rust,no_run
+/// # use serde_derive::{Deserialize, Serialize};
+/// use amethyst_config::LayeredConfig;
+///
+/// #[derive(Default, Deserialize, Serialize)]
+/// struct WindowConfig {
+///     width: u32,
+///     height: u32,
+/// }
+///
+/// #[derive(Default, Deserialize, Serialize)]
+/// struct GameConfig {
+///     window: WindowConfig,
+///     difficulty: String,
+/// }
+///
+/// // `AMETHYST_WINDOW__WIDTH=800` or `--set window.width=800` both set `window.width`.
+/// let config: GameConfig = LayeredConfig::new()
+///     .with_file("config/game.ron")
+///     .with_optional_file("user/game.toml")
+///     .with_env()
+///     .with_args(std::env::args())
+///     .load()
+///     .expect("Failed loading config");
+/// ```
+///
+/// Errors are reported as `ConfigError::Layer`, naming the layer and, where possible, the key
+/// path that failed.
+///
+/// Layers are merged as JSON values, which the configuration is deserialized from at the end.
+/// RON layers are read as the configuration type on top of the layers below, so only their
+/// structs are merged field by field: maps, sequences and enum variants in a RON layer replace
+/// the values below as a whole.
+#[derive(Debug)]
+pub struct LayeredConfig<T> {
+    defaults: T,
+    layers: Vec<Layer>,
+}
+
+#[derive(Debug)]
+enum Layer {
+    File {
+        path: PathBuf,
+        required: bool,
+    },
+    Overrides {
+        name: &'static str,
+        entries: Vec<(String, String)>,
+        strict: bool,
+    },
+    Invalid {
+        name: &'static str,
+        error: ConfigError,
+    },
+}
+
+impl<T> LayeredConfig<T>
+where
+    T: Default,
+{
+    /// Creates a new layered configuration starting from `T::default()`.
+    pub fn new() -> Self {
+        LayeredConfig::with_defaults(T::default())
+    }
+}
+
+impl<T> LayeredConfig<T> {
+    /// Creates a new layered configuration starting from `defaults`.
+    pub fn with_defaults(defaults: T) -> Self {
+        LayeredConfig {
+            defaults,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Adds a file layer. Loading fails if the file does not exist.
+    ///
+    /// The format is selected by the file extension, see `ConfigFormat`.
+    pub fn with_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.layers.push(Layer::File {
+            path: path.into(),
+            required: true,
+        });
+        self
+    }
+
+    /// Adds a file layer that is skipped if the file does not exist, e.g. user overrides.
+    pub fn with_optional_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.layers.push(Layer::File {
+            path: path.into(),
+            required: false,
+        });
+        self
+    }
+
+    /// Adds a layer from environment variables starting with `AMETHYST_`.
+    ///
+    /// See `with_env_prefix`.
+    pub fn with_env(self) -> Self {
+        self.with_env_prefix(ENV_PREFIX)
+    }
+
+    /// Adds a layer from environment variables starting with `prefix`.
+    ///
+    /// The rest of the variable name is lowercased and split at double underscores, so with the
+    /// prefix `AMETHYST_` the variable `AMETHYST_WINDOW__WIDTH` sets `window.width`. Variables
+    /// which don't match a key of the configuration are ignored.
+    ///
+    /// The environment is read when this method is called.
+    pub fn with_env_prefix(self, prefix: &str) -> Self {
+        self.with_vars(prefix, std::env::vars())
+    }
+
+    fn with_vars<I>(mut self, prefix: &str, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let entries = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(prefix) && name.len() > prefix.len())
+            .map(|(name, value)| {
+                (
+                    name[prefix.len()..].to_lowercase().replace("__", "."),
+                    value,
+                )
+            })
+            .collect();
+        self.layers.push(Layer::Overrides {
+            name: "environment",
+            entries,
+            strict: false,
+        });
+        self
+    }
+
+    /// Adds a layer from `--set key.path=value` command line arguments.
+    ///
+    /// Both `--set key=value` and `--set=key=value` are accepted, all other arguments are
+    /// ignored. Unlike environment variables, unknown keys are an error.
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        match parse_args(args) {
+            Ok(entries) => self.layers.push(Layer::Overrides {
+                name: "command line",
+                entries,
+                strict: true,
+            }),
+            Err(error) => self.layers.push(Layer::Invalid {
+                name: "command line",
+                error,
+            }),
+        }
+        self
+    }
+}
+
+impl<T> LayeredConfig<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Merges all layers and deserializes the result.
+    pub fn load(self) -> Result<T, ConfigError> {
+        let mut tree = serde_json::to_value(&self.defaults)
+            .map_err(|e| layer_error("defaults", None, e.into()))?;
+
+        for layer in self.layers {
+            match layer {
+                Layer::File { path, required } => {
+                    if let Some(bytes) = read_file(&path, required)? {
+                        apply_file::<T>(&mut tree, &path, &bytes)?;
+                    }
+                }
+                Layer::Overrides {
+                    name,
+                    entries,
+                    strict,
+                } => {
+                    for (key, raw) in entries {
+                        apply_override::<T>(&mut tree, name, &key, &raw, strict)?;
+                    }
+                }
+                Layer::Invalid { name, error } => return Err(layer_error(name, None, error)),
+            }
+        }
+
+        serde_json::from_value(tree).map_err(|e| layer_error("merged", None, e.into()))
+    }
+}
+
+fn layer_error<L: ToString>(layer: L, key: Option<&str>, error: ConfigError) -> ConfigError {
+    ConfigError::Layer {
+        layer: layer.to_string(),
+        key: key.map(str::to_owned),
+        error: Box::new(error),
+    }
+}
+
+fn parse_args<I, S>(args: I) -> Result<Vec<(String, String)>, ConfigError>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let mut args = args.into_iter().map(Into::<String>::into);
+    let mut entries = Vec::new();
+    while let Some(arg) = args.next() {
+        let assignment = if arg == "--set" {
+            args.next().ok_or_else(|| {
+                ConfigError::Override("`--set` must be followed by `key=value`".to_string())
+            })?
+        } else if arg.starts_with("--set=") {
+            arg["--set=".len()..].to_string()
+        } else {
+            continue;
+        };
+        let mut parts = assignment.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) if !key.is_empty() => {
+                entries.push((key.to_string(), value.to_string()))
+            }
+            _ => {
+                return Err(ConfigError::Override(format!(
+                    "expected `key=value`, got `{}`",
+                    assignment
+                )))
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn read_file(path: &Path, required: bool) -> Result<Option<Vec<u8>>, ConfigError> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(ref e) if !required && e.kind() == io::ErrorKind::NotFound => {
+            debug!("Skipping missing config layer {}", path.display());
+            Ok(None)
+        }
+        Err(e) => Err(layer_error(path.display(), None, e.into())),
+    }
+}
+
+fn apply_file<T>(tree: &mut Value, path: &Path, bytes: &[u8]) -> Result<(), ConfigError>
+where
+    T: Serialize + DeserializeOwned,
+{
+    match ConfigFormat::from_path(path).map_err(|e| layer_error(path.display(), None, e))? {
+        // RON needs the configuration type to tell its values apart, e.g. enum variants from
+        // structs, so the layer is read as `T` on top of the tree.
+        ConfigFormat::Ron => {
+            let config =
+                read_ron::<T>(bytes, tree).map_err(|e| layer_error(path.display(), None, e))?;
+            *tree = serde_json::to_value(&config)
+                .map_err(|e| layer_error(path.display(), None, e.into()))?;
+            Ok(())
+        }
+        format => {
+            let layer = format
+                .deserialize(bytes)
+                .map_err(|e| layer_error(path.display(), None, e))?;
+            merge_file::<T>(tree, layer, path)
+        }
+    }
+}
+
+fn read_ron<T: DeserializeOwned>(bytes: &[u8], base: &Value) -> Result<T, ConfigError> {
+    let mut deserializer = ron::de::Deserializer::from_bytes(bytes)?;
+    let config = overlay::deserialize(&mut deserializer, base)?;
+    deserializer.end()?;
+    Ok(config)
+}
+
+fn validate<T: DeserializeOwned>(tree: &Value) -> Result<(), ConfigError> {
+    serde_json::from_value::<T>(tree.clone())
+        .map(drop)
+        .map_err(ConfigError::from)
+}
+
+fn merge_file<T: DeserializeOwned>(
+    tree: &mut Value,
+    layer: Value,
+    path: &Path,
+) -> Result<(), ConfigError> {
+    let mut merged = tree.clone();
+    merge(&mut merged, layer.clone());
+    let error = match validate::<T>(&merged) {
+        Ok(()) => {
+            *tree = merged;
+            return Ok(());
+        }
+        Err(e) => e,
+    };
+
+    // Apply the values one by one to find the key which breaks the configuration.
+    let mut partial = tree.clone();
+    let mut leaves = Vec::new();
+    collect_leaves(layer, &mut Vec::new(), &mut leaves);
+    for (key, value) in leaves {
+        let key = key.join(".");
+        if let Some(slot) = lookup(&mut partial, &key, true) {
+            *slot = value;
+        }
+        if let Err(e) = validate::<T>(&partial) {
+            return Err(layer_error(path.display(), Some(&key), e));
+        }
+    }
+    Err(layer_error(path.display(), None, error))
+}
+
+fn apply_override<T: DeserializeOwned>(
+    tree: &mut Value,
+    name: &str,
+    key: &str,
+    raw: &str,
+    strict: bool,
+) -> Result<(), ConfigError> {
+    let mut candidate = tree.clone();
+    let slot = match lookup(&mut candidate, key, false) {
+        Some(slot) => slot,
+        None if strict => {
+            return Err(layer_error(
+                name,
+                Some(key),
+                ConfigError::Override("unknown key".to_string()),
+            ))
+        }
+        None => {
+            debug!("Ignoring unknown config key `{}` from {}", key, name);
+            return Ok(());
+        }
+    };
+
+    // Values are parsed as JSON, so `800`, `true` and `[1, 2]` keep their type. Anything else,
+    // or a value that doesn't fit the field, is used as a string.
+    let parsed = serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+    let is_string = parsed.is_string();
+    *slot = parsed;
+    let mut result = validate::<T>(&candidate);
+    if result.is_err() && !is_string {
+        if let Some(slot) = lookup(&mut candidate, key, false) {
+            *slot = Value::String(raw.to_string());
+        }
+        result = validate::<T>(&candidate);
+    }
+
+    match result {
+        Ok(()) => {
+            *tree = candidate;
+            Ok(())
+        }
+        Err(e) => Err(layer_error(name, Some(key), e)),
+    }
+}
+
+/// Merges `layer` into `base`, recursing into objects present in both.
+fn merge(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Object(base), Value::Object(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+fn collect_leaves(value: Value, path: &mut Vec<String>, leaves: &mut Vec<(Vec<String>, Value)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                path.push(key);
+                collect_leaves(value, path, leaves);
+                path.pop();
+            }
+        }
+        value => leaves.push((path.clone(), value)),
+    }
+}
+
+/// Finds the value at the dotted key path. Array elements are addressed by index.
+///
+/// With `create`, missing object keys are inserted and non-container values are replaced by
+/// objects on the way.
+fn lookup<'v>(tree: &'v mut Value, key: &str, create: bool) -> Option<&'v mut Value> {
+    if key.is_empty() {
+        return Some(tree);
+    }
+    key.split('.').try_fold(tree, |current, segment| {
+        if create && !current.is_object() && !current.is_array() {
+            *current = Value::Object(Map::new());
+        }
+        match current {
+            Value::Array(items) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(move |index| items.get_mut(index)),
+            Value::Object(map) => {
+                if create {
+                    Some(map.entry(segment).or_insert(Value::Null))
+                } else {
+                    map.get_mut(segment)
+                }
+            }
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_derive::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+    struct Window {
+        width: u32,
+        height: u32,
+        title: String,
+    }
+
+    #[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+    struct Game {
+        window: Window,
+        volume: f32,
+    }
+
+    fn write_temp(name: &str, content: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("amethyst_layered_{}_{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn layers_merge_field_by_field() {
+        let shipped = write_temp("shipped.ron", "(window: (width: 800, height: 600))");
+        let user = write_temp("user.toml", "volume = 0.5\n[window]\ntitle = \"Game\"\n");
+
+        let config: Game = LayeredConfig::new()
+            .with_file(&shipped)
+            .with_optional_file(&user)
+            .with_optional_file(std::env::temp_dir().join("amethyst_layered_missing.yaml"))
+            .with_vars(
+                "AMETHYST_",
+                vec![
+                    ("AMETHYST_WINDOW__HEIGHT".to_string(), "720".to_string()),
+                    ("AMETHYST_NUM_THREADS".to_string(), "4".to_string()),
+                    ("PATH".to_string(), "/bin".to_string()),
+                ],
+            )
+            .with_args(vec![
+                "game",
+                "--set",
+                "window.width=1280",
+                "--set=window.title=true",
+            ])
+            .load()
+            .unwrap();
+
+        assert_eq!(
+            config,
+            Game {
+                window: Window {
+                    width: 1280,
+                    height: 720,
+                    title: "true".to_string(),
+                },
+                volume: 0.5,
+            }
+        );
+
+        fs::remove_file(shipped).unwrap();
+        fs::remove_file(user).unwrap();
+    }
+
+    #[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+    struct Bindings {
+        scale: Option<f32>,
+        keys: std::collections::BTreeMap<u32, String>,
+    }
+
+    #[test]
+    fn ron_options_and_map_keys() {
+        let shipped = write_temp(
+            "bindings.ron",
+            "(scale: Some(1.5), keys: { 1: \"jump\", 2: \"run\" })",
+        );
+        let config: Bindings = LayeredConfig::new().with_file(&shipped).load().unwrap();
+        assert_eq!(config.scale, Some(1.5));
+        assert_eq!(config.keys[&1], "jump");
+        assert_eq!(config.keys[&2], "run");
+        fs::remove_file(shipped).unwrap();
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    enum Output {
+        Off,
+        Colored,
+        File(String),
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Logging {
+        output: Output,
+        seed: u64,
+        window: Window,
+    }
+
+    #[test]
+    fn ron_layers_are_typed() {
+        let shipped = write_temp(
+            "logging.json",
+            r#"{"output": {"File": "game.log"}, "seed": 1, "window": {"width": 800}}"#,
+        );
+        let user = write_temp(
+            "logging.ron",
+            "(output: Colored, seed: 9007199254740993, window: (height: 600))",
+        );
+        let config = LayeredConfig::with_defaults(Logging {
+            output: Output::Off,
+            seed: 0,
+            window: Window::default(),
+        })
+        .with_file(&shipped)
+        .with_file(&user)
+        .load()
+        .unwrap();
+
+        assert_eq!(
+            config,
+            Logging {
+                output: Output::Colored,
+                seed: 9_007_199_254_740_993,
+                window: Window {
+                    width: 800,
+                    height: 600,
+                    title: String::new(),
+                },
+            }
+        );
+
+        fs::remove_file(shipped).unwrap();
+        fs::remove_file(user).unwrap();
+    }
+
+    #[test]
+    fn errors_name_layer_and_key() {
+        let shipped = write_temp("broken.json", r#"{"window": {"width": "wide"}}"#);
+        let error = LayeredConfig::<Game>::new()
+            .with_file(&shipped)
+            .load()
+            .unwrap_err();
+        match error {
+            ConfigError::Layer { layer, key, .. } => {
+                assert_eq!(layer, shipped.display().to_string());
+                assert_eq!(key.as_ref().map(String::as_str), Some("window.width"));
+            }
+            e => panic!("Unexpected error: {}", e),
+        }
+        fs::remove_file(shipped).unwrap();
+
+        let error = LayeredConfig::<Game>::new()
+            .with_args(vec!["--set", "window.depth=3"])
+            .load()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "command line: `window.depth`: Invalid override: unknown key"
+        );
+    }
+}
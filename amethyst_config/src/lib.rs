@@ -1,4 +1,7 @@
-//! Loads RON files into a structure for easy / statically typed usage.
+//! Loads RON, JSON, TOML and YAML files into a structure for easy / statically typed usage.
+//!
+//! `LayeredConfig` additionally merges defaults, files, environment variables and command line
+//! overrides.
 
 #![crate_name = "amethyst_config"]
 #![warn(
@@ -17,7 +20,12 @@ use std::{
 };
 
 use ron::{self, de::Error as DeError, ser::Error as SerError};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+pub use crate::layered::LayeredConfig;
+
+mod layered;
+mod overlay;
 
 /// Error related to anything that manages/creates configurations as well as
 /// "workspace"-related things.
@@ -29,8 +37,27 @@ pub enum ConfigError {
     Parser(DeError),
     /// Occurs if a value is ill-formed during serialization (like a poisoned mutex).
     Serializer(SerError),
+    /// Errors while parsing or serializing JSON, also used for merging configuration layers.
+    Json(serde_json::Error),
+    /// Errors while parsing TOML.
+    TomlParser(toml::de::Error),
+    /// Errors while serializing TOML.
+    TomlSerializer(toml::ser::Error),
+    /// Errors while parsing or serializing YAML.
+    Yaml(serde_yaml::Error),
     /// Related to the path of the file.
     Extension(PathBuf),
+    /// A malformed override, e.g. a `--set` argument without `=`.
+    Override(String),
+    /// An error in one layer of a `LayeredConfig`.
+    Layer {
+        /// Name of the layer, e.g. the file path or "environment".
+        layer: String,
+        /// The key path that failed, e.g. `window.width`, if it is known.
+        key: Option<String>,
+        /// The underlying error.
+        error: Box<ConfigError>,
+    },
 }
 
 impl fmt::Display for ConfigError {
@@ -39,6 +66,10 @@ impl fmt::Display for ConfigError {
             ConfigError::File(ref err) => write!(f, "{}", err),
             ConfigError::Parser(ref msg) => write!(f, "{}", msg),
             ConfigError::Serializer(ref msg) => write!(f, "{}", msg),
+            ConfigError::Json(ref err) => write!(f, "{}", err),
+            ConfigError::TomlParser(ref err) => write!(f, "{}", err),
+            ConfigError::TomlSerializer(ref err) => write!(f, "{}", err),
+            ConfigError::Yaml(ref err) => write!(f, "{}", err),
             ConfigError::Extension(ref path) => {
                 let found = match path.extension() {
                     Some(extension) => format!("{:?}", extension),
@@ -47,11 +78,21 @@ impl fmt::Display for ConfigError {
 
                 write!(
                     f,
-                    "{}: Invalid path extension, expected \"ron\", got {}.",
+                    "{}: Invalid path extension, expected \"ron\", \"json\", \"toml\", \"yaml\" \
+                     or \"yml\", got {}.",
                     path.display().to_string(),
                     found,
                 )
             }
+            ConfigError::Override(ref msg) => write!(f, "Invalid override: {}", msg),
+            ConfigError::Layer {
+                ref layer,
+                ref key,
+                ref error,
+            } => match key {
+                Some(key) => write!(f, "{}: `{}`: {}", layer, key, error),
+                None => write!(f, "{}: {}", layer, error),
+            },
         }
     }
 }
@@ -74,30 +115,115 @@ impl From<SerError> for ConfigError {
     }
 }
 
+impl From<serde_json::Error> for ConfigError {
+    fn from(e: serde_json::Error) -> Self {
+        ConfigError::Json(e)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> Self {
+        ConfigError::TomlParser(e)
+    }
+}
+
+impl From<toml::ser::Error> for ConfigError {
+    fn from(e: toml::ser::Error) -> Self {
+        ConfigError::TomlSerializer(e)
+    }
+}
+
+impl From<serde_yaml::Error> for ConfigError {
+    fn from(e: serde_yaml::Error) -> Self {
+        ConfigError::Yaml(e)
+    }
+}
+
 impl Error for ConfigError {
     fn description(&self) -> &str {
         match *self {
             ConfigError::File(_) => "Project file error",
             ConfigError::Parser(_) => "Project parser error",
             ConfigError::Serializer(_) => "Project serializer error",
+            ConfigError::Json(_) => "Project JSON error",
+            ConfigError::TomlParser(_) => "Project TOML parser error",
+            ConfigError::TomlSerializer(_) => "Project TOML serializer error",
+            ConfigError::Yaml(_) => "Project YAML error",
             ConfigError::Extension(_) => "Invalid extension or directory for a file",
+            ConfigError::Override(_) => "Invalid configuration override",
+            ConfigError::Layer { .. } => "Configuration layer error",
         }
     }
 
     fn cause(&self) -> Option<&dyn Error> {
         match *self {
             ConfigError::File(ref err) => Some(err),
+            ConfigError::Layer { ref error, .. } => Some(&**error),
             _ => None,
         }
     }
 }
 
+/// File formats configuration can be stored in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// Rusty Object Notation, files ending in `.ron`.
+    Ron,
+    /// JSON, files ending in `.json`.
+    Json,
+    /// TOML, files ending in `.toml`.
+    Toml,
+    /// YAML, files ending in `.yaml` or `.yml`.
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Selects the format by the extension of `path`.
+    pub fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        match path.extension().and_then(std::ffi::OsStr::to_str) {
+            Some("ron") => Ok(ConfigFormat::Ron),
+            Some("json") => Ok(ConfigFormat::Json),
+            Some("toml") => Ok(ConfigFormat::Toml),
+            Some("yaml") | Some("yml") => Ok(ConfigFormat::Yaml),
+            _ => Err(ConfigError::Extension(path.to_path_buf())),
+        }
+    }
+
+    /// Deserializes a value in this format.
+    pub fn deserialize<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, ConfigError> {
+        match self {
+            ConfigFormat::Ron => {
+                let mut de = ron::de::Deserializer::from_bytes(bytes)?;
+                let val = T::deserialize(&mut de)?;
+                de.end()?;
+
+                Ok(val)
+            }
+            ConfigFormat::Json => Ok(serde_json::from_slice(bytes)?),
+            ConfigFormat::Toml => Ok(toml::from_slice(bytes)?),
+            ConfigFormat::Yaml => Ok(serde_yaml::from_slice(bytes)?),
+        }
+    }
+
+    /// Serializes a value in this format.
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String, ConfigError> {
+        match self {
+            ConfigFormat::Ron => Ok(ron::ser::to_string_pretty(value, Default::default())?),
+            ConfigFormat::Json => Ok(serde_json::to_string_pretty(value)?),
+            ConfigFormat::Toml => Ok(toml::to_string_pretty(value)?),
+            ConfigFormat::Yaml => Ok(serde_yaml::to_string(value)?),
+        }
+    }
+}
+
 /// Trait implemented by the `config!` macro.
 pub trait Config
 where
     Self: Sized,
 {
     /// Loads a configuration structure from a file.
+    ///
+    /// The format is selected by the file extension, see `ConfigFormat`.
     fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError>;
 
     /// Loads a configuration structure from a file.
@@ -106,10 +232,12 @@ where
         Self::load(path)
     }
 
-    /// Loads configuration structure from raw bytes.
+    /// Loads configuration structure from raw RON bytes.
     fn load_bytes(bytes: &[u8]) -> Result<Self, ConfigError>;
 
     /// Writes a configuration structure to a file.
+    ///
+    /// The format is selected by the file extension, see `ConfigFormat`.
     fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError>;
 }
 
@@ -118,38 +246,24 @@ where
     T: for<'a> Deserialize<'a> + Serialize,
 {
     fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
-        use std::{fs::File, io::Read};
-
         let path = path.as_ref();
+        let format = ConfigFormat::from_path(path)?;
+        let content = std::fs::read(path)?;
 
-        let content = {
-            let mut file = File::open(path)?;
-            let mut buffer = Vec::new();
-            file.read_to_end(&mut buffer)?;
-
-            buffer
-        };
-
-        if path.extension().and_then(std::ffi::OsStr::to_str) == Some("ron") {
-            Self::load_bytes(&content)
-        } else {
-            Err(ConfigError::Extension(path.to_path_buf()))
-        }
+        format.deserialize(&content)
     }
 
     fn load_bytes(bytes: &[u8]) -> Result<Self, ConfigError> {
-        let mut de = ron::de::Deserializer::from_bytes(bytes)?;
-        let val = T::deserialize(&mut de)?;
-        de.end()?;
-
-        Ok(val)
+        ConfigFormat::Ron.deserialize(bytes)
     }
 
     fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
-        use ron::ser::to_string_pretty;
         use std::{fs::File, io::Write};
 
-        let s = to_string_pretty(self, Default::default())?;
+        let path = path.as_ref();
+        // Unknown extensions have always been written as RON.
+        let format = ConfigFormat::from_path(path).unwrap_or(ConfigFormat::Ron);
+        let s = format.serialize(self)?;
         File::create(path)?.write_all(s.as_bytes())?;
 
         Ok(())
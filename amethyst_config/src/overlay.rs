@@ -0,0 +1,406 @@
+//! Deserializes a partial layer on top of the configuration merged so far.
+//!
+//! The layer is read with the configuration type, so enums, options and integers keep their
+//! meaning. Struct fields the layer leaves out are taken from the values below it.
+
+use std::fmt;
+
+use serde::de::{
+    self, value::StrDeserializer, Deserialize, DeserializeSeed, Deserializer, IntoDeserializer,
+    MapAccess, SeqAccess, Visitor,
+};
+use serde_json::{Map, Value};
+
+/// Deserializes `T` from `deserializer`, filling in missing struct fields from `base`.
+///
+/// Only structs are merged field by field, all other values in the layer are used as they are.
+pub(crate) fn deserialize<'de, D, T>(deserializer: D, base: &Value) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(Overlay {
+        inner: deserializer,
+        base: Some(base),
+    })
+}
+
+macro_rules! forward {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.inner.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+macro_rules! forward_all_but {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        forward! {
+            deserialize_any();
+            deserialize_bool();
+            deserialize_i8();
+            deserialize_i16();
+            deserialize_i32();
+            deserialize_i64();
+            deserialize_u8();
+            deserialize_u16();
+            deserialize_u32();
+            deserialize_u64();
+            deserialize_f32();
+            deserialize_f64();
+            deserialize_char();
+            deserialize_str();
+            deserialize_string();
+            deserialize_bytes();
+            deserialize_byte_buf();
+            deserialize_unit();
+            deserialize_unit_struct(name: &'static str);
+            deserialize_newtype_struct(name: &'static str);
+            deserialize_seq();
+            deserialize_tuple(len: usize);
+            deserialize_tuple_struct(name: &'static str, len: usize);
+            deserialize_map();
+            deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+            deserialize_ignored_any();
+            $($method($($arg: $ty),*);)*
+        }
+    };
+}
+
+/// A deserializer which takes missing struct fields from `base`.
+struct Overlay<'b, D> {
+    inner: D,
+    base: Option<&'b Value>,
+}
+
+impl<'de, 'b, D> Deserializer<'de> for Overlay<'b, D>
+where
+    D: Deserializer<'de>,
+{
+    type Error = D::Error;
+
+    forward_all_but! {
+        deserialize_identifier();
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.inner.deserialize_option(OptionVisitor {
+            visitor,
+            base: self.base.filter(|base| !base.is_null()),
+        })
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.inner.deserialize_struct(
+            name,
+            fields,
+            StructVisitor {
+                visitor,
+                base: self.base.and_then(Value::as_object),
+                fields,
+            },
+        )
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+struct OptionVisitor<'b, V> {
+    visitor: V,
+    base: Option<&'b Value>,
+}
+
+impl<'de, 'b, V> Visitor<'de> for OptionVisitor<'b, V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.visitor.expecting(formatter)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<V::Value, E> {
+        self.visitor.visit_none()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<V::Value, E> {
+        self.visitor.visit_unit()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<V::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.visitor.visit_some(Overlay {
+            inner: deserializer,
+            base: self.base,
+        })
+    }
+}
+
+struct StructVisitor<'b, V> {
+    visitor: V,
+    base: Option<&'b Map<String, Value>>,
+    fields: &'static [&'static str],
+}
+
+impl<'de, 'b, V> Visitor<'de> for StructVisitor<'b, V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.visitor.expecting(formatter)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<V::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.visitor.visit_seq(seq)
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<V::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        self.visitor.visit_map(StructAccess {
+            inner: map,
+            base: self.base,
+            fields: self.fields,
+            seen: Vec::new(),
+            current: None,
+            layer_done: false,
+            next_field: 0,
+            fill: None,
+        })
+    }
+}
+
+/// Yields the fields of the layer, followed by the fields only `base` has.
+struct StructAccess<'b, A> {
+    inner: A,
+    base: Option<&'b Map<String, Value>>,
+    fields: &'static [&'static str],
+    seen: Vec<String>,
+    /// The base value of the field whose value the layer yields next.
+    current: Option<&'b Value>,
+    layer_done: bool,
+    next_field: usize,
+    /// The base value yielded next, once the layer is done.
+    fill: Option<&'b Value>,
+}
+
+impl<'de, 'b, A> MapAccess<'de> for StructAccess<'b, A>
+where
+    A: MapAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, A::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let mut capture = KeyCapture {
+            seed: Some(seed),
+            name: None,
+        };
+        if !self.layer_done {
+            if let Some(key) = self.inner.next_key_seed(&mut capture)? {
+                self.current = match capture.name {
+                    Some(name) => {
+                        let current = self.base.and_then(|base| base.get(&name));
+                        self.seen.push(name);
+                        current
+                    }
+                    None => None,
+                };
+                return Ok(Some(key));
+            }
+            self.layer_done = true;
+        }
+
+        let base = match self.base {
+            Some(base) => base,
+            None => return Ok(None),
+        };
+        let seed = capture
+            .seed
+            .ok_or_else(|| de::Error::custom("struct key was consumed at the end of the map"))?;
+        while let Some(&field) = self.fields.get(self.next_field) {
+            self.next_field += 1;
+            if self.seen.iter().any(|seen| seen == field) {
+                continue;
+            }
+            if let Some(value) = base.get(field) {
+                self.fill = Some(value);
+                let key: StrDeserializer<'_, A::Error> = field.into_deserializer();
+                return seed.deserialize(key).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<S>(&mut self, seed: S) -> Result<S::Value, A::Error>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        match self.fill.take() {
+            Some(value) => seed.deserialize(value.clone()).map_err(de::Error::custom),
+            None => self.inner.next_value_seed(ValueSeed {
+                seed,
+                base: self.current.take(),
+            }),
+        }
+    }
+}
+
+/// Deserializes a key, remembering its name. The seed is kept if the map has no more keys.
+struct KeyCapture<K> {
+    seed: Option<K>,
+    name: Option<String>,
+}
+
+impl<'de, K> DeserializeSeed<'de> for &mut KeyCapture<K>
+where
+    K: DeserializeSeed<'de>,
+{
+    type Value = K::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<K::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let seed = self
+            .seed
+            .take()
+            .ok_or_else(|| de::Error::custom("struct key was deserialized twice"))?;
+        seed.deserialize(KeyName {
+            inner: deserializer,
+            name: &mut self.name,
+        })
+    }
+}
+
+struct KeyName<'n, D> {
+    inner: D,
+    name: &'n mut Option<String>,
+}
+
+impl<'de, 'n, D> Deserializer<'de> for KeyName<'n, D>
+where
+    D: Deserializer<'de>,
+{
+    type Error = D::Error;
+
+    forward_all_but! {
+        deserialize_option();
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.inner.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.inner.deserialize_identifier(NameVisitor {
+            visitor,
+            name: self.name,
+        })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+struct NameVisitor<'n, V> {
+    visitor: V,
+    name: &'n mut Option<String>,
+}
+
+impl<'de, 'n, V> Visitor<'de> for NameVisitor<'n, V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.visitor.expecting(formatter)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<V::Value, E> {
+        self.visitor.visit_u64(value)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<V::Value, E> {
+        *self.name = Some(value.to_owned());
+        self.visitor.visit_str(value)
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, value: &'de str) -> Result<V::Value, E> {
+        *self.name = Some(value.to_owned());
+        self.visitor.visit_borrowed_str(value)
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<V::Value, E> {
+        *self.name = Some(value.clone());
+        self.visitor.visit_string(value)
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<V::Value, E> {
+        self.visitor.visit_bytes(value)
+    }
+}
+
+struct ValueSeed<'b, S> {
+    seed: S,
+    base: Option<&'b Value>,
+}
+
+impl<'de, 'b, S> DeserializeSeed<'de> for ValueSeed<'b, S>
+where
+    S: DeserializeSeed<'de>,
+{
+    type Value = S::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<S::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.seed.deserialize(Overlay {
+            inner: deserializer,
+            base: self.base,
+        })
+    }
+}
@@ -11,4 +11,21 @@ pub trait SystemBundle<'a, 'b> {
         world: &mut World,
         dispatcher: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error>;
+
+    /// Like `build`, but also gets the builder of the fixed update dispatcher, so systems which
+    /// need a constant time step, like physics, can be added there.
+    ///
+    /// `GameDataBuilder::with_bundle` calls this instead of `build`. By default all systems are
+    /// added to `dispatcher` by `build`.
+    fn build_with_fixed(
+        self,
+        world: &mut World,
+        dispatcher: &mut DispatcherBuilder<'a, 'b>,
+        _fixed_dispatcher: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error>
+    where
+        Self: Sized,
+    {
+        self.build(world, dispatcher)
+    }
 }
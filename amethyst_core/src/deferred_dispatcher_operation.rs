@@ -19,6 +19,19 @@ pub trait DispatcherOperation<'a, 'b> {
         world: &mut World,
         dispatcher_builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error>;
+
+    /// Executes the dispatcher builder instruction, with access to the builder of the fixed
+    /// update dispatcher.
+    ///
+    /// Only bundles use `fixed_dispatcher_builder`, by default this calls `exec`.
+    fn exec_with_fixed(
+        self: Box<Self>,
+        world: &mut World,
+        dispatcher_builder: &mut DispatcherBuilder<'a, 'b>,
+        _fixed_dispatcher_builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        self.exec(world, dispatcher_builder)
+    }
 }
 
 /// Deferred operation Add Barrier
@@ -175,4 +188,14 @@ where
         self.bundle.build(world, dispatcher_builder)?;
         Ok(())
     }
+
+    fn exec_with_fixed(
+        self: Box<Self>,
+        world: &mut World,
+        dispatcher_builder: &mut DispatcherBuilder<'a, 'b>,
+        fixed_dispatcher_builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        self.bundle
+            .build_with_fixed(world, dispatcher_builder, fixed_dispatcher_builder)
+    }
 }
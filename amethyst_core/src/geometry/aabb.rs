@@ -0,0 +1,222 @@
+use nalgebra::{convert, zero, Matrix4, Point3, RealField, Vector3, U3};
+
+use super::{Intersects, Ray, RayHit, Transformable};
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Aabb<T>
+where
+    T: RealField,
+{
+    /// The corner with the smallest coordinates.
+    pub min: Point3<T>,
+    /// The corner with the largest coordinates.
+    pub max: Point3<T>,
+}
+
+impl<T> Aabb<T>
+where
+    T: RealField,
+{
+    /// Create a new `Aabb` spanning the two opposite corners `a` and `b`.
+    pub fn new(a: Point3<T>, b: Point3<T>) -> Self {
+        Aabb {
+            min: min_point(&a, &b),
+            max: max_point(&a, &b),
+        }
+    }
+
+    /// Create a new `Aabb` from its center and the distances from the center to its faces.
+    pub fn from_center_half_extents(center: Point3<T>, half_extents: Vector3<T>) -> Self {
+        Self::new(center - half_extents, center + half_extents)
+    }
+
+    /// Create the smallest `Aabb` containing all `points`, or `None` if there are none.
+    pub fn from_points<'a, I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a Point3<T>>,
+        T: 'a,
+    {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self::new(*first, *first), |aabb, point| {
+            aabb.merged_point(point)
+        }))
+    }
+
+    /// The center of this `Aabb`.
+    pub fn center(&self) -> Point3<T> {
+        nalgebra::center(&self.min, &self.max)
+    }
+
+    /// The distances from the center to the faces of this `Aabb`.
+    pub fn half_extents(&self) -> Vector3<T> {
+        self.size() * convert::<f64, T>(0.5)
+    }
+
+    /// The size of this `Aabb` along each axis.
+    pub fn size(&self) -> Vector3<T> {
+        self.max - self.min
+    }
+
+    /// The eight corners of this `Aabb`.
+    pub fn corners(&self) -> [Point3<T>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Point3::new(min.x, min.y, min.z),
+            Point3::new(max.x, min.y, min.z),
+            Point3::new(min.x, max.y, min.z),
+            Point3::new(max.x, max.y, min.z),
+            Point3::new(min.x, min.y, max.z),
+            Point3::new(max.x, min.y, max.z),
+            Point3::new(min.x, max.y, max.z),
+            Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// Returns `true` if `point` is inside or on the surface of this `Aabb`.
+    pub fn contains_point(&self, point: &Point3<T>) -> bool {
+        (0..3).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+
+    /// Returns `true` if `other` lies completely inside this `Aabb`.
+    pub fn contains_aabb(&self, other: &Aabb<T>) -> bool {
+        self.contains_point(&other.min) && self.contains_point(&other.max)
+    }
+
+    /// Returns the point inside this `Aabb` closest to `point`.
+    pub fn closest_point(&self, point: &Point3<T>) -> Point3<T> {
+        min_point(&max_point(point, &self.min), &self.max)
+    }
+
+    /// Returns the smallest `Aabb` containing this one and `other`.
+    pub fn merged(&self, other: &Aabb<T>) -> Self {
+        self.merged_point(&other.min).merged_point(&other.max)
+    }
+
+    /// Returns the smallest `Aabb` containing this one and `point`.
+    pub fn merged_point(&self, point: &Point3<T>) -> Self {
+        Aabb {
+            min: min_point(&self.min, point),
+            max: max_point(&self.max, point),
+        }
+    }
+
+    /// Returns where `ray` enters this `Aabb`, or `None` if it misses.
+    pub fn intersect_ray(&self, ray: &Ray<T>) -> Option<RayHit<T>> {
+        let mut enter = zero::<T>();
+        let mut exit = T::max_value();
+        // The axis and side of the face the ray enters through.
+        let mut enter_face = None;
+        for i in 0..3 {
+            let (origin, direction) = (ray.origin[i], ray.direction[i]);
+            if direction == zero() {
+                if origin < self.min[i] || origin > self.max[i] {
+                    return None;
+                }
+                continue;
+            }
+            let mut near = (self.min[i] - origin) / direction;
+            let mut far = (self.max[i] - origin) / direction;
+            let mut side = -T::one();
+            if near > far {
+                std::mem::swap(&mut near, &mut far);
+                side = T::one();
+            }
+            if near > enter {
+                enter = near;
+                enter_face = Some((i, side));
+            }
+            exit = exit.min(far);
+            if enter > exit {
+                return None;
+            }
+        }
+        Some(match enter_face {
+            Some((axis, side)) => {
+                let mut normal = Vector3::zeros();
+                normal[axis] = side;
+                RayHit::new(ray, enter, normal)
+            }
+            None => RayHit::inside(ray),
+        })
+    }
+}
+
+/// The componentwise minimum of `a` and `b`.
+fn min_point<T: RealField>(a: &Point3<T>, b: &Point3<T>) -> Point3<T> {
+    Point3::from(a.coords.zip_map(&b.coords, |a, b| a.min(b)))
+}
+
+/// The componentwise maximum of `a` and `b`.
+fn max_point<T: RealField>(a: &Point3<T>, b: &Point3<T>) -> Point3<T> {
+    Point3::from(a.coords.zip_map(&b.coords, |a, b| a.max(b)))
+}
+
+impl<T> Intersects<Aabb<T>> for Aabb<T>
+where
+    T: RealField,
+{
+    fn intersects(&self, other: &Aabb<T>) -> bool {
+        (0..3).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+}
+
+impl<T> Transformable<T> for Aabb<T>
+where
+    T: RealField,
+{
+    fn transformed(&self, matrix: &Matrix4<T>) -> Self {
+        let center = matrix.transform_point(&self.center());
+        let linear = matrix.fixed_slice::<U3, U3>(0, 0).map(|x| x.abs());
+        Self::from_center_half_extents(center, linear * self.half_extents())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::*;
+
+    use super::*;
+    use crate::math::UnitQuaternion;
+
+    #[test]
+    fn ray_hits_nearest_face() {
+        let aabb = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let ray = Ray {
+            origin: Point3::new(-5.0, 0.5, 0.0),
+            direction: Vector3::x(),
+        };
+        let hit = aabb.intersect_ray(&ray).unwrap();
+        assert_relative_eq!(hit.distance, 4.0);
+        assert_relative_eq!(hit.point, Point3::new(-1.0, 0.5, 0.0));
+        assert_relative_eq!(hit.normal, -Vector3::x());
+
+        let away = Ray {
+            origin: Point3::new(-5.0, 0.5, 0.0),
+            direction: -Vector3::x(),
+        };
+        assert!(aabb.intersect_ray(&away).is_none());
+        let inside = Ray {
+            origin: Point3::origin(),
+            direction: Vector3::y(),
+        };
+        assert_relative_eq!(aabb.intersect_ray(&inside).unwrap().distance, 0.0);
+    }
+
+    #[test]
+    fn transformed_bounds_rotated_box() {
+        let aabb = Aabb::new(Point3::new(-1.0, -2.0, -1.0), Point3::new(1.0, 2.0, 1.0));
+        let rotation = UnitQuaternion::from_euler_angles(0.0, 0.0, std::f32::consts::FRAC_PI_2);
+        let matrix = rotation
+            .to_homogeneous()
+            .append_translation(&Vector3::new(10.0, 0.0, 0.0));
+        let moved = aabb.transformed(&matrix);
+        assert_relative_eq!(moved.min, Point3::new(8.0, -1.0, -1.0), epsilon = 0.000_01);
+        assert_relative_eq!(moved.max, Point3::new(12.0, 1.0, 1.0), epsilon = 0.000_01);
+
+        let merged = aabb.merged(&moved);
+        assert!(merged.contains_aabb(&aabb) && merged.contains_aabb(&moved));
+        assert!(!aabb.intersects(&moved));
+    }
+}
@@ -0,0 +1,235 @@
+use nalgebra::{convert, one, zero, Matrix4, Point3, RealField, Vector3};
+
+use super::{
+    closest_point_on_segment, max_scale, Aabb, Intersects, Obb, Ray, RayHit, Sphere, Transformable,
+};
+
+/// A capsule, the set of points within `radius` of the segment from `start` to `end`.
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Capsule<T>
+where
+    T: RealField,
+{
+    /// The center of one cap.
+    pub start: Point3<T>,
+    /// The center of the other cap.
+    pub end: Point3<T>,
+    /// The radius of the capsule.
+    pub radius: T,
+}
+
+impl<T> Capsule<T>
+where
+    T: RealField,
+{
+    /// Create a new `Capsule`.
+    pub fn new(start: Point3<T>, end: Point3<T>, radius: T) -> Self {
+        Capsule { start, end, radius }
+    }
+
+    /// The smallest `Aabb` containing this `Capsule`.
+    pub fn bounding_aabb(&self) -> Aabb<T> {
+        let radius = Vector3::repeat(self.radius);
+        let aabb = Aabb::new(self.start, self.end);
+        Aabb::new(aabb.min - radius, aabb.max + radius)
+    }
+
+    /// Returns the point on the center segment closest to `point`.
+    pub fn closest_point_on_axis(&self, point: &Point3<T>) -> Point3<T> {
+        closest_point_on_segment(point, &self.start, &self.end)
+    }
+
+    /// Returns `true` if `point` is inside or on the surface of this `Capsule`.
+    pub fn contains_point(&self, point: &Point3<T>) -> bool {
+        (point - self.closest_point_on_axis(point)).norm_squared() <= self.radius * self.radius
+    }
+
+    /// Returns where `ray` enters this `Capsule`, or `None` if it misses.
+    pub fn intersect_ray(&self, ray: &Ray<T>) -> Option<RayHit<T>> {
+        if self.contains_point(&ray.origin) {
+            return Some(RayHit::inside(ray));
+        }
+        let caps = [
+            Sphere::new(self.start, self.radius).intersect_ray(ray),
+            Sphere::new(self.end, self.radius).intersect_ray(ray),
+        ];
+        caps.iter()
+            .cloned()
+            .chain(std::iter::once(self.intersect_ray_body(ray)))
+            .flatten()
+            .min_by(|a, b| {
+                a.distance
+                    .partial_cmp(&b.distance)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+
+    /// Intersects `ray` with the side of the cylinder between the caps.
+    fn intersect_ray_body(&self, ray: &Ray<T>) -> Option<RayHit<T>> {
+        let axis = self.end - self.start;
+        let offset = ray.origin - self.start;
+        let (dd, nd, md) = (
+            axis.norm_squared(),
+            ray.direction.dot(&axis),
+            offset.dot(&axis),
+        );
+        let a = dd * ray.direction.norm_squared() - nd * nd;
+        if a == zero() {
+            // Parallel to the axis, only the caps can be hit.
+            return None;
+        }
+        let b = dd * offset.dot(&ray.direction) - nd * md;
+        let c = dd * (offset.norm_squared() - self.radius * self.radius) - md * md;
+        let discriminant = b * b - a * c;
+        if discriminant < zero() {
+            return None;
+        }
+        let distance = (-b - discriminant.sqrt()) / a;
+        let along_axis = md + distance * nd;
+        if distance < zero() || along_axis < zero() || along_axis > dd {
+            return None;
+        }
+        let hit = RayHit::new(ray, distance, Vector3::zeros());
+        let center = self.start + axis * (along_axis / dd);
+        Some(RayHit {
+            normal: (hit.point - center).normalize(),
+            ..hit
+        })
+    }
+
+    /// Smallest distance from the center segment to a convex shape, given the closest point of
+    /// the shape to any point.
+    ///
+    /// The distance is convex along the segment, so a ternary search finds the minimum.
+    fn axis_distance_squared<F>(&self, closest_point: F) -> T
+    where
+        F: Fn(&Point3<T>) -> Point3<T>,
+    {
+        let axis = self.end - self.start;
+        let distance = |t: T| {
+            let point = self.start + axis * t;
+            (closest_point(&point) - point).norm_squared()
+        };
+        let third = convert::<f64, T>(1.0 / 3.0);
+        let (mut low, mut high) = (zero::<T>(), one::<T>());
+        for _ in 0..32 {
+            let a = low + (high - low) * third;
+            let b = high - (high - low) * third;
+            if distance(a) <= distance(b) {
+                high = b;
+            } else {
+                low = a;
+            }
+        }
+        distance((low + high) * convert::<f64, T>(0.5))
+    }
+}
+
+impl<T> Intersects<Capsule<T>> for Capsule<T>
+where
+    T: RealField,
+{
+    fn intersects(&self, other: &Capsule<T>) -> bool {
+        let radius = self.radius + other.radius;
+        let distance = self.axis_distance_squared(|point| other.closest_point_on_axis(point));
+        distance <= radius * radius
+    }
+}
+
+impl<T> Intersects<Sphere<T>> for Capsule<T>
+where
+    T: RealField,
+{
+    fn intersects(&self, other: &Sphere<T>) -> bool {
+        let radius = self.radius + other.radius;
+        (other.center - self.closest_point_on_axis(&other.center)).norm_squared() <= radius * radius
+    }
+}
+
+symmetric_intersects!(Capsule, Sphere);
+
+impl<T> Intersects<Aabb<T>> for Capsule<T>
+where
+    T: RealField,
+{
+    fn intersects(&self, other: &Aabb<T>) -> bool {
+        self.axis_distance_squared(|point| other.closest_point(point)) <= self.radius * self.radius
+    }
+}
+
+symmetric_intersects!(Capsule, Aabb);
+
+impl<T> Intersects<Obb<T>> for Capsule<T>
+where
+    T: RealField,
+{
+    fn intersects(&self, other: &Obb<T>) -> bool {
+        self.axis_distance_squared(|point| other.closest_point(point)) <= self.radius * self.radius
+    }
+}
+
+symmetric_intersects!(Capsule, Obb);
+
+impl<T> Transformable<T> for Capsule<T>
+where
+    T: RealField,
+{
+    fn transformed(&self, matrix: &Matrix4<T>) -> Self {
+        Capsule::new(
+            matrix.transform_point(&self.start),
+            matrix.transform_point(&self.end),
+            self.radius * max_scale(matrix),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::*;
+
+    use super::*;
+
+    #[test]
+    fn ray_hits_body_and_caps() {
+        let capsule = Capsule::new(Point3::new(0.0, -1.0, 0.0), Point3::new(0.0, 1.0, 0.0), 0.5);
+        let side = Ray {
+            origin: Point3::new(-5.0, 0.5, 0.0),
+            direction: Vector3::x(),
+        };
+        let hit = capsule.intersect_ray(&side).unwrap();
+        assert_relative_eq!(hit.distance, 4.5, epsilon = 0.000_01);
+        assert_relative_eq!(hit.normal, -Vector3::x(), epsilon = 0.000_01);
+
+        let top = Ray {
+            origin: Point3::new(0.0, 5.0, 0.0),
+            direction: -Vector3::y(),
+        };
+        let hit = capsule.intersect_ray(&top).unwrap();
+        assert_relative_eq!(hit.distance, 3.5, epsilon = 0.000_01);
+        assert_relative_eq!(hit.normal, Vector3::y(), epsilon = 0.000_01);
+
+        let miss = Ray {
+            origin: Point3::new(-5.0, 1.6, 0.0),
+            direction: Vector3::x(),
+        };
+        assert!(capsule.intersect_ray(&miss).is_none());
+    }
+
+    #[test]
+    fn overlaps() {
+        let capsule = Capsule::new(Point3::new(0.0, -1.0, 0.0), Point3::new(0.0, 1.0, 0.0), 0.5);
+        let crossing = Capsule::new(Point3::new(-2.0, 0.0, 0.9), Point3::new(2.0, 0.0, 0.9), 0.5);
+        assert!(capsule.intersects(&crossing));
+        let apart = Capsule {
+            radius: 0.3,
+            ..crossing
+        };
+        assert!(!capsule.intersects(&apart));
+
+        let aabb = Aabb::new(Point3::new(0.4, 0.9, -1.0), Point3::new(2.0, 2.0, 1.0));
+        assert!(capsule.intersects(&aabb));
+        let aabb = Aabb::new(Point3::new(0.4, 1.4, -1.0), Point3::new(2.0, 2.0, 1.0));
+        assert!(!capsule.intersects(&aabb));
+        assert!(capsule.intersects(&Sphere::new(Point3::new(0.0, 2.0, 0.0), 0.6)));
+    }
+}
@@ -0,0 +1,207 @@
+use nalgebra::{zero, Matrix4, Point3, RealField, Vector3, Vector4};
+
+use super::{Aabb, Intersects, Obb, Plane, Ray, RayHit, Sphere};
+use crate::transform::Transform;
+
+/// A view frustum, the space a camera can see.
+///
+/// Stored as six planes with normals pointing into the frustum, so a point is inside a plane when
+/// `Plane::dot_point` is not negative.
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Frustum<T>
+where
+    T: RealField,
+{
+    planes: [Plane<T>; 6],
+}
+
+impl<T> Frustum<T>
+where
+    T: RealField,
+{
+    /// Create a new `Frustum` from six planes with normals pointing inwards.
+    pub fn new(planes: [Plane<T>; 6]) -> Self {
+        Frustum { planes }
+    }
+
+    /// Extracts the `Frustum` from a combined projection and view matrix.
+    ///
+    /// The projection is expected to map depth to `0.0..=1.0`, like the projections of
+    /// `amethyst_rendy` cameras.
+    pub fn from_matrix(matrix: &Matrix4<T>) -> Self {
+        let row = |i: usize| -> Vector4<T> { matrix.row(i).transpose() };
+        let plane = |v: Vector4<T>| Plane::new(v.xyz(), v.w).normalize();
+        Frustum::new([
+            plane(row(3) + row(0)),
+            plane(row(3) - row(0)),
+            plane(row(3) + row(1)),
+            plane(row(3) - row(1)),
+            plane(row(2)),
+            plane(row(3) - row(2)),
+        ])
+    }
+
+    /// The left, right, bottom, top, near and far planes, with normals pointing inwards.
+    ///
+    /// Bottom and top are swapped if the projection flips the Y axis.
+    pub fn planes(&self) -> &[Plane<T>; 6] {
+        &self.planes
+    }
+
+    /// Returns `true` if `point` is inside or on the surface of this `Frustum`.
+    pub fn contains_point(&self, point: &Point3<T>) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.dot_point(point) >= zero())
+    }
+
+    /// Returns where `ray` enters this `Frustum`, or `None` if it misses.
+    pub fn intersect_ray(&self, ray: &Ray<T>) -> Option<RayHit<T>> {
+        let mut enter = zero::<T>();
+        let mut exit = T::max_value();
+        let mut normal = None;
+        for plane in &self.planes {
+            let distance = plane.dot_point(&ray.origin);
+            let speed = plane.dot(&ray.direction);
+            if speed == zero() {
+                if distance < zero() {
+                    return None;
+                }
+                continue;
+            }
+            let t = -distance / speed;
+            if speed > zero() {
+                // Moving inwards through this plane.
+                if t > enter {
+                    enter = t;
+                    normal = Some(-plane.normal());
+                }
+            } else {
+                exit = exit.min(t);
+            }
+            if enter > exit {
+                return None;
+            }
+        }
+        Some(match normal {
+            Some(normal) => RayHit::new(ray, enter, normal),
+            None => RayHit::inside(ray),
+        })
+    }
+}
+
+impl Frustum<f32> {
+    /// Extracts the `Frustum` of a camera with the given `projection` matrix placed by the global
+    /// matrix of `transform`.
+    pub fn from_camera(projection: &Matrix4<f32>, transform: &Transform) -> Self {
+        Self::from_matrix(&(projection * transform.global_view_matrix()))
+    }
+}
+
+impl<T> Intersects<Sphere<T>> for Frustum<T>
+where
+    T: RealField,
+{
+    fn intersects(&self, other: &Sphere<T>) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.dot_point(&other.center) >= -other.radius)
+    }
+}
+
+symmetric_intersects!(Frustum, Sphere);
+
+impl<T> Intersects<Aabb<T>> for Frustum<T>
+where
+    T: RealField,
+{
+    /// Conservative test, boxes close to the edges of the frustum may intersect without touching
+    /// it.
+    fn intersects(&self, other: &Aabb<T>) -> bool {
+        self.planes.iter().all(|plane| {
+            let normal = plane.normal();
+            // The corner furthest along the normal.
+            let corner = Point3::from(Vector3::from_fn(|i, _| {
+                if normal[i] >= zero() {
+                    other.max[i]
+                } else {
+                    other.min[i]
+                }
+            }));
+            plane.dot_point(&corner) >= zero()
+        })
+    }
+}
+
+symmetric_intersects!(Frustum, Aabb);
+
+impl<T> Intersects<Obb<T>> for Frustum<T>
+where
+    T: RealField,
+{
+    /// Conservative test, boxes close to the edges of the frustum may intersect without touching
+    /// it.
+    fn intersects(&self, other: &Obb<T>) -> bool {
+        let axes = other.axes();
+        self.planes.iter().all(|plane| {
+            let radius = (0..3).fold(zero::<T>(), |radius, i| {
+                radius + other.half_extents[i] * plane.dot(&axes[i]).abs()
+            });
+            plane.dot_point(&other.center) >= -radius
+        })
+    }
+}
+
+symmetric_intersects!(Frustum, Obb);
+
+#[cfg(test)]
+mod tests {
+    use approx::*;
+
+    use super::*;
+
+    /// Orthographic projection of `-1.0..=1.0` on X and Y, and a depth of `1.0..=3.0` in front of
+    /// the camera.
+    fn frustum() -> Frustum<f32> {
+        #[rustfmt::skip]
+        let projection = Matrix4::new(
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, -0.5, -0.5,
+            0.0, 0.0, 0.0, 1.0,
+        );
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(10.0, 0.0, 0.0);
+        transform.copy_local_to_global();
+        Frustum::from_camera(&projection, &transform)
+    }
+
+    #[test]
+    fn contains_and_intersects() {
+        let frustum = frustum();
+        assert!(frustum.contains_point(&Point3::new(10.5, 0.5, -2.0)));
+        assert!(!frustum.contains_point(&Point3::new(10.5, 0.5, -0.5)));
+        assert!(!frustum.contains_point(&Point3::new(10.5, 0.5, -3.5)));
+        assert!(!frustum.contains_point(&Point3::new(0.0, 0.0, -2.0)));
+
+        assert!(frustum.intersects(&Sphere::new(Point3::new(11.2, 0.0, -2.0), 0.5)));
+        assert!(!frustum.intersects(&Sphere::new(Point3::new(11.8, 0.0, -2.0), 0.5)));
+        let aabb = Aabb::new(Point3::new(8.0, -0.5, -5.0), Point3::new(9.5, 0.5, 0.0));
+        assert!(frustum.intersects(&aabb));
+        assert!(!frustum.intersects(&Aabb {
+            max: Point3::new(8.5, 0.5, 0.0),
+            ..aabb
+        }));
+    }
+
+    #[test]
+    fn ray_enters_through_near_plane() {
+        let ray = Ray {
+            origin: Point3::new(10.0, 0.0, 5.0),
+            direction: -Vector3::z(),
+        };
+        let hit = frustum().intersect_ray(&ray).unwrap();
+        assert_relative_eq!(hit.distance, 6.0, epsilon = 0.000_01);
+        assert_relative_eq!(hit.normal, Vector3::z(), epsilon = 0.000_01);
+    }
+}
@@ -1,6 +1,123 @@
 //!
 //! Geometry helper functionality.
-use nalgebra::{one, zero, Point3, RealField, Vector3};
+use nalgebra::{one, zero, Matrix4, Point3, RealField, Vector3, U1, U3};
+
+use crate::transform::Transform;
+
+pub use self::{
+    aabb::Aabb, capsule::Capsule, frustum::Frustum, obb::Obb, sphere::Sphere, triangle::Triangle,
+};
+
+/// Where a `Ray` hits a shape.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RayHit<T>
+where
+    T: RealField,
+{
+    /// Distance from the ray origin along `direction`, in units of its length, see
+    /// `Ray::point_at`.
+    pub distance: T,
+    /// The point where the ray enters the shape.
+    pub point: Point3<T>,
+    /// Normalized surface normal at `point`, pointing out of the shape.
+    ///
+    /// A ray starting inside a shape hits it at distance zero, with the normal pointing against
+    /// the ray.
+    pub normal: Vector3<T>,
+}
+
+impl<T> RayHit<T>
+where
+    T: RealField,
+{
+    fn new(ray: &Ray<T>, distance: T, normal: Vector3<T>) -> Self {
+        RayHit {
+            distance,
+            point: ray.origin + ray.direction * distance,
+            normal,
+        }
+    }
+
+    fn inside(ray: &Ray<T>) -> Self {
+        RayHit::new(ray, zero(), -ray.direction.normalize())
+    }
+}
+
+/// Overlap test between two shapes.
+///
+/// Implemented in both directions for every supported pair of shapes, so `a.intersects(&b)` is
+/// the same as `b.intersects(&a)`. Shapes which only touch intersect.
+pub trait Intersects<Rhs> {
+    /// Returns `true` if `self` and `other` overlap.
+    fn intersects(&self, other: &Rhs) -> bool;
+}
+
+/// Shapes which can be moved into another coordinate space.
+pub trait Transformable<T>: Sized
+where
+    T: RealField,
+{
+    /// Returns this shape transformed by the affine `matrix`.
+    ///
+    /// Shapes which can't represent the result exactly, like an `Aabb` under rotation, return
+    /// the smallest shape of their kind containing it.
+    fn transformed(&self, matrix: &Matrix4<T>) -> Self;
+
+    /// Returns this shape moved from the local space of `transform` into world space, using its
+    /// global matrix.
+    fn transformed_by(&self, transform: &Transform) -> Self
+    where
+        Self: Transformable<f32>,
+    {
+        Transformable::<f32>::transformed(self, transform.global_matrix())
+    }
+}
+
+/// Implements `Intersects<$a> for $b` in terms of `Intersects<$b> for $a`.
+macro_rules! symmetric_intersects {
+    ($a:ident, $b:ident) => {
+        impl<T> $crate::geometry::Intersects<$a<T>> for $b<T>
+        where
+            T: nalgebra::RealField,
+        {
+            fn intersects(&self, other: &$a<T>) -> bool {
+                other.intersects(self)
+            }
+        }
+    };
+}
+
+mod aabb;
+mod capsule;
+mod frustum;
+mod obb;
+mod sphere;
+mod triangle;
+
+/// Largest scale factor of the linear part of `matrix`, used to scale radii.
+fn max_scale<T: RealField>(matrix: &Matrix4<T>) -> T {
+    let x = matrix.fixed_slice::<U3, U1>(0, 0).norm();
+    let y = matrix.fixed_slice::<U3, U1>(0, 1).norm();
+    let z = matrix.fixed_slice::<U3, U1>(0, 2).norm();
+    x.max(y).max(z)
+}
+
+/// Point on the segment from `a` to `b` closest to `point`.
+fn closest_point_on_segment<T: RealField>(
+    point: &Point3<T>,
+    a: &Point3<T>,
+    b: &Point3<T>,
+) -> Point3<T> {
+    let ab = b - a;
+    let length_squared = ab.norm_squared();
+    if length_squared == zero() {
+        return *a;
+    }
+    let t = ((point - a).dot(&ab) / length_squared)
+        .max(zero())
+        .min(one());
+    a + ab * t
+}
 
 /// A plane which can be intersected by a ray.
 #[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -133,12 +250,20 @@ where
     pub fn at_distance(&self, z: T) -> Point3<T> {
         self.origin - (self.direction * z)
     }
+
+    /// Returns the point at `origin + direction * t`.
+    ///
+    /// This is the convention of `RayHit::distance`. Note that `at_distance` and
+    /// `intersect_plane` measure distances in the opposite direction.
+    pub fn point_at(&self, t: T) -> Point3<T> {
+        self.origin + self.direction * t
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use approx::{assert_ulps_eq, relative_eq};
+    use approx::{assert_relative_eq, assert_ulps_eq};
 
     #[test]
     #[allow(clippy::mistyped_literal_suffixes)]
@@ -164,12 +289,24 @@ pub mod tests {
 
     #[test]
     fn at_distance() {
-        relative_eq!(
-            Ray {
+        assert_relative_eq!(
+            Ray::<f32> {
                 origin: Point3::new(0.020_277_506, -0.033_236_53, 51.794),
                 direction: Vector3::new(0.179_559_51, -0.294_313_04, -0.938_689_65),
             }
             .at_distance(5.0),
+            Point3::new(-0.877_520_04, 1.438_328_7, 56.487_45)
+        );
+    }
+
+    #[test]
+    fn point_at() {
+        assert_relative_eq!(
+            Ray::<f32> {
+                origin: Point3::new(0.020_277_506, -0.033_236_53, 51.794),
+                direction: Vector3::new(0.179_559_51, -0.294_313_04, -0.938_689_65),
+            }
+            .point_at(5.0),
             Point3::new(0.918_075_1, -1.504_801_8, 47.100_55)
         );
     }
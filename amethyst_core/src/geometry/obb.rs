@@ -0,0 +1,248 @@
+use nalgebra::{
+    convert, zero, Matrix3, Matrix4, Point3, RealField, Rotation3, UnitQuaternion, Vector3, U3,
+};
+
+use super::{Aabb, Intersects, Ray, RayHit, Sphere, Transformable};
+
+/// An oriented bounding box.
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Obb<T>
+where
+    T: RealField,
+{
+    /// The center of the box.
+    pub center: Point3<T>,
+    /// The distances from the center to the faces, along the axes of the box.
+    pub half_extents: Vector3<T>,
+    /// The rotation from the local axes of the box to world axes.
+    pub rotation: UnitQuaternion<T>,
+}
+
+impl<T> Obb<T>
+where
+    T: RealField,
+{
+    /// Create a new `Obb`.
+    pub fn new(center: Point3<T>, half_extents: Vector3<T>, rotation: UnitQuaternion<T>) -> Self {
+        Obb {
+            center,
+            half_extents,
+            rotation,
+        }
+    }
+
+    /// Create an `Obb` covering the same space as `aabb`.
+    pub fn from_aabb(aabb: &Aabb<T>) -> Self {
+        Obb::new(
+            aabb.center(),
+            aabb.half_extents(),
+            UnitQuaternion::identity(),
+        )
+    }
+
+    /// The world-space directions of the local X, Y and Z axes of the box.
+    pub fn axes(&self) -> [Vector3<T>; 3] {
+        [
+            self.rotation * Vector3::x(),
+            self.rotation * Vector3::y(),
+            self.rotation * Vector3::z(),
+        ]
+    }
+
+    /// Converts a world-space `point` into the local space of the box, centered on the origin.
+    pub fn to_local(&self, point: &Point3<T>) -> Point3<T> {
+        Point3::from(self.rotation.inverse() * (point - self.center))
+    }
+
+    /// Converts a `point` in the local space of the box into world space.
+    pub fn to_world(&self, point: &Point3<T>) -> Point3<T> {
+        self.center + self.rotation * point.coords
+    }
+
+    /// The smallest `Aabb` containing this `Obb`.
+    pub fn bounding_aabb(&self) -> Aabb<T> {
+        let linear = self
+            .rotation
+            .to_rotation_matrix()
+            .into_inner()
+            .map(|x| x.abs());
+        Aabb::from_center_half_extents(self.center, linear * self.half_extents)
+    }
+
+    /// Returns `true` if `point` is inside or on the surface of this `Obb`.
+    pub fn contains_point(&self, point: &Point3<T>) -> bool {
+        self.local_aabb().contains_point(&self.to_local(point))
+    }
+
+    /// Returns the point inside this `Obb` closest to `point`.
+    pub fn closest_point(&self, point: &Point3<T>) -> Point3<T> {
+        self.to_world(&self.local_aabb().closest_point(&self.to_local(point)))
+    }
+
+    /// Returns where `ray` enters this `Obb`, or `None` if it misses.
+    pub fn intersect_ray(&self, ray: &Ray<T>) -> Option<RayHit<T>> {
+        let local_ray = Ray {
+            origin: self.to_local(&ray.origin),
+            direction: self.rotation.inverse() * ray.direction,
+        };
+        let hit = self.local_aabb().intersect_ray(&local_ray)?;
+        Some(RayHit::new(ray, hit.distance, self.rotation * hit.normal))
+    }
+
+    fn local_aabb(&self) -> Aabb<T> {
+        Aabb::new(
+            Point3::from(-self.half_extents),
+            Point3::from(self.half_extents),
+        )
+    }
+}
+
+impl<T> Intersects<Obb<T>> for Obb<T>
+where
+    T: RealField,
+{
+    /// Separating axis test over the face normals of both boxes and their cross products.
+    fn intersects(&self, other: &Obb<T>) -> bool {
+        let (a, b) = (self.axes(), other.axes());
+        let (ha, hb) = (self.half_extents, other.half_extents);
+        let offset = other.center - self.center;
+        let t = Vector3::new(offset.dot(&a[0]), offset.dot(&a[1]), offset.dot(&a[2]));
+
+        // Rotation of `other` in the space of `self`. The epsilon keeps nearly parallel edges,
+        // whose cross products are close to zero, from separating the boxes.
+        let r = Matrix3::from_fn(|i, j| a[i].dot(&b[j]));
+        let abs_r = r.map(|x| x.abs() + convert::<f64, T>(1e-6));
+
+        for i in 0..3 {
+            let rb = hb[0] * abs_r[(i, 0)] + hb[1] * abs_r[(i, 1)] + hb[2] * abs_r[(i, 2)];
+            if t[i].abs() > ha[i] + rb {
+                return false;
+            }
+        }
+        for j in 0..3 {
+            let ra = ha[0] * abs_r[(0, j)] + ha[1] * abs_r[(1, j)] + ha[2] * abs_r[(2, j)];
+            let distance = t[0] * r[(0, j)] + t[1] * r[(1, j)] + t[2] * r[(2, j)];
+            if distance.abs() > ra + hb[j] {
+                return false;
+            }
+        }
+        for i in 0..3 {
+            let (i1, i2) = ((i + 1) % 3, (i + 2) % 3);
+            for j in 0..3 {
+                let (j1, j2) = ((j + 1) % 3, (j + 2) % 3);
+                let ra = ha[i1] * abs_r[(i2, j)] + ha[i2] * abs_r[(i1, j)];
+                let rb = hb[j1] * abs_r[(i, j2)] + hb[j2] * abs_r[(i, j1)];
+                let distance = t[i2] * r[(i1, j)] - t[i1] * r[(i2, j)];
+                if distance.abs() > ra + rb {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+impl<T> Intersects<Aabb<T>> for Obb<T>
+where
+    T: RealField,
+{
+    fn intersects(&self, other: &Aabb<T>) -> bool {
+        self.intersects(&Obb::from_aabb(other))
+    }
+}
+
+symmetric_intersects!(Obb, Aabb);
+
+impl<T> Intersects<Sphere<T>> for Obb<T>
+where
+    T: RealField,
+{
+    fn intersects(&self, other: &Sphere<T>) -> bool {
+        other.contains_point(&self.closest_point(&other.center))
+    }
+}
+
+symmetric_intersects!(Obb, Sphere);
+
+impl<T> Transformable<T> for Obb<T>
+where
+    T: RealField,
+{
+    /// Non-uniform scaling is applied along the axes of the box, which is exact unless the box
+    /// is rotated relative to the scaled axes.
+    fn transformed(&self, matrix: &Matrix4<T>) -> Self {
+        let linear = matrix.fixed_slice::<U3, U3>(0, 0);
+        let axes = self.axes();
+        let x = linear * axes[0];
+        let y = linear * axes[1];
+        let half_extents = Vector3::new(
+            x.norm() * self.half_extents.x,
+            y.norm() * self.half_extents.y,
+            (linear * axes[2]).norm() * self.half_extents.z,
+        );
+        // Orthonormalize the transformed axes, a sheared box can't be represented.
+        let basis = x.try_normalize(zero()).and_then(|x| {
+            let y = (y - x * x.dot(&y)).try_normalize(zero())?;
+            Some(Matrix3::from_columns(&[x, y, x.cross(&y)]))
+        });
+        let rotation = basis.map_or(self.rotation, |basis| {
+            UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(basis))
+        });
+        Obb::new(matrix.transform_point(&self.center), half_extents, rotation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::*;
+
+    use super::*;
+
+    #[test]
+    fn separating_axis_test() {
+        let a = Obb::new(
+            Point3::origin(),
+            Vector3::new(1.0, 1.0, 1.0),
+            UnitQuaternion::identity(),
+        );
+        // Rotated by 45 degrees the corner of `b` reaches to 2.0 - sqrt(2).
+        let b = Obb::new(
+            Point3::new(2.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 1.0),
+            UnitQuaternion::from_euler_angles(0.0, 0.0, std::f32::consts::FRAC_PI_4),
+        );
+        assert!(a.intersects(&b));
+        let c = Obb {
+            center: Point3::new(2.5, 0.0, 0.0),
+            ..b
+        };
+        assert!(!a.intersects(&c));
+        assert!(c.contains_point(&Point3::new(1.2, 0.0, 0.0)));
+        assert!(!c.contains_point(&Point3::new(1.2, 1.0, 0.0)));
+        assert!(c.intersects(&Aabb::new(
+            Point3::new(0.0, -0.5, -0.5),
+            Point3::new(1.2, 0.5, 0.5)
+        )));
+    }
+
+    #[test]
+    fn ray_normal_in_world_space() {
+        let obb = Obb::new(
+            Point3::new(0.0, 0.0, -5.0),
+            Vector3::new(1.0, 1.0, 1.0),
+            UnitQuaternion::from_euler_angles(0.0, std::f32::consts::FRAC_PI_4, 0.0),
+        );
+        let ray = Ray {
+            origin: Point3::new(0.3, 0.0, 0.0),
+            direction: -Vector3::z(),
+        };
+        let hit = obb.intersect_ray(&ray).unwrap();
+        let half = 0.5_f32.sqrt();
+        assert_relative_eq!(hit.distance, 5.3 - 2.0_f32.sqrt(), epsilon = 0.000_01);
+        assert_relative_eq!(
+            hit.normal,
+            Vector3::new(half, 0.0, half),
+            epsilon = 0.000_01
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use nalgebra::{convert, zero, Matrix4, Point3, RealField, Vector3};
+
+use super::{max_scale, Aabb, Intersects, Ray, RayHit, Transformable};
+
+/// A sphere.
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Sphere<T>
+where
+    T: RealField,
+{
+    /// The center of the sphere.
+    pub center: Point3<T>,
+    /// The radius of the sphere.
+    pub radius: T,
+}
+
+impl<T> Sphere<T>
+where
+    T: RealField,
+{
+    /// Create a new `Sphere`.
+    pub fn new(center: Point3<T>, radius: T) -> Self {
+        Sphere { center, radius }
+    }
+
+    /// Create the smallest `Sphere` containing `aabb`.
+    pub fn from_aabb(aabb: &Aabb<T>) -> Self {
+        Sphere::new(aabb.center(), aabb.half_extents().norm())
+    }
+
+    /// The smallest `Aabb` containing this `Sphere`.
+    pub fn bounding_aabb(&self) -> Aabb<T> {
+        let radius = Vector3::repeat(self.radius);
+        Aabb::new(self.center - radius, self.center + radius)
+    }
+
+    /// Returns `true` if `point` is inside or on the surface of this `Sphere`.
+    pub fn contains_point(&self, point: &Point3<T>) -> bool {
+        (point - self.center).norm_squared() <= self.radius * self.radius
+    }
+
+    /// Returns the smallest `Sphere` containing this one and `other`.
+    pub fn merged(&self, other: &Sphere<T>) -> Self {
+        let offset = other.center - self.center;
+        let distance = offset.norm();
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return *other;
+        }
+        let radius = (distance + self.radius + other.radius) * convert::<f64, T>(0.5);
+        let center = self.center + offset * ((radius - self.radius) / distance);
+        Sphere::new(center, radius)
+    }
+
+    /// Returns where `ray` enters this `Sphere`, or `None` if it misses.
+    pub fn intersect_ray(&self, ray: &Ray<T>) -> Option<RayHit<T>> {
+        let offset = ray.origin - self.center;
+        let c = offset.norm_squared() - self.radius * self.radius;
+        if c <= zero() {
+            return Some(RayHit::inside(ray));
+        }
+        let a = ray.direction.norm_squared();
+        let b = offset.dot(&ray.direction);
+        let discriminant = b * b - a * c;
+        if b > zero() || discriminant < zero() || a == zero() {
+            return None;
+        }
+        let distance = (-b - discriminant.sqrt()) / a;
+        let hit = RayHit::new(ray, distance, Vector3::zeros());
+        Some(RayHit {
+            normal: (hit.point - self.center).normalize(),
+            ..hit
+        })
+    }
+}
+
+impl<T> Intersects<Sphere<T>> for Sphere<T>
+where
+    T: RealField,
+{
+    fn intersects(&self, other: &Sphere<T>) -> bool {
+        let radius = self.radius + other.radius;
+        (other.center - self.center).norm_squared() <= radius * radius
+    }
+}
+
+impl<T> Intersects<Sphere<T>> for Aabb<T>
+where
+    T: RealField,
+{
+    fn intersects(&self, other: &Sphere<T>) -> bool {
+        other.contains_point(&self.closest_point(&other.center))
+    }
+}
+
+symmetric_intersects!(Aabb, Sphere);
+
+impl<T> Transformable<T> for Sphere<T>
+where
+    T: RealField,
+{
+    fn transformed(&self, matrix: &Matrix4<T>) -> Self {
+        Sphere::new(
+            matrix.transform_point(&self.center),
+            self.radius * max_scale(matrix),
+        )
+    }
+}
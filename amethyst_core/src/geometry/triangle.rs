@@ -0,0 +1,258 @@
+use nalgebra::{convert, one, zero, Matrix4, Point3, RealField, Vector3};
+
+use super::{Aabb, Intersects, Obb, Ray, RayHit, Sphere, Transformable};
+
+/// A triangle.
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Triangle<T>
+where
+    T: RealField,
+{
+    /// The first corner.
+    pub a: Point3<T>,
+    /// The second corner.
+    pub b: Point3<T>,
+    /// The third corner.
+    pub c: Point3<T>,
+}
+
+impl<T> Triangle<T>
+where
+    T: RealField,
+{
+    /// Create a new `Triangle`.
+    pub fn new(a: Point3<T>, b: Point3<T>, c: Point3<T>) -> Self {
+        Triangle { a, b, c }
+    }
+
+    /// The normalized normal of the front face, which has its corners in counter-clockwise order.
+    pub fn normal(&self) -> Vector3<T> {
+        (self.b - self.a).cross(&(self.c - self.a)).normalize()
+    }
+
+    /// The area of this `Triangle`.
+    pub fn area(&self) -> T {
+        (self.b - self.a).cross(&(self.c - self.a)).norm() * convert::<f64, T>(0.5)
+    }
+
+    /// The center of mass of this `Triangle`.
+    pub fn centroid(&self) -> Point3<T> {
+        Point3::from((self.a.coords + self.b.coords + self.c.coords) / convert::<f64, T>(3.0))
+    }
+
+    /// The smallest `Aabb` containing this `Triangle`.
+    pub fn bounding_aabb(&self) -> Aabb<T> {
+        Aabb::new(self.a, self.b).merged_point(&self.c)
+    }
+
+    /// Returns the point on this `Triangle` closest to `point`.
+    pub fn closest_point(&self, point: &Point3<T>) -> Point3<T> {
+        let (a, b, c) = (self.a, self.b, self.c);
+        let (ab, ac, ap) = (b - a, c - a, point - a);
+        let (d1, d2) = (ab.dot(&ap), ac.dot(&ap));
+        if d1 <= zero() && d2 <= zero() {
+            return a;
+        }
+        let bp = point - b;
+        let (d3, d4) = (ab.dot(&bp), ac.dot(&bp));
+        if d3 >= zero() && d4 <= d3 {
+            return b;
+        }
+        let vc = d1 * d4 - d3 * d2;
+        if vc <= zero() && d1 >= zero() && d3 <= zero() {
+            return a + ab * (d1 / (d1 - d3));
+        }
+        let cp = point - c;
+        let (d5, d6) = (ab.dot(&cp), ac.dot(&cp));
+        if d6 >= zero() && d5 <= d6 {
+            return c;
+        }
+        let vb = d5 * d2 - d1 * d6;
+        if vb <= zero() && d2 >= zero() && d6 <= zero() {
+            return a + ac * (d2 / (d2 - d6));
+        }
+        let va = d3 * d6 - d5 * d4;
+        if va <= zero() && d4 - d3 >= zero() && d5 - d6 >= zero() {
+            return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+        }
+        let denominator = one::<T>() / (va + vb + vc);
+        a + ab * (vb * denominator) + ac * (vc * denominator)
+    }
+
+    /// Returns where `ray` hits either face of this `Triangle`, or `None` if it misses.
+    ///
+    /// The normal of the hit points towards the side the ray came from.
+    pub fn intersect_ray(&self, ray: &Ray<T>) -> Option<RayHit<T>> {
+        let (ab, ac) = (self.b - self.a, self.c - self.a);
+        let p = ray.direction.cross(&ac);
+        let determinant = ab.dot(&p);
+        if determinant == zero() {
+            return None;
+        }
+        let inverse = one::<T>() / determinant;
+        let offset = ray.origin - self.a;
+        let u = offset.dot(&p) * inverse;
+        if u < zero() || u > one() {
+            return None;
+        }
+        let q = offset.cross(&ab);
+        let v = ray.direction.dot(&q) * inverse;
+        if v < zero() || u + v > one() {
+            return None;
+        }
+        let distance = ac.dot(&q) * inverse;
+        if distance < zero() {
+            return None;
+        }
+        let normal = self.normal();
+        let normal = if normal.dot(&ray.direction) > zero() {
+            -normal
+        } else {
+            normal
+        };
+        Some(RayHit::new(ray, distance, normal))
+    }
+
+    /// Separating axis test against a box centered on the origin with the given `half_extents`,
+    /// with the corners of this triangle in the space of the box.
+    fn intersects_centered_box(&self, half_extents: &Vector3<T>) -> bool {
+        let corners = [self.a.coords, self.b.coords, self.c.coords];
+        let edges = [
+            corners[1] - corners[0],
+            corners[2] - corners[1],
+            corners[0] - corners[2],
+        ];
+        let separated = |axis: &Vector3<T>| {
+            let projections = [
+                corners[0].dot(axis),
+                corners[1].dot(axis),
+                corners[2].dot(axis),
+            ];
+            let min = projections[0].min(projections[1]).min(projections[2]);
+            let max = projections[0].max(projections[1]).max(projections[2]);
+            let radius = half_extents.dot(&axis.map(|x| x.abs()));
+            min > radius || max < -radius
+        };
+
+        let box_axes = [Vector3::x(), Vector3::y(), Vector3::z()];
+        if box_axes.iter().any(&separated) {
+            return false;
+        }
+        if separated(&edges[0].cross(&edges[1])) {
+            return false;
+        }
+        !box_axes
+            .iter()
+            .flat_map(|axis| edges.iter().map(move |edge| axis.cross(edge)))
+            .any(|axis| separated(&axis))
+    }
+}
+
+impl<T> Intersects<Sphere<T>> for Triangle<T>
+where
+    T: RealField,
+{
+    fn intersects(&self, other: &Sphere<T>) -> bool {
+        other.contains_point(&self.closest_point(&other.center))
+    }
+}
+
+symmetric_intersects!(Triangle, Sphere);
+
+impl<T> Intersects<Aabb<T>> for Triangle<T>
+where
+    T: RealField,
+{
+    fn intersects(&self, other: &Aabb<T>) -> bool {
+        let center = other.center().coords;
+        Triangle::new(self.a - center, self.b - center, self.c - center)
+            .intersects_centered_box(&other.half_extents())
+    }
+}
+
+symmetric_intersects!(Triangle, Aabb);
+
+impl<T> Intersects<Obb<T>> for Triangle<T>
+where
+    T: RealField,
+{
+    fn intersects(&self, other: &Obb<T>) -> bool {
+        Triangle::new(
+            other.to_local(&self.a),
+            other.to_local(&self.b),
+            other.to_local(&self.c),
+        )
+        .intersects_centered_box(&other.half_extents)
+    }
+}
+
+symmetric_intersects!(Triangle, Obb);
+
+impl<T> Transformable<T> for Triangle<T>
+where
+    T: RealField,
+{
+    fn transformed(&self, matrix: &Matrix4<T>) -> Self {
+        Triangle::new(
+            matrix.transform_point(&self.a),
+            matrix.transform_point(&self.b),
+            matrix.transform_point(&self.c),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::*;
+
+    use super::*;
+
+    fn triangle() -> Triangle<f32> {
+        Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+            Point3::new(0.0, 2.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn ray_hits_both_faces() {
+        let front = Ray {
+            origin: Point3::new(0.5, 0.5, 3.0),
+            direction: -Vector3::z(),
+        };
+        let hit = triangle().intersect_ray(&front).unwrap();
+        assert_relative_eq!(hit.distance, 3.0);
+        assert_relative_eq!(hit.normal, Vector3::z());
+
+        let back = Ray {
+            origin: Point3::new(0.5, 0.5, -3.0),
+            direction: Vector3::z(),
+        };
+        assert_relative_eq!(
+            triangle().intersect_ray(&back).unwrap().normal,
+            -Vector3::z()
+        );
+        let outside = Ray {
+            origin: Point3::new(1.5, 1.5, 3.0),
+            direction: -Vector3::z(),
+        };
+        assert!(triangle().intersect_ray(&outside).is_none());
+    }
+
+    #[test]
+    fn overlaps() {
+        assert_relative_eq!(
+            triangle().closest_point(&Point3::new(2.0, 2.0, 1.0)),
+            Point3::new(1.0, 1.0, 0.0)
+        );
+        assert!(triangle().intersects(&Sphere::new(Point3::new(1.0, 1.0, 0.5), 0.5)));
+        assert!(!triangle().intersects(&Sphere::new(Point3::new(1.5, 1.5, 0.0), 0.5)));
+
+        // Only the separating axes along the triangle edges keep these apart.
+        let aabb = Aabb::new(Point3::new(1.2, 1.2, -1.0), Point3::new(2.0, 2.0, 1.0));
+        assert!(!triangle().intersects(&aabb));
+        let aabb = Aabb::new(Point3::new(0.9, 0.9, -1.0), Point3::new(2.0, 2.0, 1.0));
+        assert!(triangle().intersects(&aabb));
+    }
+}
@@ -30,7 +30,7 @@ use std::sync::Arc;
 pub use crate::{
     bundle::SystemBundle,
     event::EventReader,
-    system_ext::{Pausable, SystemExt},
+    system_ext::{ActiveState, AtFixedRate, EveryNFrames, InState, Pausable, RunIf, SystemExt},
     timing::*,
     transform::*,
 };
@@ -39,7 +39,9 @@ pub use self::{
     axis::{Axis2, Axis3},
     hidden::{Hidden, HiddenPropagate},
     hide_system::{HideHierarchySystem, HideHierarchySystemDesc},
-    named::{Named, WithNamed},
+    named::{NameIndex, NameIndexSystem, NameIndexSystemDesc, Named, WithNamed},
+    rng::{Rng, RngConfig, RngState, RngStream},
+    spatial::{SpatialBounds, SpatialIndex, SpatialIndexSystem, SpatialIndexSystemDesc},
     system_desc::{RunNowDesc, SystemDesc},
 };
 
@@ -47,6 +49,8 @@ pub mod bundle;
 pub mod deferred_dispatcher_operation;
 pub mod frame_limiter;
 pub mod geometry;
+#[cfg(feature = "saveload")]
+pub mod saveload;
 pub mod timing;
 pub mod transform;
 
@@ -55,6 +59,8 @@ mod event;
 mod hidden;
 mod hide_system;
 mod named;
+mod rng;
+mod spatial;
 mod system_desc;
 mod system_ext;
 
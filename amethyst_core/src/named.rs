@@ -1,8 +1,23 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashMap, ops::Deref};
 
-use crate::ecs::{world::LazyBuilder, Component, DenseVecStorage, EntityBuilder, WriteStorage};
+use crate::{
+    ecs::{
+        prelude::{
+            ComponentEvent, Entities, Entity, Join, ReadStorage, ReaderId, System, SystemData,
+            World, WorldExt, Write,
+        },
+        storage::MaskedStorage,
+        world::{Index, LazyBuilder},
+        Component, DenseVecStorage, EntityBuilder, FlaggedStorage, Storage, WriteStorage,
+    },
+    transform::Parent,
+    SystemDesc,
+};
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
 /// A component that gives a name to an [`Entity`].
 ///
 /// There are two ways you can get a name for an entity:
@@ -113,7 +128,7 @@ impl Named {
 }
 
 impl Component for Named {
-    type Storage = DenseVecStorage<Self>;
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
 }
 
 /// An easy way to name an `Entity` and give it a `Named` `Component`.
@@ -150,3 +165,245 @@ impl<'a> WithNamed for LazyBuilder<'a> {
         self
     }
 }
+
+/// Resource mapping names to the entities with that `Named` component.
+///
+/// Kept up to date by the `NameIndexSystem`. Besides plain names, it resolves paths like
+/// `"player/arm_l/hand"`, where every segment after the first must name a child of the entity
+/// found for the previous segment, following the `Parent` components.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// fn run(&mut self, (index, parents): (Read<'_, NameIndex>, ReadStorage<'_, Parent>)) {
+///     if let Some(hand) = index.find_path("player/arm_l/hand", &parents) {
+///         // ...
+///     }
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct NameIndex {
+    entities: HashMap<Cow<'static, str>, Vec<Entity>>,
+    names: HashMap<Index, (Entity, Cow<'static, str>)>,
+}
+
+impl NameIndex {
+    /// Returns an entity named `name`. If several entities share the name, the one named first
+    /// is returned.
+    pub fn find(&self, name: &str) -> Option<Entity> {
+        self.find_all(name).first().cloned()
+    }
+
+    /// Returns all entities named `name`.
+    pub fn find_all(&self, name: &str) -> &[Entity] {
+        self.entities
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the name of `entity`.
+    pub fn name_of(&self, entity: Entity) -> Option<&str> {
+        self.names
+            .get(&entity.id())
+            .filter(|(named, _)| *named == entity)
+            .map(|(_, name)| name.as_ref())
+    }
+
+    /// Resolves a `/` separated path of names. The first segment may name any entity, every
+    /// following segment names a child of the previous one.
+    pub fn find_path<D>(&self, path: &str, parents: &Storage<'_, Parent, D>) -> Option<Entity>
+    where
+        D: Deref<Target = MaskedStorage<Parent>>,
+    {
+        let mut segments = path.split('/').filter(|segment| !segment.is_empty());
+        let first = segments.next()?;
+        self.resolve(self.find_all(first).to_vec(), segments, parents)
+    }
+
+    /// Resolves a `/` separated path of names relative to `root`. Every segment names a child of
+    /// the previous one, the first segment a child of `root`.
+    pub fn find_path_from<D>(
+        &self,
+        root: Entity,
+        path: &str,
+        parents: &Storage<'_, Parent, D>,
+    ) -> Option<Entity>
+    where
+        D: Deref<Target = MaskedStorage<Parent>>,
+    {
+        let segments = path.split('/').filter(|segment| !segment.is_empty());
+        self.resolve(vec![root], segments, parents)
+    }
+
+    fn resolve<'p, D>(
+        &self,
+        mut candidates: Vec<Entity>,
+        segments: impl Iterator<Item = &'p str>,
+        parents: &Storage<'_, Parent, D>,
+    ) -> Option<Entity>
+    where
+        D: Deref<Target = MaskedStorage<Parent>>,
+    {
+        for segment in segments {
+            candidates = self
+                .find_all(segment)
+                .iter()
+                .filter(|child| {
+                    parents
+                        .get(**child)
+                        .map_or(false, |parent| candidates.contains(&parent.entity))
+                })
+                .cloned()
+                .collect();
+            if candidates.is_empty() {
+                return None;
+            }
+        }
+        candidates.first().cloned()
+    }
+
+    fn insert(&mut self, entity: Entity, name: Cow<'static, str>) {
+        self.entities.entry(name.clone()).or_default().push(entity);
+        self.names.insert(entity.id(), (entity, name));
+    }
+
+    /// Like `insert`, but keeps the position of `entity` among the entities sharing its name
+    /// if it is indexed with `name` already.
+    fn update(&mut self, entity: Entity, name: &Cow<'static, str>) {
+        match self.names.get(&entity.id()) {
+            Some((indexed, indexed_name)) if *indexed == entity && indexed_name == name => {}
+            _ => {
+                self.remove(entity.id());
+                self.insert(entity, name.clone());
+            }
+        }
+    }
+
+    fn remove(&mut self, id: Index) {
+        if let Some((entity, name)) = self.names.remove(&id) {
+            if let Some(entities) = self.entities.get_mut(&name) {
+                entities.retain(|named| *named != entity);
+                if entities.is_empty() {
+                    self.entities.remove(&name);
+                }
+            }
+        }
+    }
+}
+
+/// Builds a `NameIndexSystem`, indexing the entities which are already named.
+#[derive(Default, Debug)]
+pub struct NameIndexSystemDesc;
+
+impl<'a, 'b> SystemDesc<'a, 'b, NameIndexSystem> for NameIndexSystemDesc {
+    fn build(self, world: &mut World) -> NameIndexSystem {
+        <NameIndexSystem as System<'_>>::SystemData::setup(world);
+
+        let mut index = NameIndex::default();
+        let names_events_id = {
+            let mut names = WriteStorage::<Named>::fetch(&world);
+            for (entity, named) in (&*world.entities(), &names).join() {
+                index.insert(entity, named.name.clone());
+            }
+            names.register_reader()
+        };
+        *world.write_resource::<NameIndex>() = index;
+
+        NameIndexSystem::new(names_events_id)
+    }
+}
+
+/// Updates the `NameIndex` resource from changes to `Named` components.
+#[derive(Debug)]
+pub struct NameIndexSystem {
+    names_events_id: ReaderId<ComponentEvent>,
+}
+
+impl NameIndexSystem {
+    /// Creates a new `NameIndexSystem`.
+    pub fn new(names_events_id: ReaderId<ComponentEvent>) -> Self {
+        NameIndexSystem { names_events_id }
+    }
+}
+
+impl<'a> System<'a> for NameIndexSystem {
+    type SystemData = (Entities<'a>, ReadStorage<'a, Named>, Write<'a, NameIndex>);
+
+    fn run(&mut self, (entities, names, mut index): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("name_index_system");
+
+        for event in names.channel().read(&mut self.names_events_id) {
+            match event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => {
+                    let entity = entities.entity(*id);
+                    match names.get(entity) {
+                        Some(named) => index.update(entity, &named.name),
+                        None => index.remove(*id),
+                    }
+                }
+                ComponentEvent::Removed(id) => index.remove(*id),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::prelude::{Builder, RunNow};
+
+    #[test]
+    fn index_follows_changes_and_resolves_paths() {
+        let mut world = World::new();
+        world.register::<Named>();
+        world.register::<Parent>();
+        let player = world.create_entity().named("player").build();
+        let mut system = NameIndexSystemDesc::default().build(&mut world);
+
+        let arm = world
+            .create_entity()
+            .named("arm_l")
+            .with(Parent::new(player))
+            .build();
+        let hand = world
+            .create_entity()
+            .named("hand")
+            .with(Parent::new(arm))
+            .build();
+        // Same name, different parent.
+        let other_hand = world.create_entity().named("hand").build();
+        system.run_now(&world);
+
+        {
+            let index = world.read_resource::<NameIndex>();
+            let parents = world.read_storage::<Parent>();
+            assert_eq!(index.find("player"), Some(player));
+            assert_eq!(index.find_all("hand"), &[hand, other_hand]);
+            assert_eq!(index.name_of(arm), Some("arm_l"));
+            assert_eq!(index.find_path("player/arm_l/hand", &parents), Some(hand));
+            assert_eq!(index.find_path_from(arm, "hand", &parents), Some(hand));
+            assert_eq!(index.find_path("player/hand", &parents), None);
+        }
+
+        world.write_storage::<Named>().get_mut(arm).unwrap().name = "arm_r".into();
+        // Modified without renaming, `hand` is still the first entity with its name.
+        world.write_storage::<Named>().get_mut(hand).unwrap();
+        system.run_now(&world);
+        assert_eq!(
+            world.read_resource::<NameIndex>().find_all("hand"),
+            &[hand, other_hand]
+        );
+
+        world.delete_entity(other_hand).unwrap();
+        world.maintain();
+        system.run_now(&world);
+
+        let index = world.read_resource::<NameIndex>();
+        let parents = world.read_storage::<Parent>();
+        assert_eq!(index.find("arm_l"), None);
+        assert_eq!(index.find_all("hand"), &[hand]);
+        assert_eq!(index.find_path("player/arm_r/hand", &parents), Some(hand));
+    }
+}
@@ -0,0 +1,253 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hasher,
+};
+
+use amethyst_error::{format_err, Error, ResultExt};
+use fnv::FnvHasher;
+use log::info;
+use rand::{Error as RandError, RngCore};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+
+/// Seed configuration for the `Rng` resource, loaded from a configuration file or the command
+/// line.
+///
+/// Without a seed a random one is chosen and logged, so the run can be repeated later.
+///
+/// # Examples
+///
+/// ```no_run
+/// use amethyst::prelude::*;
+/// use amethyst::core::RngConfig;
+///
+/// let config = RngConfig::load("./config/rng.ron");
+/// // Or from `--seed 1234` on the command line.
+/// let config = RngConfig::from_args(std::env::args()).unwrap();
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize, new)]
+pub struct RngConfig {
+    /// The seed of the `Rng`, or `None` to choose one at random.
+    pub seed: Option<u64>,
+}
+
+impl RngConfig {
+    /// Reads the seed from a `--seed <seed>` or `--seed=<seed>` argument. Without one the seed is
+    /// left to be chosen at random.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the seed is missing or not a number.
+    pub fn from_args<I>(args: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let value = if arg == "--seed" {
+                args.next()
+                    .ok_or_else(|| format_err!("Missing value for --seed"))?
+            } else if arg.starts_with("--seed=") {
+                arg["--seed=".len()..].to_owned()
+            } else {
+                continue;
+            };
+            let seed = value
+                .parse()
+                .with_context(|_| format_err!("Invalid --seed {:?}", value))?;
+            return Ok(RngConfig::new(Some(seed)));
+        }
+        Ok(RngConfig::default())
+    }
+}
+
+/// Resource for reproducible randomness.
+///
+/// Hands out independent random number streams keyed by name. Each stream is derived only from
+/// the seed and its name, so the numbers a system draws don't depend on which other streams
+/// exist or in which order systems run. Streams implement `rand::RngCore`, so all of `rand`'s
+/// distributions can be used with them.
+///
+/// The state of all streams can be saved with `state` and restored with `restore`, usually next
+/// to a save game.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rand::Rng as _;
+///
+/// fn run(&mut self, (mut rng, mut enemies): (Write<'_, Rng>, WriteStorage<'_, Enemy>)) {
+///     let rng = rng.stream("enemy_ai");
+///     for enemy in (&mut enemies).join() {
+///         enemy.aggressive = rng.gen_bool(0.3);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rng {
+    seed: u64,
+    streams: HashMap<String, RngStream>,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::from_entropy()
+    }
+}
+
+impl Rng {
+    /// Creates a new `Rng` with the given seed.
+    pub fn new(seed: u64) -> Self {
+        Rng {
+            seed,
+            streams: HashMap::new(),
+        }
+    }
+
+    /// Creates a new `Rng` with a random seed, which is logged.
+    pub fn from_entropy() -> Self {
+        let seed = rand::random();
+        info!("Random number generator seed: {}", seed);
+        Rng::new(seed)
+    }
+
+    /// Creates a new `Rng` from the seed in `config`, or a random seed if there is none.
+    pub fn from_config(config: &RngConfig) -> Self {
+        config.seed.map_or_else(Rng::from_entropy, Rng::new)
+    }
+
+    /// The seed all streams are derived from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Changes the seed and restarts all streams.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.streams.clear();
+    }
+
+    /// Returns the stream called `name`, creating it on first use.
+    pub fn stream(&mut self, name: &str) -> &mut RngStream {
+        let seed = self.seed;
+        self.streams
+            .entry(name.to_owned())
+            .or_insert_with(|| RngStream::derive(seed, name))
+    }
+
+    /// Captures the seed and the position of every stream.
+    pub fn state(&self) -> RngState {
+        RngState {
+            seed: self.seed,
+            streams: self
+                .streams
+                .iter()
+                .map(|(name, stream)| (name.clone(), stream.clone()))
+                .collect(),
+        }
+    }
+
+    /// Returns all streams to a state captured by `state`.
+    pub fn restore(&mut self, state: RngState) {
+        self.seed = state.seed;
+        self.streams = state.streams.into_iter().collect();
+    }
+}
+
+/// A named random number stream of the `Rng` resource.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RngStream {
+    rng: Pcg32,
+}
+
+impl RngStream {
+    fn derive(seed: u64, name: &str) -> Self {
+        // The hash must not change between builds, which isn't guaranteed for the std hasher.
+        let mut hasher = FnvHasher::default();
+        hasher.write(name.as_bytes());
+        let key = hasher.finish();
+        RngStream {
+            rng: Pcg32::new(split_mix(seed ^ key), key),
+        }
+    }
+}
+
+impl RngCore for RngStream {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RandError> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+/// Serializable state of the `Rng` resource, see `Rng::state`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RngState {
+    seed: u64,
+    streams: BTreeMap<String, RngStream>,
+}
+
+/// Scrambles `x` so that similar seeds give unrelated generator states.
+fn split_mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(rng: &mut Rng, name: &str) -> Vec<u32> {
+        (0..4).map(|_| rng.stream(name).next_u32()).collect()
+    }
+
+    #[test]
+    fn streams_are_independent_of_order() {
+        let mut a = Rng::new(42);
+        let physics = draw(&mut a, "physics");
+        let ai = draw(&mut a, "ai");
+
+        let mut b = Rng::new(42);
+        assert_eq!(draw(&mut b, "ai"), ai);
+        assert_eq!(draw(&mut b, "physics"), physics);
+        assert_ne!(physics, ai);
+        assert_ne!(draw(&mut Rng::new(43), "ai"), ai);
+    }
+
+    #[test]
+    fn restores_saved_state() {
+        let mut rng = Rng::new(7);
+        draw(&mut rng, "loot");
+        let state = rng.state();
+        let expected = draw(&mut rng, "loot");
+
+        let mut restored = Rng::new(0);
+        restored.restore(state);
+        assert_eq!(restored.seed(), 7);
+        assert_eq!(draw(&mut restored, "loot"), expected);
+    }
+
+    #[test]
+    fn seed_from_args() {
+        let args = |args: &[&str]| {
+            RngConfig::from_args(args.iter().map(|arg| arg.to_string())).map(|config| config.seed)
+        };
+        assert_eq!(args(&["game", "--seed", "12"]).unwrap(), Some(12));
+        assert_eq!(args(&["game", "--seed=34"]).unwrap(), Some(34));
+        assert_eq!(args(&["game"]).unwrap(), None);
+        assert!(args(&["game", "--seed"]).is_err());
+        assert!(args(&["game", "--seed", "abc"]).is_err());
+    }
+}
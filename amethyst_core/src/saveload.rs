@@ -0,0 +1,367 @@
+//! Saving and loading snapshots of marked entities.
+//!
+//! Entities are opted into snapshots by giving them a [`Saveable`] marker, usually with
+//! [`MarkedBuilder::marked`]. The set of components that is written out is chosen at compile time
+//! as a tuple implementing [`SaveableComponents`], e.g. `(Transform, Named, Parent)`. Any
+//! component that is `Clone + Serialize + DeserializeOwned` can be part of that tuple; components
+//! that hold an `Entity` (such as `Parent`) implement `ConvertSaveload` so that the reference is
+//! stored as a marker and remapped to the new entity on load.
+//!
+//! # Examples
+//!
+//! ```
+//! use amethyst_core::{
+//!     ecs::prelude::*,
+//!     saveload::{self, MarkedBuilder, SaveFormat, SaveLoadBundle, Saveable},
+//!     transform::{Parent, Transform},
+//!     Named, SystemBundle,
+//! };
+//!
+//! type Snapshot = (Transform, Named, Parent);
+//!
+//! let mut world = World::new();
+//! let mut dispatcher = DispatcherBuilder::new();
+//! SaveLoadBundle::<Snapshot>::new()
+//!     .build(&mut world, &mut dispatcher)
+//!     .unwrap();
+//!
+//! let root = world
+//!     .create_entity()
+//!     .with(Transform::default())
+//!     .marked::<Saveable>()
+//!     .build();
+//! world
+//!     .create_entity()
+//!     .with(Transform::default())
+//!     .with(Parent::new(root))
+//!     .marked::<Saveable>()
+//!     .build();
+//!
+//! let bytes = saveload::save::<Snapshot>(&world, SaveFormat::Ron).unwrap();
+//! world.delete_all();
+//! world.maintain();
+//!
+//! let loaded = saveload::load::<Snapshot>(&world, SaveFormat::Ron, &bytes).unwrap();
+//! assert_eq!(loaded.len(), 2);
+//! ```
+
+use std::marker::PhantomData;
+
+use amethyst_error::{format_err, Error, ResultExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    bundle::SystemBundle,
+    ecs::{
+        prelude::{
+            Component, DispatcherBuilder, Entities, Entity, Join, ReadStorage, World, WorldExt,
+            WriteExpect, WriteStorage,
+        },
+        saveload::{
+            ConvertSaveload, DeserializeComponents, EntityData, MarkerAllocator,
+            SerializeComponents, SimpleMarker, SimpleMarkerAllocator,
+        },
+    },
+};
+
+pub use crate::ecs::saveload::MarkedBuilder;
+
+/// Tag type for the id space of [`Saveable`] markers.
+#[derive(Debug, Clone, Copy)]
+pub struct SaveableTag;
+
+/// Marker component for entities that are included in save games.
+pub type Saveable = SimpleMarker<SaveableTag>;
+
+/// Resource handing out unique [`Saveable`] markers.
+pub type SaveableAllocator = SimpleMarkerAllocator<SaveableTag>;
+
+/// The serialized form of a single saved entity.
+pub type SavedEntity<C> = EntityData<Saveable, <C as SaveableComponents>::Data>;
+
+/// Encoding used for a save game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SaveFormat {
+    /// Human readable RON text.
+    Ron,
+    /// Compact binary encoding using `bincode`.
+    Binary,
+}
+
+/// A set of components that is written into and read back from save games.
+///
+/// This is implemented for tuples of up to 16 components.
+pub trait SaveableComponents: Send + Sync + 'static {
+    /// The serialized form of the components of one entity.
+    type Data: Serialize + DeserializeOwned;
+
+    /// Registers all components of the set in the `World`.
+    fn register(world: &mut World);
+
+    /// Collects the components of all entities marked as `Saveable`.
+    fn save(world: &World) -> Result<Vec<SavedEntity<Self>>, Error>
+    where
+        Self: Sized;
+
+    /// Rebuilds entities from their saved form, returning the entities that were written to.
+    ///
+    /// Entities whose marker is already known to the `SaveableAllocator` are reused, all other
+    /// entities are created. Entity references are remapped to the loaded entities.
+    fn load(world: &World, entities: Vec<SavedEntity<Self>>) -> Result<Vec<Entity>, Error>
+    where
+        Self: Sized;
+}
+
+macro_rules! impl_saveable_components {
+    ($($ty:ident),*) => {
+        impl<$($ty,)*> SaveableComponents for ($($ty,)*)
+        where
+            $(
+                $ty: Component + ConvertSaveload<Saveable> + Send + Sync,
+                <$ty as Component>::Storage: Default,
+                Error: From<<$ty as ConvertSaveload<Saveable>>::Error>,
+            )*
+        {
+            type Data = ($(Option<<$ty as ConvertSaveload<Saveable>>::Data>,)*);
+
+            fn register(world: &mut World) {
+                $(world.register::<$ty>();)*
+            }
+
+            fn save(world: &World) -> Result<Vec<SavedEntity<Self>>, Error> {
+                let (entities, markers, storages) = world.system_data::<(
+                    Entities<'_>,
+                    ReadStorage<'_, Saveable>,
+                    ($(ReadStorage<'_, $ty>,)*),
+                )>();
+                let ids = |entity| markers.get(entity).cloned();
+
+                (&*entities, &markers)
+                    .join()
+                    .map(|(entity, marker)| {
+                        Ok(EntityData {
+                            marker: *marker,
+                            components: SerializeComponents::<Error, Saveable>::serialize_entity(
+                                &storages, entity, &ids,
+                            )?,
+                        })
+                    })
+                    .collect()
+            }
+
+            fn load(
+                world: &World,
+                saved: Vec<SavedEntity<Self>>,
+            ) -> Result<Vec<Entity>, Error> {
+                let (entities, mut markers, mut allocator, mut storages) = world.system_data::<(
+                    Entities<'_>,
+                    WriteStorage<'_, Saveable>,
+                    WriteExpect<'_, SaveableAllocator>,
+                    ($(WriteStorage<'_, $ty>,)*),
+                )>();
+
+                let mut loaded = Vec::with_capacity(saved.len());
+                for EntityData { marker, components } in saved {
+                    let entity = allocator.retrieve_entity(marker, &mut markers, &entities);
+                    let ids = |marker: Saveable| {
+                        Some(allocator.retrieve_entity(marker, &mut markers, &entities))
+                    };
+                    DeserializeComponents::<Error, Saveable>::deserialize_entity(
+                        &mut storages, entity, components, ids,
+                    )?;
+                    loaded.push(entity);
+                }
+                Ok(loaded)
+            }
+        }
+    };
+}
+
+macro_rules! impl_saveable_components_all {
+    ($head:ident) => {
+        impl_saveable_components!($head);
+    };
+    ($head:ident, $($tail:ident),*) => {
+        impl_saveable_components!($head, $($tail),*);
+        impl_saveable_components_all!($($tail),*);
+    };
+}
+
+impl_saveable_components_all!(CA, CB, CC, CD, CE, CF, CG, CH, CI, CJ, CK, CL, CM, CN, CO, CP);
+
+/// Serializes the components `C` of every `Saveable` entity in the world.
+pub fn save<C>(world: &World, format: SaveFormat) -> Result<Vec<u8>, Error>
+where
+    C: SaveableComponents,
+{
+    let saved = C::save(world).with_context(|_| format_err!("Failed collecting save game"))?;
+    match format {
+        SaveFormat::Ron => ron::ser::to_string_pretty(&saved, Default::default())
+            .map(String::into_bytes)
+            .with_context(|_| format_err!("Failed serializing save game to Ron")),
+        SaveFormat::Binary => bincode::serialize(&saved)
+            .with_context(|_| format_err!("Failed serializing save game to binary")),
+    }
+}
+
+/// Deserializes a save game produced by [`save`] and rebuilds its entities.
+///
+/// Returns every entity that was created or updated.
+pub fn load<C>(world: &World, format: SaveFormat, bytes: &[u8]) -> Result<Vec<Entity>, Error>
+where
+    C: SaveableComponents,
+{
+    let saved: Vec<SavedEntity<C>> = match format {
+        SaveFormat::Ron => ron::de::from_bytes(bytes)
+            .with_context(|_| format_err!("Failed parsing Ron save game"))?,
+        SaveFormat::Binary => bincode::deserialize(bytes)
+            .with_context(|_| format_err!("Failed parsing binary save game"))?,
+    };
+
+    {
+        // Forget markers of entities that have been deleted since they were marked.
+        let (entities, markers, mut allocator) = world.system_data::<(
+            Entities<'_>,
+            ReadStorage<'_, Saveable>,
+            WriteExpect<'_, SaveableAllocator>,
+        )>();
+        allocator.maintain(&entities, &markers);
+    }
+
+    C::load(world, saved).with_context(|_| format_err!("Failed loading save game"))
+}
+
+/// Registers the `Saveable` marker, its allocator and the components `C`.
+///
+/// Saving and loading is done with [`save`] and [`load`], typically from a `State`.
+///
+/// ## Errors
+///
+/// No errors will be returned by this bundle.
+#[derive(Debug)]
+pub struct SaveLoadBundle<C> {
+    _marker: PhantomData<C>,
+}
+
+impl<C> SaveLoadBundle<C> {
+    /// Creates a new save/load bundle.
+    pub fn new() -> Self {
+        SaveLoadBundle {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<C> Default for SaveLoadBundle<C> {
+    fn default() -> Self {
+        SaveLoadBundle::new()
+    }
+}
+
+impl<'a, 'b, C> SystemBundle<'a, 'b> for SaveLoadBundle<C>
+where
+    C: SaveableComponents,
+{
+    fn build(
+        self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.register::<Saveable>();
+        world
+            .entry::<SaveableAllocator>()
+            .or_insert_with(SaveableAllocator::default);
+        C::register(world);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ecs::prelude::Builder,
+        transform::{Parent, Transform},
+        Named,
+    };
+
+    type Snapshot = (Transform, Named, Parent);
+
+    fn setup() -> World {
+        let mut world = World::new();
+        SaveLoadBundle::<Snapshot>::new()
+            .build(&mut world, &mut DispatcherBuilder::new())
+            .unwrap();
+        world
+    }
+
+    fn roundtrip(format: SaveFormat) {
+        let mut world = setup();
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(1.0, 2.0, 3.0);
+        let root = world
+            .create_entity()
+            .with(transform.clone())
+            .with(Named::new("root"))
+            .marked::<Saveable>()
+            .build();
+        world
+            .create_entity()
+            .with(Transform::default())
+            .with(Named::new("child"))
+            .with(Parent::new(root))
+            .marked::<Saveable>()
+            .build();
+        // Unmarked entities are not saved.
+        world.create_entity().with(Named::new("skipped")).build();
+
+        let bytes = save::<Snapshot>(&world, format).unwrap();
+        world.delete_all();
+        world.maintain();
+
+        let loaded = load::<Snapshot>(&world, format, &bytes).unwrap();
+        assert_eq!(loaded.len(), 2);
+
+        let names = world.read_storage::<Named>();
+        let parents = world.read_storage::<Parent>();
+        let transforms = world.read_storage::<Transform>();
+        let new_root = loaded
+            .iter()
+            .cloned()
+            .find(|e| names.get(*e).unwrap().name == "root")
+            .unwrap();
+        let new_child = loaded
+            .iter()
+            .cloned()
+            .find(|e| names.get(*e).unwrap().name == "child")
+            .unwrap();
+
+        assert_eq!(parents.get(new_child).unwrap().entity, new_root);
+        assert!(parents.get(new_root).is_none());
+        assert_eq!(transforms.get(new_root).unwrap(), &transform);
+    }
+
+    #[test]
+    fn roundtrip_ron() {
+        roundtrip(SaveFormat::Ron);
+    }
+
+    #[test]
+    fn roundtrip_binary() {
+        roundtrip(SaveFormat::Binary);
+    }
+
+    #[test]
+    fn unmarked_parent_fails() {
+        let mut world = setup();
+        let root = world.create_entity().build();
+        world
+            .create_entity()
+            .with(Parent::new(root))
+            .marked::<Saveable>()
+            .build();
+
+        assert!(save::<Snapshot>(&world, SaveFormat::Ron).is_err());
+    }
+}
@@ -0,0 +1,551 @@
+use std::{
+    collections::{HashMap, HashSet},
+    marker::PhantomData,
+};
+
+use derivative::Derivative;
+
+use crate::{
+    ecs::{
+        hibitset::{BitSet, BitSetLike},
+        prelude::{
+            Component, ComponentEvent, Entities, Entity, Join, ReadStorage, ReaderId, System,
+            SystemData, World, WorldExt, Write,
+        },
+        storage::Tracked,
+        world::Index,
+        WriteStorage,
+    },
+    geometry::{Aabb, Intersects, Ray, RayHit, Sphere, Transformable},
+    math::{Point3, Vector3},
+    transform::Transform,
+    SystemDesc,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+type Cell = [i32; 3];
+
+/// A component describing the extent of an entity for the `SpatialIndex`.
+///
+/// The storage of the component must be tracked, usually a `FlaggedStorage`, so the
+/// `SpatialIndexSystem` notices when bounds change.
+pub trait SpatialBounds: Component + Send + Sync {
+    /// The bounds of the entity in the local space of its `Transform`.
+    fn bounding_sphere(&self) -> Sphere<f32>;
+}
+
+/// Resource answering spatial queries over entities without scanning all `Transform`s.
+///
+/// Entities are stored as world-space bounding spheres in a loose grid, so queries only look at
+/// the cells around the queried region. Spheres with a radius larger than half the cell size are
+/// kept in a list checked by every query, so pick a cell size larger than most entities.
+///
+/// Kept up to date by the `SpatialIndexSystem` from entities with a `Transform` and a
+/// `SpatialBounds` component. Insert the resource before building the system to change the cell
+/// size.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// fn run(&mut self, (index, explosions): (Read<'_, SpatialIndex>, ReadStorage<'_, Explosion>)) {
+///     for explosion in explosions.join() {
+///         for hit in index.query_radius(&explosion.position, explosion.radius) {
+///             // ...
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SpatialIndex {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<Index>>,
+    large: Vec<Index>,
+    entries: HashMap<Index, Entry>,
+    // Range of cells that were ever occupied, bounding ray casts.
+    min_cell: Cell,
+    max_cell: Cell,
+}
+
+#[derive(Debug)]
+struct Entry {
+    entity: Entity,
+    sphere: Sphere<f32>,
+    cell: Option<Cell>,
+}
+
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        SpatialIndex::new(8.0)
+    }
+}
+
+impl SpatialIndex {
+    /// Creates an empty `SpatialIndex` with cubic cells of `cell_size` world units.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cell_size` is not positive.
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "The cell size must be positive");
+        SpatialIndex {
+            cell_size,
+            cells: HashMap::new(),
+            large: Vec::new(),
+            entries: HashMap::new(),
+            min_cell: [i32::max_value(); 3],
+            max_cell: [i32::min_value(); 3],
+        }
+    }
+
+    /// The size of the grid cells in world units.
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// The number of indexed entities.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no entities are indexed.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the world-space bounds of `entity` as of the last update.
+    pub fn bounds(&self, entity: Entity) -> Option<&Sphere<f32>> {
+        self.entries
+            .get(&entity.id())
+            .filter(|entry| entry.entity == entity)
+            .map(|entry| &entry.sphere)
+    }
+
+    /// Returns the entities whose bounds are within `radius` of `center`.
+    pub fn query_radius(&self, center: &Point3<f32>, radius: f32) -> Vec<Entity> {
+        let sphere = Sphere::new(*center, radius);
+        self.candidates(&sphere.bounding_aabb())
+            .filter(|entry| entry.sphere.intersects(&sphere))
+            .map(|entry| entry.entity)
+            .collect()
+    }
+
+    /// Returns the entities whose bounds intersect `aabb`.
+    pub fn query_aabb(&self, aabb: &Aabb<f32>) -> Vec<Entity> {
+        self.candidates(aabb)
+            .filter(|entry| entry.sphere.intersects(aabb))
+            .map(|entry| entry.entity)
+            .collect()
+    }
+
+    /// Returns the `k` entities whose bounds are nearest to `point`, closest first, with their
+    /// distance. Entities whose bounds contain `point` have a distance of zero.
+    pub fn nearest(&self, point: &Point3<f32>, k: usize) -> Vec<(Entity, f32)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut radius = self.cell_size;
+        loop {
+            let region = Aabb::from_center_half_extents(*point, Vector3::repeat(radius));
+            let mut found = self
+                .candidates(&region)
+                .map(|entry| {
+                    let distance = (point - entry.sphere.center).norm() - entry.sphere.radius;
+                    (entry.entity, distance.max(0.0))
+                })
+                .filter(|(_, distance)| *distance <= radius)
+                .collect::<Vec<_>>();
+            // Everything not found is further away than `radius`, so the closest found are the
+            // closest overall.
+            if found.len() >= k || found.len() == self.entries.len() || radius.is_infinite() {
+                found.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+                found.truncate(k);
+                return found;
+            }
+            radius *= 2.0;
+        }
+    }
+
+    /// Returns the entity whose bounds `ray` hits first within `max_distance`, and where it hits.
+    ///
+    /// Distances are measured in units of the length of `ray.direction`.
+    pub fn cast_ray(&self, ray: &Ray<f32>, max_distance: f32) -> Option<(Entity, RayHit<f32>)> {
+        let mut best: Option<(Entity, RayHit<f32>)> = None;
+        let consider = |id: &Index, best: &mut Option<(Entity, RayHit<f32>)>| {
+            let entry = &self.entries[id];
+            if let Some(hit) = entry.sphere.intersect_ray(ray) {
+                let closer = best.map_or(true, |(_, best)| hit.distance < best.distance);
+                if hit.distance <= max_distance && closer {
+                    *best = Some((entry.entity, hit));
+                }
+            }
+        };
+        for id in &self.large {
+            consider(id, &mut best);
+        }
+        if self.cells.is_empty() {
+            return best;
+        }
+
+        // Walk the cells along the ray. A gridded sphere reaches at most half a cell out of its
+        // cell, so every sphere the ray hits is in a neighbour of a cell the ray passes.
+        let mut cell = self.cell_of(&ray.origin);
+        let mut step = [0; 3];
+        let mut next = [std::f32::INFINITY; 3];
+        let mut delta = [std::f32::INFINITY; 3];
+        for i in 0..3 {
+            let direction = ray.direction[i];
+            if direction > 0.0 {
+                step[i] = 1;
+                next[i] = ((cell[i] + 1) as f32 * self.cell_size - ray.origin[i]) / direction;
+                delta[i] = self.cell_size / direction;
+            } else if direction < 0.0 {
+                step[i] = -1;
+                next[i] = (cell[i] as f32 * self.cell_size - ray.origin[i]) / direction;
+                delta[i] = -self.cell_size / direction;
+            }
+        }
+        let mut visited = HashSet::new();
+        let mut enter = 0.0;
+        loop {
+            let done = enter > max_distance
+                || best.map_or(false, |(_, hit)| hit.distance < enter)
+                || (0..3).any(|i| self.left_occupied_range(cell[i], step[i], i));
+            if done {
+                return best;
+            }
+            for neighbour in neighbours(cell) {
+                if visited.insert(neighbour) {
+                    for id in self.cells.get(&neighbour).into_iter().flatten() {
+                        consider(id, &mut best);
+                    }
+                }
+            }
+            let axis = (0..3)
+                .min_by(|a, b| next[*a].partial_cmp(&next[*b]).unwrap())
+                .unwrap();
+            if next[axis].is_infinite() {
+                return best;
+            }
+            enter = next[axis];
+            cell[axis] += step[axis];
+            next[axis] += delta[axis];
+        }
+    }
+
+    /// Returns `true` if a ray at `coordinate` along `axis` moving by `step` won't reach an
+    /// occupied cell or its neighbours anymore.
+    fn left_occupied_range(&self, coordinate: i32, step: i32, axis: usize) -> bool {
+        let (min, max) = (
+            self.min_cell[axis].saturating_sub(1),
+            self.max_cell[axis].saturating_add(1),
+        );
+        (step >= 0 && coordinate > max) || (step <= 0 && coordinate < min)
+    }
+
+    fn cell_of(&self, point: &Point3<f32>) -> Cell {
+        let cell = |x: f32| (x / self.cell_size).floor() as i32;
+        [cell(point.x), cell(point.y), cell(point.z)]
+    }
+
+    /// Entries which may intersect `region`, plus all large entries.
+    fn candidates<'s>(&'s self, region: &Aabb<f32>) -> impl Iterator<Item = &'s Entry> + 's {
+        let margin = Vector3::repeat(self.cell_size * 0.5);
+        let min = self.cell_of(&(region.min - margin));
+        let max = self.cell_of(&(region.max + margin));
+        let in_range = move |cell: &Cell| (0..3).all(|i| min[i] <= cell[i] && cell[i] <= max[i]);
+        let range_cells = (0..3)
+            .map(|i| (i64::from(max[i]) - i64::from(min[i]) + 1) as f64)
+            .product::<f64>();
+
+        // Walk the cells in range, or all occupied cells if there are fewer.
+        let cells: Box<dyn Iterator<Item = &'s Vec<Index>> + 's> =
+            if range_cells <= self.cells.len() as f64 {
+                Box::new(
+                    (min[0]..=max[0])
+                        .flat_map(move |x| (min[1]..=max[1]).map(move |y| (x, y)))
+                        .flat_map(move |(x, y)| (min[2]..=max[2]).map(move |z| [x, y, z]))
+                        .filter_map(move |cell| self.cells.get(&cell)),
+                )
+            } else {
+                Box::new(
+                    self.cells
+                        .iter()
+                        .filter(move |(cell, _)| in_range(cell))
+                        .map(|(_, ids)| ids),
+                )
+            };
+        cells
+            .flatten()
+            .chain(self.large.iter())
+            .map(move |id| &self.entries[id])
+    }
+
+    fn insert(&mut self, entity: Entity, sphere: Sphere<f32>) {
+        self.remove(entity.id());
+        let cell = if sphere.radius <= self.cell_size * 0.5 {
+            let cell = self.cell_of(&sphere.center);
+            self.cells.entry(cell).or_default().push(entity.id());
+            for (i, &coordinate) in cell.iter().enumerate() {
+                self.min_cell[i] = self.min_cell[i].min(coordinate);
+                self.max_cell[i] = self.max_cell[i].max(coordinate);
+            }
+            Some(cell)
+        } else {
+            self.large.push(entity.id());
+            None
+        };
+        self.entries.insert(
+            entity.id(),
+            Entry {
+                entity,
+                sphere,
+                cell,
+            },
+        );
+    }
+
+    fn remove(&mut self, id: Index) {
+        let entry = match self.entries.remove(&id) {
+            Some(entry) => entry,
+            None => return,
+        };
+        let ids = match entry.cell {
+            Some(cell) => match self.cells.get_mut(&cell) {
+                Some(ids) => ids,
+                None => return,
+            },
+            None => &mut self.large,
+        };
+        if let Some(position) = ids.iter().position(|other| *other == id) {
+            ids.swap_remove(position);
+        }
+        if let Some(cell) = entry.cell {
+            if self.cells[&cell].is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+}
+
+fn neighbours(cell: Cell) -> impl Iterator<Item = Cell> {
+    (-1..=1).flat_map(move |x| {
+        (-1..=1).flat_map(move |y| (-1..=1).map(move |z| [cell[0] + x, cell[1] + y, cell[2] + z]))
+    })
+}
+
+/// Builds a `SpatialIndexSystem`, indexing the entities which already have bounds.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub struct SpatialIndexSystemDesc<B> {
+    marker: PhantomData<B>,
+}
+
+impl<'a, 'b, B> SystemDesc<'a, 'b, SpatialIndexSystem<B>> for SpatialIndexSystemDesc<B>
+where
+    B: SpatialBounds,
+    B::Storage: Tracked,
+{
+    fn build(self, world: &mut World) -> SpatialIndexSystem<B> {
+        <SpatialIndexSystem<B> as System<'_>>::SystemData::setup(world);
+
+        let (transform_events_id, bounds_events_id) = {
+            let mut transforms = WriteStorage::<Transform>::fetch(&world);
+            let mut bounds = WriteStorage::<B>::fetch(&world);
+            let mut index = world.write_resource::<SpatialIndex>();
+            for (entity, transform, bounds) in (&*world.entities(), &transforms, &bounds).join() {
+                index.insert(entity, world_sphere(transform, bounds));
+            }
+            (transforms.register_reader(), bounds.register_reader())
+        };
+
+        SpatialIndexSystem::new(transform_events_id, bounds_events_id)
+    }
+}
+
+/// Updates the `SpatialIndex` resource from changed `Transform`s and bounds `B`.
+///
+/// Add it after the `TransformSystem`, so the index uses the global matrices of this frame.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct SpatialIndexSystem<B> {
+    dirty: BitSet,
+    transform_events_id: ReaderId<ComponentEvent>,
+    bounds_events_id: ReaderId<ComponentEvent>,
+    marker: PhantomData<B>,
+}
+
+impl<B> SpatialIndexSystem<B> {
+    /// Creates a new `SpatialIndexSystem`.
+    pub fn new(
+        transform_events_id: ReaderId<ComponentEvent>,
+        bounds_events_id: ReaderId<ComponentEvent>,
+    ) -> Self {
+        SpatialIndexSystem {
+            dirty: BitSet::new(),
+            transform_events_id,
+            bounds_events_id,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, B> System<'a> for SpatialIndexSystem<B>
+where
+    B: SpatialBounds,
+    B::Storage: Tracked,
+{
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, B>,
+        Write<'a, SpatialIndex>,
+    );
+
+    fn run(&mut self, (entities, transforms, bounds, mut index): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("spatial_index_system");
+
+        self.dirty.clear();
+        let events = transforms
+            .channel()
+            .read(&mut self.transform_events_id)
+            .chain(bounds.channel().read(&mut self.bounds_events_id));
+        for event in events {
+            match event {
+                ComponentEvent::Inserted(id)
+                | ComponentEvent::Modified(id)
+                | ComponentEvent::Removed(id) => {
+                    self.dirty.add(*id);
+                }
+            }
+        }
+
+        for id in (&self.dirty).iter() {
+            let entity = entities.entity(id);
+            match (transforms.get(entity), bounds.get(entity)) {
+                (Some(transform), Some(bounds)) => {
+                    index.insert(entity, world_sphere(transform, bounds))
+                }
+                _ => index.remove(id),
+            }
+        }
+    }
+}
+
+fn world_sphere<B: SpatialBounds>(transform: &Transform, bounds: &B) -> Sphere<f32> {
+    bounds.bounding_sphere().transformed_by(transform)
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::*;
+
+    use super::*;
+    use crate::ecs::prelude::{Builder, DenseVecStorage, FlaggedStorage, RunNow};
+
+    struct Radius(f32);
+
+    impl Component for Radius {
+        type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+    }
+
+    impl SpatialBounds for Radius {
+        fn bounding_sphere(&self) -> Sphere<f32> {
+            Sphere::new(Point3::origin(), self.0)
+        }
+    }
+
+    fn index() -> (SpatialIndex, Vec<Entity>) {
+        let mut world = World::new();
+        let mut index = SpatialIndex::new(2.0);
+        let spheres = [
+            Sphere::new(Point3::new(0.0, 0.0, 0.0), 0.5),
+            Sphere::new(Point3::new(3.0, 0.0, 0.0), 0.5),
+            Sphere::new(Point3::new(-7.0, 1.0, 0.0), 0.5),
+            Sphere::new(Point3::new(20.0, 0.0, 0.0), 5.0),
+        ];
+        let entities = spheres
+            .iter()
+            .map(|sphere| {
+                let entity = world.create_entity().build();
+                index.insert(entity, *sphere);
+                entity
+            })
+            .collect::<Vec<_>>();
+        (index, entities)
+    }
+
+    #[test]
+    fn radius_box_and_nearest_queries() {
+        let (index, e) = index();
+        let mut near = index.query_radius(&Point3::new(1.5, 0.0, 0.0), 1.1);
+        near.sort();
+        assert_eq!(near, vec![e[0], e[1]]);
+        assert_eq!(
+            index.query_aabb(&Aabb::new(
+                Point3::new(14.0, -1.0, -1.0),
+                Point3::new(16.0, 1.0, 1.0)
+            )),
+            vec![e[3]]
+        );
+
+        let nearest = index.nearest(&Point3::new(-1.0, 0.0, 0.0), 3);
+        let nearest_entities = nearest.iter().map(|(e, _)| *e).collect::<Vec<_>>();
+        assert_eq!(nearest_entities, vec![e[0], e[1], e[2]]);
+        assert_relative_eq!(nearest[0].1, 0.5);
+        assert_eq!(index.nearest(&Point3::origin(), 10).len(), 4);
+    }
+
+    #[test]
+    fn ray_cast_finds_first_hit() {
+        let (index, e) = index();
+        let ray = Ray {
+            origin: Point3::new(-20.0, 0.0, 0.0),
+            direction: Vector3::x(),
+        };
+        let (entity, hit) = index.cast_ray(&ray, 100.0).unwrap();
+        assert_eq!(entity, e[0]);
+        assert_relative_eq!(hit.distance, 19.5);
+        assert!(index.cast_ray(&ray, 10.0).is_none());
+
+        let backwards = Ray {
+            origin: Point3::new(100.0, 0.0, 0.0),
+            direction: -Vector3::x(),
+        };
+        assert_eq!(index.cast_ray(&backwards, 1000.0).unwrap().0, e[3]);
+        let miss = Ray {
+            origin: Point3::new(0.0, 5.0, 0.0),
+            direction: Vector3::y(),
+        };
+        assert!(index.cast_ray(&miss, std::f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn system_follows_transforms() {
+        let mut world = World::new();
+        world.register::<Transform>();
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(10.0, 0.0, 0.0);
+        transform.copy_local_to_global();
+        let mut system = SpatialIndexSystemDesc::<Radius>::default().build(&mut world);
+        let entity = world
+            .create_entity()
+            .with(transform)
+            .with(Radius(1.0))
+            .build();
+        system.run_now(&world);
+        assert_eq!(
+            world
+                .read_resource::<SpatialIndex>()
+                .query_radius(&Point3::new(10.0, 0.0, 0.0), 0.1),
+            vec![entity]
+        );
+
+        world.delete_entity(entity).unwrap();
+        world.maintain();
+        system.run_now(&world);
+        assert!(world.read_resource::<SpatialIndex>().is_empty());
+    }
+}
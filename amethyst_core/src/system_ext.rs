@@ -3,9 +3,14 @@
 //! This modules contains an extension trait for the System trait which adds useful transformation
 //! functions.
 
+use std::{any::TypeId, marker::PhantomData, time::Duration};
+
+use derivative::Derivative;
+
 use crate::{
-    ecs::prelude::{Read, System},
-    shred::{RunningTime, SystemData},
+    ecs::prelude::{Read, System, World},
+    shred::{ResourceId, RunningTime, SystemData},
+    timing::Time,
 };
 
 #[cfg(feature = "profiler")]
@@ -80,6 +85,53 @@ pub trait SystemExt {
     where
         Self: Sized,
         V: Send + Sync + Default + PartialEq;
+
+    /// Only run the system when `predicate` returns `true`.
+    ///
+    /// The predicate is called every frame with the `World`, before the data of the system is
+    /// fetched. It may only fetch resources and storages which the system itself reads or
+    /// writes, others may be in use by systems running in parallel.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let dispatcher = DispatcherBuilder::default()
+    ///     .with(
+    ///         EnemyAiSystem.run_if(|world| world.read_resource::<Enemies>().count > 0),
+    ///         "enemy_ai",
+    ///         &[],
+    ///     )
+    ///     .build();
+    /// ```
+    fn run_if<F>(self, predicate: F) -> RunIf<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(&World) -> bool + Send;
+
+    /// Only run the system on the first frame and every `n`th frame after it.
+    ///
+    /// An `n` of zero is treated like one.
+    fn every_n_frames(self, n: u64) -> EveryNFrames<Self>
+    where
+        Self: Sized;
+
+    /// Run the system once per `interval` of accumulated `Time::delta_time`.
+    ///
+    /// If more than one `interval` passed since the last frame, the system runs several times
+    /// to catch up, at most `DEFAULT_MAX_RUNS` times per frame unless changed with
+    /// `AtFixedRate::with_max_runs`. A zero `interval` runs the system once per frame.
+    fn at_fixed_rate(self, interval: Duration) -> AtFixedRate<Self>
+    where
+        Self: Sized;
+
+    /// Only run the system while the active state is of type `T`.
+    ///
+    /// The active state is read from the `ActiveState` resource, which the application's state
+    /// machine keeps up to date. Only states which report their type from
+    /// `State::state_type_id` can match, all others count as no active state.
+    fn in_state<T: ?Sized + 'static>(self) -> InState<Self, T>
+    where
+        Self: Sized;
 }
 
 impl<'s, S> SystemExt for S
@@ -96,6 +148,50 @@ where
             value,
         }
     }
+
+    fn run_if<F>(self, predicate: F) -> RunIf<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(&World) -> bool + Send,
+    {
+        RunIf {
+            system: self,
+            predicate,
+        }
+    }
+
+    fn every_n_frames(self, n: u64) -> EveryNFrames<Self>
+    where
+        Self: Sized,
+    {
+        EveryNFrames {
+            system: self,
+            n: n.max(1),
+            frame: 0,
+        }
+    }
+
+    fn at_fixed_rate(self, interval: Duration) -> AtFixedRate<Self>
+    where
+        Self: Sized,
+    {
+        AtFixedRate {
+            system: self,
+            interval,
+            max_runs: AtFixedRate::<S>::DEFAULT_MAX_RUNS,
+            accumulated: Duration::from_secs(0),
+        }
+    }
+
+    fn in_state<T: ?Sized + 'static>(self) -> InState<Self, T>
+    where
+        Self: Sized,
+    {
+        InState {
+            system: self,
+            marker: PhantomData,
+        }
+    }
 }
 
 /// A system that is enabled when `V` has a specific value.
@@ -132,3 +228,395 @@ where
         self.system.running_time()
     }
 }
+
+/// `SystemData` which declares the accesses of `D` but only fetches it when asked to.
+///
+/// Used by system wrappers which decide whether, or how often, to run the wrapped system.
+#[allow(missing_debug_implementations)]
+pub struct LazySystemData<'a, D> {
+    world: &'a World,
+    marker: PhantomData<D>,
+}
+
+impl<'a, D> LazySystemData<'a, D>
+where
+    D: SystemData<'a>,
+{
+    /// Returns the `World` the data is fetched from.
+    pub fn world(&self) -> &'a World {
+        self.world
+    }
+
+    /// Fetches the data.
+    pub fn fetch(&self) -> D {
+        D::fetch(self.world)
+    }
+}
+
+impl<'a, D> SystemData<'a> for LazySystemData<'a, D>
+where
+    D: SystemData<'a>,
+{
+    fn setup(world: &mut World) {
+        D::setup(world);
+    }
+
+    fn fetch(world: &'a World) -> Self {
+        LazySystemData {
+            world,
+            marker: PhantomData,
+        }
+    }
+
+    fn reads() -> Vec<ResourceId> {
+        D::reads()
+    }
+
+    fn writes() -> Vec<ResourceId> {
+        D::writes()
+    }
+}
+
+/// A system that only runs when a predicate returns `true`.
+///
+/// This is created using the [`SystemExt::run_if`] method.
+///
+/// [`SystemExt::run_if`]: trait.SystemExt.html#tymethod.run_if
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct RunIf<S, F> {
+    system: S,
+    #[derivative(Debug = "ignore")]
+    predicate: F,
+}
+
+impl<'s, S, F> System<'s> for RunIf<S, F>
+where
+    S::SystemData: SystemData<'s>,
+    S: System<'s>,
+    F: FnMut(&World) -> bool + Send,
+{
+    type SystemData = LazySystemData<'s, S::SystemData>;
+
+    fn run(&mut self, data: Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("run_if_system");
+
+        if (self.predicate)(data.world()) {
+            self.system.run(data.fetch());
+        }
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        self.system.setup(world);
+    }
+}
+
+/// A system that only runs every `n`th frame.
+///
+/// This is created using the [`SystemExt::every_n_frames`] method.
+///
+/// [`SystemExt::every_n_frames`]: trait.SystemExt.html#tymethod.every_n_frames
+#[derive(Debug)]
+pub struct EveryNFrames<S> {
+    system: S,
+    n: u64,
+    frame: u64,
+}
+
+impl<'s, S> System<'s> for EveryNFrames<S>
+where
+    S::SystemData: SystemData<'s>,
+    S: System<'s>,
+{
+    type SystemData = LazySystemData<'s, S::SystemData>;
+
+    fn run(&mut self, data: Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("every_n_frames_system");
+
+        let frame = self.frame;
+        self.frame = (self.frame + 1) % self.n;
+        if frame == 0 {
+            self.system.run(data.fetch());
+        }
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        self.system.setup(world);
+    }
+}
+
+/// A system that runs at a fixed rate, independent of the frame rate.
+///
+/// This is created using the [`SystemExt::at_fixed_rate`] method.
+///
+/// [`SystemExt::at_fixed_rate`]: trait.SystemExt.html#tymethod.at_fixed_rate
+#[derive(Debug)]
+pub struct AtFixedRate<S> {
+    system: S,
+    interval: Duration,
+    max_runs: u32,
+    accumulated: Duration,
+}
+
+impl<S> AtFixedRate<S> {
+    /// The number of times the system runs per frame at most, unless changed.
+    pub const DEFAULT_MAX_RUNS: u32 = 5;
+
+    /// Limits how often the system runs in a single frame to catch up.
+    ///
+    /// When the limit is reached, the remaining time is dropped instead of being caught up in
+    /// the following frames, so a slow frame doesn't cause more slow frames. A `max_runs` of
+    /// zero is treated like one.
+    pub fn with_max_runs(mut self, max_runs: u32) -> Self {
+        self.max_runs = max_runs.max(1);
+        self
+    }
+}
+
+impl<'s, S> System<'s> for AtFixedRate<S>
+where
+    S::SystemData: SystemData<'s>,
+    S: System<'s>,
+{
+    type SystemData = (Read<'s, Time>, LazySystemData<'s, S::SystemData>);
+
+    fn run(&mut self, (time, data): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("at_fixed_rate_system");
+
+        self.accumulated += time.delta_time();
+        // Release `Time` in case the system writes it.
+        drop(time);
+
+        if self.interval == Duration::from_secs(0) {
+            self.accumulated = Duration::from_secs(0);
+            self.system.run(data.fetch());
+            return;
+        }
+        let mut runs = 0;
+        while self.accumulated >= self.interval {
+            if runs == self.max_runs {
+                self.accumulated = Duration::from_secs(0);
+                break;
+            }
+            self.accumulated -= self.interval;
+            self.system.run(data.fetch());
+            runs += 1;
+        }
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        <Read<'_, Time> as SystemData<'_>>::setup(world);
+        self.system.setup(world);
+    }
+}
+
+/// Resource holding the type of the active state.
+///
+/// Written by the application's state machine whenever the state stack changes, and read by
+/// systems created with [`SystemExt::in_state`].
+///
+/// [`SystemExt::in_state`]: trait.SystemExt.html#tymethod.in_state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ActiveState {
+    type_id: Option<TypeId>,
+}
+
+impl ActiveState {
+    /// Creates the resource from the `TypeId` of the active state.
+    pub fn new(type_id: Option<TypeId>) -> Self {
+        ActiveState { type_id }
+    }
+
+    /// Creates the resource for an active state of type `T`.
+    pub fn of<T: ?Sized + 'static>() -> Self {
+        ActiveState::new(Some(TypeId::of::<T>()))
+    }
+
+    /// Returns the `TypeId` of the active state, or `None` if there is none.
+    pub fn type_id(&self) -> Option<TypeId> {
+        self.type_id
+    }
+
+    /// Checks whether the active state is of type `T`.
+    pub fn is<T: ?Sized + 'static>(&self) -> bool {
+        self.type_id == Some(TypeId::of::<T>())
+    }
+}
+
+/// A system that only runs while the active state is of type `T`.
+///
+/// This is created using the [`SystemExt::in_state`] method.
+///
+/// [`SystemExt::in_state`]: trait.SystemExt.html#tymethod.in_state
+#[derive(Derivative)]
+#[derivative(Debug(bound = "S: std::fmt::Debug"))]
+pub struct InState<S, T: ?Sized> {
+    system: S,
+    marker: PhantomData<fn() -> Box<T>>,
+}
+
+impl<'s, S, T: ?Sized + 'static> System<'s> for InState<S, T>
+where
+    S::SystemData: SystemData<'s>,
+    S: System<'s>,
+{
+    type SystemData = (Read<'s, ActiveState>, LazySystemData<'s, S::SystemData>);
+
+    fn run(&mut self, (active_state, data): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("in_state_system");
+
+        let active = active_state.is::<T>();
+        // Release `ActiveState` in case the system writes it.
+        drop(active_state);
+        if active {
+            self.system.run(data.fetch());
+        }
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        <Read<'_, ActiveState> as SystemData<'_>>::setup(world);
+        self.system.setup(world);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::prelude::{DispatcherBuilder, ReadExpect, WorldExt, Write};
+
+    struct Count;
+
+    impl<'s> System<'s> for Count {
+        type SystemData = Write<'s, u32>;
+
+        fn run(&mut self, mut count: Self::SystemData) {
+            *count += 1;
+        }
+    }
+
+    /// Counts like `Count`, but needs a resource nobody inserts.
+    struct NeedsMissing;
+
+    struct Missing;
+
+    impl<'s> System<'s> for NeedsMissing {
+        type SystemData = (ReadExpect<'s, Missing>, Write<'s, u32>);
+
+        fn run(&mut self, (_, mut count): Self::SystemData) {
+            *count += 1;
+        }
+    }
+
+    struct Menu;
+
+    const FRAME: Duration = Duration::from_millis(10);
+
+    fn run_frames<'s, S>(system: S, frames: usize) -> u32
+    where
+        S: for<'a> System<'a> + Send + 's,
+    {
+        let mut world = World::new();
+        let mut time = Time::default();
+        time.set_delta_time(FRAME);
+        world.insert(time);
+        world.insert(ActiveState::of::<Menu>());
+        let mut dispatcher = DispatcherBuilder::new().with(system, "", &[]).build();
+        dispatcher.setup(&mut world);
+        for _ in 0..frames {
+            dispatcher.dispatch(&world);
+        }
+        let count = *world.read_resource::<u32>();
+        count
+    }
+
+    #[test]
+    fn run_if() {
+        assert_eq!(run_frames(Count.run_if(|_| false), 3), 0);
+        assert_eq!(
+            run_frames(Count.run_if(|world| world.has_value::<Time>()), 3),
+            3
+        );
+        assert_eq!(run_frames(NeedsMissing.run_if(|_| false), 3), 0);
+    }
+
+    #[test]
+    fn every_n_frames_skips_frames() {
+        assert_eq!(run_frames(Count.every_n_frames(3), 1), 1);
+        assert_eq!(run_frames(Count.every_n_frames(3), 3), 1);
+        assert_eq!(run_frames(Count.every_n_frames(3), 4), 2);
+        assert_eq!(run_frames(Count.every_n_frames(3), 7), 3);
+        assert_eq!(run_frames(Count.every_n_frames(0), 3), 3);
+    }
+
+    #[test]
+    fn at_fixed_rate_accumulates_time() {
+        assert_eq!(
+            run_frames(Count.at_fixed_rate(Duration::from_millis(25)), 5),
+            2
+        );
+        assert_eq!(
+            run_frames(Count.at_fixed_rate(Duration::from_millis(4)), 2),
+            5
+        );
+        assert_eq!(
+            run_frames(Count.at_fixed_rate(Duration::from_secs(0)), 3),
+            3
+        );
+    }
+
+    #[test]
+    fn at_fixed_rate_caps_runs_per_frame() {
+        // Ten intervals pass per frame, but only `DEFAULT_MAX_RUNS` are caught up.
+        assert_eq!(
+            run_frames(Count.at_fixed_rate(Duration::from_millis(1)), 2),
+            2 * AtFixedRate::<Count>::DEFAULT_MAX_RUNS
+        );
+        assert_eq!(
+            run_frames(
+                Count
+                    .at_fixed_rate(Duration::from_millis(1))
+                    .with_max_runs(3),
+                2
+            ),
+            6
+        );
+        // The dropped time isn't caught up later: 7ms of every 10ms frame are lost.
+        assert_eq!(
+            run_frames(
+                Count
+                    .at_fixed_rate(Duration::from_millis(1))
+                    .with_max_runs(3),
+                1
+            ),
+            3
+        );
+    }
+
+    #[test]
+    fn in_state_gates_on_active_state() {
+        assert_eq!(run_frames(Count.in_state::<Menu>(), 2), 2);
+        assert_eq!(run_frames(Count.in_state::<Count>(), 2), 0);
+        // The system data is only fetched in the matching state.
+        assert_eq!(run_frames(NeedsMissing.in_state::<Count>(), 2), 0);
+    }
+}
@@ -2,6 +2,8 @@
 
 use std::time::{Duration, Instant};
 
+use serde::{Deserialize, Serialize};
+
 /// Frame timing values.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Time {
@@ -19,6 +21,8 @@ pub struct Time {
     fixed_time: Duration,
     /// The total number of frames that have been played in this session.
     frame_number: u64,
+    /// The total number of fixed updates that have been run in this session.
+    fixed_frame_number: u64,
     ///Time elapsed since game start, ignoring the speed multipler.
     absolute_real_time: Duration,
     ///Time elapsed since game start, taking the speed multiplier into account.
@@ -73,6 +77,13 @@ impl Time {
         self.frame_number
     }
 
+    /// Gets the number of fixed updates that have been run in this session.
+    ///
+    /// This increments by 1 every time `State::fixed_update` is about to be called.
+    pub fn fixed_frame_number(&self) -> u64 {
+        self.fixed_frame_number
+    }
+
     /// Gets the time since the start of the game, taking into account the speed multiplier.
     pub fn absolute_time(&self) -> Duration {
         self.absolute_time
@@ -180,6 +191,7 @@ impl Time {
     pub fn step_fixed_update(&mut self) -> bool {
         if self.fixed_time_accumulator >= self.fixed_seconds {
             self.fixed_time_accumulator -= self.fixed_seconds;
+            self.fixed_frame_number += 1;
             true
         } else {
             false
@@ -206,6 +218,7 @@ impl Default for Time {
             fixed_time: Duration::new(0, 16_666_666),
             fixed_time_accumulator: 0.0,
             frame_number: 0,
+            fixed_frame_number: 0,
             interpolation_alpha: 0.0,
             absolute_real_time: Duration::default(),
             absolute_time: Duration::default(),
@@ -214,6 +227,24 @@ impl Default for Time {
     }
 }
 
+/// Source of the frame delta time used by the application main loop.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum TimeSource {
+    /// Measure the real time elapsed between frames, throttled by the `FrameLimiter`.
+    WallClock,
+    /// Advance time by the given duration every frame, as fast as possible.
+    ///
+    /// The `FrameLimiter` is bypassed and the frame delta never depends on how long a frame
+    /// actually took, which makes runs reproducible. Useful for headless simulations and tests.
+    Fixed(Duration),
+}
+
+impl Default for TimeSource {
+    fn default() -> Self {
+        TimeSource::WallClock
+    }
+}
+
 /// A stopwatch which accurately measures elapsed time.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Stopwatch {
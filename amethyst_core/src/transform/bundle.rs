@@ -12,8 +12,10 @@ use crate::{
 
 /// Transform bundle
 ///
-/// Will register transform components, and the `TransformSystem`.
-/// `TransformSystem` will be registered with name "transform_system".
+/// Will register transform components and the `TransformSystem`, and with
+/// `with_interpolation` also the `InterpolatedTransformSystem`.
+/// `TransformSystem` will be registered with name "transform_system", and
+/// `InterpolatedTransformSystem` with name "interpolated_transform_system".
 ///
 /// ## Errors
 ///
@@ -26,6 +28,7 @@ use crate::{
 #[derive(Debug, Default)]
 pub struct TransformBundle<'a> {
     dep: &'a [&'a str],
+    interpolation: bool,
 }
 
 impl<'a> TransformBundle<'a> {
@@ -33,6 +36,7 @@ impl<'a> TransformBundle<'a> {
     pub fn new() -> Self {
         TransformBundle {
             dep: Default::default(),
+            interpolation: false,
         }
     }
 
@@ -41,6 +45,13 @@ impl<'a> TransformBundle<'a> {
         self.dep = dep;
         self
     }
+
+    /// Also register the `InterpolatedTransformSystem`, to render entities with an
+    /// `InterpolatedTransform` smoothly between fixed updates.
+    pub fn with_interpolation(mut self) -> Self {
+        self.interpolation = true;
+        self
+    }
 }
 
 impl<'a, 'b, 'c> SystemBundle<'a, 'b> for TransformBundle<'c> {
@@ -59,6 +70,13 @@ impl<'a, 'b, 'c> SystemBundle<'a, 'b> for TransformBundle<'c> {
             "transform_system",
             &["parent_hierarchy_system"],
         );
+        if self.interpolation {
+            builder.add(
+                InterpolatedTransformSystem::new(),
+                "interpolated_transform_system",
+                &["transform_system"],
+            );
+        }
         Ok(())
     }
 }
@@ -0,0 +1,135 @@
+//! Interpolation of transforms between fixed updates.
+use crate::{
+    ecs::prelude::{Component, DenseVecStorage},
+    math::UnitQuaternion,
+    transform::Transform,
+};
+
+/// Smooths the rendered pose of an entity that is moved in `fixed_update`.
+///
+/// Whenever fixed updates have run during a frame, the `InterpolatedTransformSystem` records the
+/// entity's local `Transform`. Its global matrix is then rendered as a blend between the two most
+/// recently recorded poses, using `Time::interpolation_alpha`. This means the rendered pose
+/// lags up to one fixed step behind the simulation, in exchange for motion that does not stutter
+/// when the frame rate differs from the fixed update rate.
+///
+/// Translation and scale are blended linearly, rotation uses spherical linear interpolation.
+///
+/// The `InterpolatedTransformSystem` is added by `TransformBundle::with_interpolation`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InterpolatedTransform {
+    previous: Option<Transform>,
+    current: Option<Transform>,
+}
+
+impl InterpolatedTransform {
+    /// Creates a new `InterpolatedTransform` without any recorded pose.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The pose recorded at the second latest fixed update.
+    pub fn previous(&self) -> Option<&Transform> {
+        self.previous.as_ref()
+    }
+
+    /// The pose recorded at the latest fixed update.
+    pub fn current(&self) -> Option<&Transform> {
+        self.current.as_ref()
+    }
+
+    /// Records the pose of a new fixed update, moving the current pose into the previous one.
+    pub fn push(&mut self, transform: &Transform) {
+        self.previous = self.current.take().or_else(|| Some(transform.clone()));
+        self.current = Some(transform.clone());
+    }
+
+    /// Sets both recorded poses to `transform`, so that the next frames show it without blending.
+    ///
+    /// Use this when teleporting an entity.
+    pub fn reset(&mut self, transform: &Transform) {
+        self.previous = Some(transform.clone());
+        self.current = Some(transform.clone());
+    }
+
+    /// Returns the pose blended between the previous and the current one.
+    ///
+    /// `alpha` is `0.0` for the previous and `1.0` for the current pose. Returns `None` if no pose
+    /// has been recorded yet.
+    pub fn interpolate(&self, alpha: f32) -> Option<Transform> {
+        let (from, to) = match (&self.previous, &self.current) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return None,
+        };
+
+        // Take the shortest path, `q` and `-q` describe the same rotation.
+        let target = if from.rotation().coords.dot(&to.rotation().coords) < 0.0 {
+            -to.rotation().into_inner()
+        } else {
+            to.rotation().into_inner()
+        };
+        let target = UnitQuaternion::new_unchecked(target);
+
+        let mut blended = to.clone();
+        blended.set_translation(from.translation().lerp(to.translation(), alpha));
+        blended.set_rotation(
+            from.rotation()
+                .try_slerp(&target, alpha, std::f32::EPSILON)
+                .unwrap_or(target),
+        );
+        blended.set_scale(from.scale().lerp(to.scale(), alpha));
+        Some(blended)
+    }
+}
+
+impl Component for InterpolatedTransform {
+    type Storage = DenseVecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InterpolatedTransform;
+    use crate::{
+        approx::*,
+        math::{UnitQuaternion, Vector3},
+        Transform,
+    };
+
+    #[test]
+    fn interpolate_halfway() {
+        let mut from = Transform::default();
+        from.set_translation_xyz(0.0, 0.0, 0.0);
+        let mut to = Transform::default();
+        to.set_translation_xyz(2.0, 4.0, -6.0);
+        to.set_rotation_y_axis(std::f32::consts::FRAC_PI_2);
+        to.set_scale(Vector3::new(3.0, 3.0, 3.0));
+
+        let mut interpolated = InterpolatedTransform::new();
+        assert!(interpolated.interpolate(0.5).is_none());
+        interpolated.push(&from);
+        interpolated.push(&to);
+
+        let halfway = interpolated.interpolate(0.5).unwrap();
+        assert_relative_eq!(*halfway.translation(), Vector3::new(1.0, 2.0, -3.0));
+        assert_relative_eq!(*halfway.scale(), Vector3::new(2.0, 2.0, 2.0));
+        let expected = UnitQuaternion::from_euler_angles(0.0, std::f32::consts::FRAC_PI_4, 0.0);
+        assert!(halfway.rotation().angle_to(&expected) < 1.0e-5);
+
+        assert_relative_eq!(
+            interpolated.interpolate(1.0).unwrap().matrix(),
+            to.matrix(),
+            epsilon = 1.0e-6,
+        );
+    }
+
+    #[test]
+    fn first_push_does_not_blend() {
+        let mut to = Transform::default();
+        to.set_translation_xyz(1.0, 0.0, 0.0);
+
+        let mut interpolated = InterpolatedTransform::new();
+        interpolated.push(&to);
+
+        assert_eq!(interpolated.interpolate(0.0).unwrap(), to);
+    }
+}
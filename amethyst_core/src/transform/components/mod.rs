@@ -1,9 +1,14 @@
 //! Components for the transform processor.
 
 pub use self::{
+    interpolated_transform::InterpolatedTransform,
     parent::{HierarchyEvent, Parent, ParentHierarchy},
     transform::Transform,
 };
 
+#[cfg(feature = "saveload")]
+pub use self::parent::ParentData;
+
+mod interpolated_transform;
 mod parent;
 mod transform;
@@ -1,4 +1,10 @@
 use crate::ecs::prelude::{Component, DenseVecStorage, Entity, FlaggedStorage};
+#[cfg(feature = "saveload")]
+use crate::ecs::saveload::{ConvertSaveload, Marker};
+#[cfg(feature = "saveload")]
+use amethyst_error::{format_err, Error};
+#[cfg(feature = "saveload")]
+use serde::{Deserialize, Serialize};
 
 pub use specs_hierarchy::HierarchyEvent;
 use specs_hierarchy::{Hierarchy, Parent as HParent};
@@ -32,3 +38,37 @@ impl HParent for Parent {
         self.entity
     }
 }
+
+/// Serialized form of `Parent`, storing the marker of the parent entity.
+#[cfg(feature = "saveload")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "Parent")]
+pub struct ParentData<M> {
+    /// Marker of the parent entity
+    pub entity: M,
+}
+
+#[cfg(feature = "saveload")]
+impl<M: Marker> ConvertSaveload<M> for Parent {
+    type Data = ParentData<M>;
+    type Error = Error;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        ids(self.entity)
+            .map(|entity| ParentData { entity })
+            .ok_or_else(|| format_err!("Parent entity {:?} is not marked", self.entity))
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        let marker = data.entity;
+        ids(marker.clone())
+            .map(Parent::new)
+            .ok_or_else(|| format_err!("No entity found for parent marker {:?}", marker))
+    }
+}
@@ -0,0 +1,272 @@
+//! World-space access to `Transform`s.
+
+use amethyst_error::{format_err, Error};
+
+use crate::{
+    ecs::{
+        prelude::{Entity, World, WriteStorage},
+        shred::{ResourceId, SystemData},
+    },
+    math::{Matrix3, Matrix4, Point3, Rotation3, UnitQuaternion, Vector3, U1, U3},
+    transform::{Parent, Transform},
+};
+
+/// `SystemData` to read and write `Transform`s in world space.
+///
+/// The world-space values are computed from the local `Transform`s of the entity and its
+/// ancestors, so they are correct even before the `TransformSystem` updated the global matrices
+/// of this frame.
+///
+/// World-space rotations and scales are exact as long as the ancestors are scaled uniformly. With
+/// non-uniform scaling the closest rotation and scale are used.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// fn run(&mut self, (mut helper, targets): Self::SystemData) {
+///     for (entity, target) in targets {
+///         helper.set_global_translation(entity, target.position)?;
+///         helper.look_at_global(entity, target.look_at, Vector3::y())?;
+///     }
+/// }
+/// ```
+#[derive(SystemData)]
+#[allow(missing_debug_implementations)]
+pub struct TransformHelper<'a> {
+    /// The local transforms.
+    pub transforms: WriteStorage<'a, Transform>,
+    /// The parents defining the hierarchy.
+    pub parents: WriteStorage<'a, Parent>,
+}
+
+impl<'a> TransformHelper<'a> {
+    /// Returns the world-space matrix of `entity`, or `None` if it has no `Transform`.
+    pub fn global_matrix(&self, entity: Entity) -> Option<Matrix4<f32>> {
+        let mut matrix = self.transforms.get(entity)?.matrix();
+        let mut current = entity;
+        while let Some(parent) = self.parents.get(current) {
+            match self.transforms.get(parent.entity) {
+                Some(transform) => matrix = transform.matrix() * matrix,
+                // The `TransformSystem` stops at the first ancestor without a `Transform`.
+                None => break,
+            }
+            current = parent.entity;
+        }
+        Some(matrix)
+    }
+
+    /// Returns the world-space translation of `entity`.
+    pub fn global_translation(&self, entity: Entity) -> Option<Vector3<f32>> {
+        self.global_matrix(entity)
+            .map(|matrix| matrix.fixed_slice::<U3, U1>(0, 3).into_owned())
+    }
+
+    /// Returns the world-space rotation of `entity`.
+    pub fn global_rotation(&self, entity: Entity) -> Option<UnitQuaternion<f32>> {
+        self.global_matrix(entity)
+            .map(|matrix| decompose(&matrix).1)
+    }
+
+    /// Moves `entity` to the world-space `translation`, keeping its local rotation and scale.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `entity` has no `Transform` or its parent's matrix is not invertible.
+    pub fn set_global_translation(
+        &mut self,
+        entity: Entity,
+        translation: Vector3<f32>,
+    ) -> Result<(), Error> {
+        let inverse = self.inverse_parent_matrix(entity)?;
+        let local = inverse * Point3::from(translation).to_homogeneous();
+        self.transform_mut(entity)?.set_translation(local.xyz());
+        Ok(())
+    }
+
+    /// Rotates `entity` to the world-space `rotation`, keeping its local translation and scale.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `entity` has no `Transform`.
+    pub fn set_global_rotation(
+        &mut self,
+        entity: Entity,
+        rotation: UnitQuaternion<f32>,
+    ) -> Result<(), Error> {
+        let parent_rotation = decompose(&self.parent_matrix(entity)).1;
+        self.transform_mut(entity)?
+            .set_rotation(parent_rotation.inverse() * rotation);
+        Ok(())
+    }
+
+    /// Rotates `entity` to face the world-space `target`, like `Transform::face_towards`.
+    ///
+    /// `up` is the world-space direction the entity is rolled towards.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `entity` has no `Transform`.
+    pub fn look_at_global(
+        &mut self,
+        entity: Entity,
+        target: Vector3<f32>,
+        up: Vector3<f32>,
+    ) -> Result<(), Error> {
+        let position = self
+            .global_translation(entity)
+            .ok_or_else(|| no_transform(entity))?;
+        let rotation = UnitQuaternion::face_towards(&(position - target), &up);
+        self.set_global_rotation(entity, rotation)
+    }
+
+    /// Makes `parent` the parent of `child`, changing the local `Transform` of `child` so its
+    /// world-space pose stays the same.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `child` is `parent` or one of its ancestors, or if the matrix of
+    /// `parent` is not invertible.
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) -> Result<(), Error> {
+        let mut ancestor = Some(parent);
+        while let Some(entity) = ancestor {
+            if entity == child {
+                return Err(format_err!(
+                    "Cannot make {:?} the parent of its ancestor {:?}",
+                    parent,
+                    child
+                ));
+            }
+            ancestor = self.parents.get(entity).map(|parent| parent.entity);
+        }
+
+        if let Some(global) = self.global_matrix(child) {
+            let parent_global = self.global_matrix(parent).unwrap_or_else(Matrix4::identity);
+            let inverse = parent_global
+                .try_inverse()
+                .ok_or_else(|| format_err!("The matrix of {:?} is not invertible", parent))?;
+            let (translation, rotation, scale) = decompose(&(inverse * global));
+            let transform = self.transform_mut(child)?;
+            transform.set_translation(translation);
+            transform.set_rotation(rotation);
+            transform.set_scale(scale);
+        }
+        self.parents
+            .insert(child, Parent::new(parent))
+            .map_err(|_| format_err!("Cannot set the parent of dead entity {:?}", child))?;
+        Ok(())
+    }
+
+    fn parent_matrix(&self, entity: Entity) -> Matrix4<f32> {
+        self.parents
+            .get(entity)
+            .and_then(|parent| self.global_matrix(parent.entity))
+            .unwrap_or_else(Matrix4::identity)
+    }
+
+    fn inverse_parent_matrix(&self, entity: Entity) -> Result<Matrix4<f32>, Error> {
+        self.parent_matrix(entity)
+            .try_inverse()
+            .ok_or_else(|| format_err!("The parent matrix of {:?} is not invertible", entity))
+    }
+
+    fn transform_mut(&mut self, entity: Entity) -> Result<&mut Transform, Error> {
+        self.transforms
+            .get_mut(entity)
+            .ok_or_else(|| no_transform(entity))
+    }
+}
+
+fn no_transform(entity: Entity) -> Error {
+    format_err!("{:?} has no Transform", entity)
+}
+
+/// Splits an affine matrix into translation, rotation and scale.
+fn decompose(matrix: &Matrix4<f32>) -> (Vector3<f32>, UnitQuaternion<f32>, Vector3<f32>) {
+    let translation = matrix.fixed_slice::<U3, U1>(0, 3).into_owned();
+    let mut basis: Matrix3<f32> = matrix.fixed_slice::<U3, U3>(0, 0).into_owned();
+    let mut scale = Vector3::new(
+        basis.column(0).norm(),
+        basis.column(1).norm(),
+        basis.column(2).norm(),
+    );
+    // A mirrored basis can't be represented by a rotation.
+    if basis.determinant() < 0.0 {
+        scale.x = -scale.x;
+    }
+    for i in 0..3 {
+        if scale[i] != 0.0 {
+            let mut column = basis.column_mut(i);
+            column /= scale[i];
+        }
+    }
+    let rotation = UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(basis));
+    (translation, rotation, scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::*;
+
+    use super::*;
+    use crate::ecs::prelude::{Builder, WorldExt};
+
+    fn setup() -> (World, Entity, Entity) {
+        let mut world = World::new();
+        world.register::<Transform>();
+        world.register::<Parent>();
+
+        let mut parent_transform = Transform::default();
+        parent_transform.set_translation_xyz(1.0, 2.0, 3.0);
+        parent_transform.set_rotation_y_axis(std::f32::consts::FRAC_PI_2);
+        parent_transform.set_scale(Vector3::new(2.0, 2.0, 2.0));
+        let parent = world.create_entity().with(parent_transform).build();
+
+        let mut child_transform = Transform::default();
+        child_transform.set_translation_xyz(5.0, 0.0, 0.0);
+        let child = world.create_entity().with(child_transform).build();
+        (world, parent, child)
+    }
+
+    #[test]
+    fn set_parent_keeps_global_pose() {
+        let (world, parent, child) = setup();
+        let mut helper = world.system_data::<TransformHelper<'_>>();
+        let before = helper.global_matrix(child).unwrap();
+
+        helper.set_parent(child, parent).unwrap();
+        assert_eq!(helper.parents.get(child), Some(&Parent::new(parent)));
+        assert_relative_eq!(
+            helper.global_matrix(child).unwrap(),
+            before,
+            epsilon = 0.000_01
+        );
+        assert!(helper.set_parent(parent, child).is_err());
+    }
+
+    #[test]
+    fn global_setters_under_transformed_parent() {
+        let (world, parent, child) = setup();
+        let mut helper = world.system_data::<TransformHelper<'_>>();
+        helper.set_parent(child, parent).unwrap();
+
+        helper
+            .set_global_translation(child, Vector3::new(-4.0, 0.0, 2.0))
+            .unwrap();
+        assert_relative_eq!(
+            helper.global_translation(child).unwrap(),
+            Vector3::new(-4.0, 0.0, 2.0),
+            epsilon = 0.000_01
+        );
+
+        let rotation = UnitQuaternion::from_euler_angles(0.3, -0.2, 0.1);
+        helper.set_global_rotation(child, rotation).unwrap();
+        assert!(helper.global_rotation(child).unwrap().angle_to(&rotation) < 0.000_1);
+
+        helper
+            .look_at_global(child, Vector3::new(-4.0, 0.0, -8.0), Vector3::y())
+            .unwrap();
+        // Entities look along their negative Z axis.
+        let forward = helper.global_rotation(child).unwrap() * -Vector3::z();
+        assert_relative_eq!(forward, -Vector3::z(), epsilon = 0.000_01);
+    }
+}
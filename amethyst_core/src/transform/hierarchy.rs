@@ -0,0 +1,163 @@
+//! Queries over the `Parent` hierarchy.
+
+use std::collections::VecDeque;
+
+use crate::{
+    ecs::{
+        error::WrongGeneration,
+        prelude::{Entities, Entity, ReadExpect, ReadStorage, World},
+        shred::{ResourceId, SystemData},
+    },
+    transform::{Parent, ParentHierarchy},
+};
+
+/// `SystemData` to walk the `Parent` hierarchy and delete whole subtrees.
+///
+/// Descendants are looked up in the `ParentHierarchy`, which is updated by the
+/// `parent_hierarchy_system` of the `TransformBundle`. `Parent` components inserted since it last
+/// ran are not included yet. Ancestors are read from the `Parent` components directly.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// fn run(&mut self, (hierarchy, closed): Self::SystemData) {
+///     for panel in closed.iter() {
+///         hierarchy.despawn_recursive(*panel)?;
+///     }
+/// }
+/// ```
+#[derive(SystemData)]
+#[allow(missing_debug_implementations)]
+pub struct HierarchyQuery<'a> {
+    /// The entities, used to delete subtrees.
+    pub entities: Entities<'a>,
+    /// The hierarchy built from the `Parent` components.
+    pub hierarchy: ReadExpect<'a, ParentHierarchy>,
+    /// The parents of the entities.
+    pub parents: ReadStorage<'a, Parent>,
+}
+
+impl<'a> HierarchyQuery<'a> {
+    /// Returns the direct children of `entity`.
+    pub fn children(&self, entity: Entity) -> &[Entity] {
+        self.hierarchy.children(entity)
+    }
+
+    /// Returns the parent of `entity`.
+    pub fn parent(&self, entity: Entity) -> Option<Entity> {
+        self.parents.get(entity).map(|parent| parent.entity)
+    }
+
+    /// Iterates over all descendants of `entity` depth-first, visiting each entity before its
+    /// children. `entity` itself is not included.
+    pub fn descendants_depth_first(&self, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+        let mut stack = self
+            .children(entity)
+            .iter()
+            .rev()
+            .cloned()
+            .collect::<Vec<_>>();
+        std::iter::from_fn(move || {
+            let next = stack.pop()?;
+            stack.extend(self.children(next).iter().rev().cloned());
+            Some(next)
+        })
+    }
+
+    /// Iterates over all descendants of `entity` breadth-first, visiting all children before any
+    /// grandchildren. `entity` itself is not included.
+    pub fn descendants_breadth_first(&self, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+        let mut queue = self
+            .children(entity)
+            .iter()
+            .cloned()
+            .collect::<VecDeque<_>>();
+        std::iter::from_fn(move || {
+            let next = queue.pop_front()?;
+            queue.extend(self.children(next).iter().cloned());
+            Some(next)
+        })
+    }
+
+    /// Iterates over the parent of `entity`, its parent and so on up to the root.
+    pub fn ancestors(&self, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+        let mut current = entity;
+        std::iter::from_fn(move || {
+            current = self.parent(current)?;
+            Some(current)
+        })
+    }
+
+    /// Returns the topmost ancestor of `entity`, or `entity` itself if it has no parent.
+    pub fn root(&self, entity: Entity) -> Entity {
+        self.ancestors(entity).last().unwrap_or(entity)
+    }
+
+    /// Deletes `entity` and all its descendants.
+    ///
+    /// All entities are deleted in this frame, without waiting for the `TransformSystem` to
+    /// remove the children of deleted parents.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `entity` is already dead.
+    pub fn despawn_recursive(&self, entity: Entity) -> Result<(), WrongGeneration> {
+        // Collect first, deleting doesn't change the hierarchy until it is maintained.
+        let descendants = self.descendants_depth_first(entity).collect::<Vec<_>>();
+        self.entities.delete(entity)?;
+        for descendant in descendants {
+            // A descendant may have been deleted already, which is fine.
+            let _ = self.entities.delete(descendant);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs_hierarchy::HierarchySystem;
+
+    use super::*;
+    use crate::ecs::prelude::{Builder, RunNow, System, WorldExt};
+
+    #[test]
+    fn walks_and_despawns_subtree() {
+        let mut world = World::new();
+        let mut hierarchy_system = HierarchySystem::<Parent>::new(&mut world);
+        System::setup(&mut hierarchy_system, &mut world);
+
+        let root = world.create_entity().build();
+        let a = world.create_entity().with(Parent::new(root)).build();
+        let b = world.create_entity().with(Parent::new(root)).build();
+        let a1 = world.create_entity().with(Parent::new(a)).build();
+        let b1 = world.create_entity().with(Parent::new(b)).build();
+        let other = world.create_entity().build();
+        hierarchy_system.run_now(&world);
+
+        {
+            let query = world.system_data::<HierarchyQuery<'_>>();
+            let mut depth_first = query.descendants_depth_first(root).collect::<Vec<_>>();
+            let breadth_first = query.descendants_breadth_first(root).collect::<Vec<_>>();
+            assert_eq!(breadth_first.len(), 4);
+            assert!(breadth_first[..2].contains(&a) && breadth_first[..2].contains(&b));
+            assert!(breadth_first[2..].contains(&a1) && breadth_first[2..].contains(&b1));
+            let a_index = depth_first.iter().position(|e| *e == a).unwrap();
+            assert_eq!(depth_first[a_index + 1], a1);
+            depth_first.sort();
+            let mut expected = vec![a, b, a1, b1];
+            expected.sort();
+            assert_eq!(depth_first, expected);
+
+            assert_eq!(query.ancestors(b1).collect::<Vec<_>>(), vec![b, root]);
+            assert_eq!(query.root(a1), root);
+            assert_eq!(query.root(other), other);
+
+            query.despawn_recursive(a).unwrap();
+        }
+        world.maintain();
+        assert!(!world.is_alive(a));
+        assert!(!world.is_alive(a1));
+        assert!(world.is_alive(root));
+        assert!(world.is_alive(b1));
+    }
+}
@@ -1,7 +1,12 @@
 //! `amethyst` transform ecs module
 
-pub use self::{bundle::TransformBundle, components::*, systems::*};
+pub use self::{
+    bundle::TransformBundle, components::*, helper::TransformHelper, hierarchy::HierarchyQuery,
+    systems::*,
+};
 
 pub mod bundle;
 pub mod components;
+pub mod helper;
+pub mod hierarchy;
 pub mod systems;
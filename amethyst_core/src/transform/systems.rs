@@ -4,14 +4,15 @@ use crate::{
     ecs::{
         hibitset::BitSet,
         prelude::{
-            ComponentEvent, Entities, Join, ReadExpect, ReadStorage, ReaderId, System, SystemData,
-            World, WriteStorage,
+            ComponentEvent, Entities, Entity, Join, Read, ReadExpect, ReadStorage, ReaderId,
+            System, SystemData, World, Write, WriteStorage,
         },
     },
+    timing::Time,
     SystemDesc,
 };
 
-use crate::transform::{HierarchyEvent, Parent, ParentHierarchy, Transform};
+use crate::transform::{HierarchyEvent, InterpolatedTransform, Parent, ParentHierarchy, Transform};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
@@ -61,8 +62,9 @@ impl<'a> System<'a> for TransformSystem {
         ReadExpect<'a, ParentHierarchy>,
         WriteStorage<'a, Transform>,
         ReadStorage<'a, Parent>,
+        Option<Read<'a, BlendedTransforms>>,
     );
-    fn run(&mut self, (entities, hierarchy, mut locals, parents): Self::SystemData) {
+    fn run(&mut self, (entities, hierarchy, mut locals, parents, blended): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("transform_system");
 
@@ -72,6 +74,10 @@ impl<'a> System<'a> for TransformSystem {
             .channel()
             .read(&mut self.locals_events_id)
             .for_each(|event| match event {
+                ComponentEvent::Modified(id)
+                    if blended
+                        .as_ref()
+                        .map_or(false, |blended| blended.entities.contains(*id)) => {}
                 ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => {
                     self.local_modified.add(*id);
                 }
@@ -124,7 +130,7 @@ impl<'a> System<'a> for TransformSystem {
                         }
                         let local = local.unwrap();
                         if let Some(parent_global) = locals.get(parent.entity) {
-                            (parent_global.global_matrix * local.matrix())
+                            parent_global.global_matrix * local.matrix()
                         } else {
                             local.matrix()
                         }
@@ -140,6 +146,120 @@ impl<'a> System<'a> for TransformSystem {
     }
 }
 
+/// Entities whose `global_matrix` was blended by the `InterpolatedTransformSystem` in the last
+/// frame.
+///
+/// The blended matrices are written every frame, so the `TransformSystem` ignores the
+/// modification events of these entities instead of recomputing them.
+#[derive(Debug, Default)]
+pub struct BlendedTransforms {
+    entities: BitSet,
+}
+
+impl BlendedTransforms {
+    /// Checks whether the `global_matrix` of `entity` was blended in the last frame.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(entity.id())
+    }
+}
+
+/// Blends the `global_matrix` of entities with an `InterpolatedTransform` between the two latest
+/// fixed updates.
+///
+/// Must run after the `TransformSystem`. The global matrices of children of interpolated entities
+/// are recomputed from the blended parent, so whole hierarchies move smoothly.
+#[derive(Debug, Default)]
+pub struct InterpolatedTransformSystem {
+    last_fixed_frame: u64,
+}
+
+impl InterpolatedTransformSystem {
+    /// Creates a new interpolated transform processor.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<'a> System<'a> for InterpolatedTransformSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        ReadExpect<'a, ParentHierarchy>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+        WriteStorage<'a, InterpolatedTransform>,
+        Write<'a, BlendedTransforms>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, hierarchy, mut locals, parents, mut interpolated, mut blended): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("interpolated_transform_system");
+
+        if time.fixed_frame_number() != self.last_fixed_frame {
+            self.last_fixed_frame = time.fixed_frame_number();
+            for (local, interpolated) in (&locals, &mut interpolated).join() {
+                interpolated.push(local);
+            }
+        }
+
+        let alpha = time.interpolation_alpha();
+        // The `TransformSystem` skipped the changes of the entities blended in the last frame,
+        // so those which aren't blended anymore get their global matrix recomputed here.
+        let previous = std::mem::replace(&mut blended.entities, BitSet::new());
+        let blended = &mut blended.entities;
+
+        for (entity, interpolated, _) in (&*entities, &interpolated, !&parents).join() {
+            if let Some(blended_local) = interpolated.interpolate(alpha) {
+                if let Some(local) = locals.get_mut(entity) {
+                    local.global_matrix = blended_local.matrix();
+                    blended.add(entity.id());
+                }
+            }
+        }
+        for (entity, _, _) in (&*entities, &previous, !&parents).join() {
+            if !blended.contains(entity.id()) {
+                if let Some(local) = locals.get_mut(entity) {
+                    local.global_matrix = local.matrix();
+                }
+            }
+        }
+
+        for entity in hierarchy.all() {
+            let parent = match parents.get(*entity) {
+                Some(parent) => parent.entity,
+                None => continue,
+            };
+            let blended_local = interpolated
+                .get(*entity)
+                .and_then(|interpolated| interpolated.interpolate(alpha));
+            let parent_blended = blended.contains(parent.id());
+            let local_matrix = match &blended_local {
+                Some(blended_local) => blended_local.matrix(),
+                None if parent_blended || previous.contains(entity.id()) => {
+                    match locals.get(*entity) {
+                        Some(local) => local.matrix(),
+                        None => continue,
+                    }
+                }
+                None => continue,
+            };
+            let global_matrix = match locals.get(parent) {
+                Some(parent_local) => parent_local.global_matrix * local_matrix,
+                None => local_matrix,
+            };
+            if let Some(local) = locals.get_mut(*entity) {
+                local.global_matrix = global_matrix;
+                if blended_local.is_some() || parent_blended {
+                    blended.add(entity.id());
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
@@ -148,7 +268,11 @@ mod tests {
             shred::RunNow,
         },
         math::{Matrix4, Quaternion, Unit, Vector3},
-        transform::{Parent, Transform, TransformSystem, TransformSystemDesc},
+        timing::Time,
+        transform::{
+            InterpolatedTransform, InterpolatedTransformSystem, Parent, Transform, TransformSystem,
+            TransformSystemDesc,
+        },
         SystemDesc,
     };
     use specs_hierarchy::{Hierarchy, HierarchySystem};
@@ -486,4 +610,68 @@ mod tests {
             }
         }
     }
+
+    fn advance_time(world: &mut World, delta_seconds: f32) {
+        let mut time = world.write_resource::<Time>();
+        time.set_delta_seconds(delta_seconds);
+        time.start_fixed_update();
+        while time.step_fixed_update() {}
+        time.finish_fixed_update();
+    }
+
+    #[test]
+    fn interpolated_between_fixed_updates() {
+        let (mut world, mut hs, mut system) = transform_world();
+        let mut its = InterpolatedTransformSystem::new();
+        its.setup(&mut world);
+        world.insert(Time::default());
+        let fixed_seconds = world.read_resource::<Time>().fixed_seconds();
+
+        let parent = world
+            .create_entity()
+            .with(Transform::default())
+            .with(InterpolatedTransform::new())
+            .build();
+        let mut child_local = Transform::default();
+        child_local.set_translation_xyz(0.0, 1.0, 0.0);
+        let child = world
+            .create_entity()
+            .with(child_local)
+            .with(Parent { entity: parent })
+            .build();
+
+        let mut run = |world: &mut World, delta_seconds| {
+            advance_time(world, delta_seconds);
+            hs.run_now(world);
+            system.run_now(world);
+            its.run_now(world);
+            world.maintain();
+        };
+
+        // The first fixed update records the starting pose.
+        run(&mut world, fixed_seconds);
+
+        // The second one records the new pose, which is shown once alpha reaches 1.
+        world
+            .write_storage::<Transform>()
+            .get_mut(parent)
+            .unwrap()
+            .set_translation_x(10.0);
+        run(&mut world, fixed_seconds);
+        assert_eq!(
+            world
+                .read_storage::<Transform>()
+                .get(parent)
+                .unwrap()
+                .global_matrix()[(0, 3)],
+            0.0
+        );
+
+        // Halfway to the next fixed update both parent and child are halfway there.
+        run(&mut world, fixed_seconds / 2.0);
+        let transforms = world.read_storage::<Transform>();
+        assert_eq!(transforms.get(parent).unwrap().global_matrix()[(0, 3)], 5.0);
+        assert_eq!(transforms.get(child).unwrap().global_matrix()[(0, 3)], 5.0);
+        assert_eq!(transforms.get(child).unwrap().global_matrix()[(1, 3)], 1.0);
+    }
 }
@@ -25,7 +25,7 @@ use amethyst_rendy::{
     formats::mtl::MaterialPrefab, rendy::mesh::MeshBuilder, types::Mesh, visibility::BoundingSphere,
 };
 use derivative::Derivative;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::{collections::HashMap, ops::Range};
 
 pub use crate::format::GltfSceneFormat;
@@ -43,14 +43,18 @@ pub type GltfSceneLoaderSystem = PrefabLoaderSystem<GltfPrefab>;
 pub type GltfSceneAsset = Prefab<GltfPrefab>;
 
 /// `PrefabData` for loading Gltf files.
-#[derive(Debug, Default)]
+///
+/// Serializable so imported scenes can be stored in an `ImportCache`.
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct GltfPrefab {
     /// `Transform` will almost always be placed, the only exception is for the main `Entity` for
     /// certain scenarios (based on the data in the Gltf file)
     pub transform: Option<Transform>,
     /// `MeshData` is placed on all `Entity`s with graphics primitives
+    #[serde(deserialize_with = "deserialize_owned_mesh")]
     pub mesh: Option<MeshBuilder<'static>>,
     /// Mesh handle after sub asset loading is done
+    #[serde(skip)]
     pub mesh_handle: Option<Handle<Mesh>>,
     /// `Material` is placed on all `Entity`s with graphics primitives with material
     pub material: Option<MaterialPrefab>,
@@ -67,6 +71,15 @@ pub struct GltfPrefab {
     pub(crate) material_id: Option<usize>,
 }
 
+/// Deserializes a mesh without borrowing from the deserializer.
+fn deserialize_owned_mesh<'de, D>(deserializer: D) -> Result<Option<MeshBuilder<'static>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<MeshBuilder<'de>>::deserialize(deserializer)
+        .map(|mesh| mesh.map(MeshBuilder::into_owned))
+}
+
 impl GltfPrefab {
     /// Move the scene so the center of the bounding box is at the given `target` location.
     pub fn move_to(&mut self, target: Point3<f32>) {
@@ -93,7 +106,7 @@ impl GltfPrefab {
 }
 
 /// A GLTF node extent
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GltfNodeExtent {
     /// The beginning of this extent
     pub start: Point3<f32>,
@@ -175,7 +188,7 @@ impl From<Range<[f32; 3]>> for GltfNodeExtent {
 }
 
 /// Used during gltf loading to contain the materials used from scenes in the file
-#[derive(Debug, Derivative)]
+#[derive(Debug, Derivative, Serialize, Deserialize)]
 #[derivative(Default(bound = ""))]
 pub struct GltfMaterialSet {
     pub(crate) materials: HashMap<usize, MaterialPrefab>,
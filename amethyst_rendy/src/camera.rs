@@ -3,7 +3,7 @@
 use amethyst_assets::PrefabData;
 use amethyst_core::{
     ecs::prelude::{Component, Entity, HashMapStorage, Write, WriteStorage},
-    geometry::Ray,
+    geometry::{Frustum, Ray},
     math::{Matrix4, Point2, Point3, Vector2},
     transform::components::Transform,
 };
@@ -690,6 +690,11 @@ impl Camera {
     pub fn set_projection(&mut self, new: Projection) {
         self.inner = new;
     }
+
+    /// Returns the world-space view frustum of this camera placed by `camera_transform`.
+    pub fn frustum(&self, camera_transform: &Transform) -> Frustum<f32> {
+        Frustum::from_camera(self.as_matrix(), camera_transform)
+    }
 }
 
 impl Component for Camera {
@@ -7,11 +7,13 @@ use amethyst_core::{
     ecs::{
         hibitset::BitSet,
         prelude::{
-            Component, DenseVecStorage, Entities, Entity, Join, Read, ReadStorage, System, Write,
+            Component, DenseVecStorage, Entities, Entity, FlaggedStorage, Join, Read, ReadStorage,
+            System, Write,
         },
     },
+    geometry::Sphere,
     math::{convert, distance_squared, Matrix4, Point3, Vector4},
-    Hidden, HiddenPropagate, Transform,
+    Hidden, HiddenPropagate, SpatialBounds, Transform,
 };
 
 use serde::{Deserialize, Serialize};
@@ -75,7 +77,13 @@ impl BoundingSphere {
 }
 
 impl Component for BoundingSphere {
-    type Storage = DenseVecStorage<Self>;
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+impl SpatialBounds for BoundingSphere {
+    fn bounding_sphere(&self) -> Sphere<f32> {
+        Sphere::new(self.center, self.radius)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1,5 +1,7 @@
+use std::sync::Arc;
+
 use glyph_brush::rusttype::Font;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use amethyst_assets::{Asset, Format, Handle, ProcessableAsset, ProcessingState};
 use amethyst_core::ecs::prelude::VecStorage;
@@ -13,9 +15,35 @@ pub struct FontAsset(pub Font<'static>);
 /// A handle to font data stored with `amethyst_assets`.
 pub type FontHandle = Handle<FontAsset>;
 
+/// A font file, serialized as the file contents so it can be stored in an `ImportCache`.
 #[derive(Clone)]
 #[allow(missing_debug_implementations)]
-pub struct FontData(Font<'static>);
+pub struct FontData {
+    font: Font<'static>,
+    bytes: Arc<[u8]>,
+}
+
+impl FontData {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, Error> {
+        let bytes = Arc::<[u8]>::from(bytes);
+        let font =
+            Font::from_bytes(bytes.clone()).with_context(|_| format_err!("Font parsing error"))?;
+        Ok(FontData { font, bytes })
+    }
+}
+
+impl Serialize for FontData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.bytes.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FontData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        FontData::from_bytes(bytes).map_err(serde::de::Error::custom)
+    }
+}
 
 amethyst_assets::register_format_type!(FontData);
 
@@ -27,7 +55,7 @@ impl Asset for FontAsset {
 
 impl ProcessableAsset for FontAsset {
     fn process(data: FontData) -> Result<ProcessingState<FontAsset>, Error> {
-        Ok(ProcessingState::Loaded(FontAsset(data.0)))
+        Ok(ProcessingState::Loaded(FontAsset(data.font)))
     }
 }
 
@@ -46,8 +74,6 @@ impl Format<FontData> for TtfFormat {
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<FontData, Error> {
-        Font::from_bytes(bytes)
-            .map(FontData)
-            .with_context(|_| format_err!("Font parsing error"))
+        FontData::from_bytes(bytes)
     }
 }
@@ -20,3 +20,4 @@ pub mod removal;
 pub mod scene;
 pub mod tag;
 pub mod time_destroy;
+pub mod timers;
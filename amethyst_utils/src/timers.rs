@@ -0,0 +1,469 @@
+//! Named timers, timer components and time-scale groups.
+//!
+//! Add the `TimersBundle` to tick every `Timer` component and every timer started on the
+//! `Timers` resource. Expired timers write `TimerFinished` events, and timers started with
+//! `Timers::start_with_callback` run their callback with `&mut World` at the end of the frame.
+
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+
+use amethyst_core::{
+    ecs::prelude::{
+        Component, DenseVecStorage, DispatcherBuilder, Entities, Entity, Join, Read, System, World,
+        Write, WriteStorage,
+    },
+    shrev::EventChannel,
+    timing::Time,
+    SystemBundle,
+};
+use amethyst_error::Error;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Callback run with `&mut World` every time a timer started with
+/// `Timers::start_with_callback` expires.
+pub type TimerCallback = Arc<dyn Fn(&mut World) + Send + Sync>;
+
+/// Whether a `Timer` stops after expiring once or starts over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimerMode {
+    /// The timer expires once and is finished afterwards.
+    Once,
+    /// The timer restarts every time it expires.
+    Repeating,
+}
+
+/// A timer counting up to its duration.
+///
+/// Attached to an entity, the `TimerSystem` ticks it every frame and writes a `TimerFinished`
+/// event with the entity when it expires. A finished `Once` timer stays on the entity until it is
+/// reset or removed.
+///
+/// The timer advances by `Time::delta_time`, multiplied by the scale of its group if it has one.
+/// See `Timers::set_group_scale`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timer {
+    duration: Duration,
+    elapsed: Duration,
+    mode: TimerMode,
+    paused: bool,
+    finished: bool,
+    group: Option<String>,
+}
+
+impl Component for Timer {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Timer {
+    /// Creates a timer with the given mode.
+    pub fn new(duration: Duration, mode: TimerMode) -> Self {
+        Timer {
+            duration,
+            elapsed: Duration::from_secs(0),
+            mode,
+            paused: false,
+            finished: false,
+            group: None,
+        }
+    }
+
+    /// Creates a timer which expires once after `duration`.
+    pub fn once(duration: Duration) -> Self {
+        Self::new(duration, TimerMode::Once)
+    }
+
+    /// Creates a timer which expires every `duration`.
+    pub fn repeating(duration: Duration) -> Self {
+        Self::new(duration, TimerMode::Repeating)
+    }
+
+    /// Puts the timer into the time-scale group `group`.
+    pub fn with_group<S: Into<String>>(mut self, group: S) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    /// Returns the duration of the timer.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Returns the time elapsed since the timer was started or last expired.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the time left until the timer expires.
+    pub fn remaining(&self) -> Duration {
+        self.duration.checked_sub(self.elapsed).unwrap_or_default()
+    }
+
+    /// Returns the mode of the timer.
+    pub fn mode(&self) -> TimerMode {
+        self.mode
+    }
+
+    /// Returns the time-scale group of the timer.
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_ref().map(String::as_str)
+    }
+
+    /// Returns `true` if the timer is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Returns `true` if a `Once` timer has expired.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Stops the timer from advancing.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Lets a paused timer advance again.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Starts the timer over, including a finished `Once` timer.
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::from_secs(0);
+        self.finished = false;
+    }
+
+    /// Advances the timer by `delta` and returns how often it expired.
+    ///
+    /// A `Repeating` timer can expire several times if `delta` is longer than its duration.
+    pub fn tick(&mut self, delta: Duration) -> u32 {
+        if self.paused || self.finished {
+            return 0;
+        }
+        self.elapsed += delta;
+        if self.elapsed < self.duration {
+            return 0;
+        }
+        match self.mode {
+            TimerMode::Once => {
+                self.elapsed = self.duration;
+                self.finished = true;
+                1
+            }
+            TimerMode::Repeating if self.duration == Duration::from_secs(0) => {
+                self.elapsed = Duration::from_secs(0);
+                1
+            }
+            TimerMode::Repeating => {
+                let times = (self.elapsed.as_nanos() / self.duration.as_nanos()) as u32;
+                self.elapsed -= self.duration * times;
+                times
+            }
+        }
+    }
+}
+
+/// What a `TimerFinished` event refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimerSource {
+    /// A timer started on the `Timers` resource under this name.
+    Named(String),
+    /// A `Timer` component attached to this entity.
+    Entity(Entity),
+}
+
+/// Event written by the `TimerSystem` every time a timer expires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimerFinished {
+    /// The expired timer.
+    pub source: TimerSource,
+}
+
+struct NamedTimer {
+    timer: Timer,
+    callback: Option<TimerCallback>,
+}
+
+/// Resource holding named timers and the scales of the time-scale groups.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use amethyst_core::ecs::{World, WorldExt};
+/// # use amethyst_utils::timers::{Timer, Timers};
+/// # let mut world = World::new();
+/// # world.insert(Timers::default());
+/// let mut timers = world.write_resource::<Timers>();
+/// timers.start("spawn_wave", Timer::repeating(Duration::from_secs(30)).with_group("gameplay"));
+/// timers.start_with_callback("dash_cooldown", Timer::once(Duration::from_secs(2)), |_world| {
+///     // Enable dashing again.
+/// });
+/// // Slow motion for everything in the "gameplay" group.
+/// timers.set_group_scale("gameplay", 0.5);
+/// ```
+#[derive(Default)]
+pub struct Timers {
+    timers: HashMap<String, NamedTimer>,
+    group_scales: HashMap<String, f32>,
+    ready: Vec<TimerCallback>,
+}
+
+impl fmt::Debug for Timers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timers")
+            .field(
+                "timers",
+                &self
+                    .timers
+                    .iter()
+                    .map(|(name, named)| (name, &named.timer))
+                    .collect::<HashMap<_, _>>(),
+            )
+            .field("group_scales", &self.group_scales)
+            .field("ready", &self.ready.len())
+            .finish()
+    }
+}
+
+impl Timers {
+    /// Starts a timer under `name`, replacing any timer with the same name.
+    ///
+    /// `Once` timers are removed after they expire.
+    pub fn start<S: Into<String>>(&mut self, name: S, timer: Timer) {
+        self.timers.insert(
+            name.into(),
+            NamedTimer {
+                timer,
+                callback: None,
+            },
+        );
+    }
+
+    /// Starts a timer under `name` which runs `callback` every time it expires.
+    ///
+    /// The callback runs at the end of the frame, after the systems.
+    pub fn start_with_callback<S, F>(&mut self, name: S, timer: Timer, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(&mut World) + Send + Sync + 'static,
+    {
+        self.timers.insert(
+            name.into(),
+            NamedTimer {
+                timer,
+                callback: Some(Arc::new(callback)),
+            },
+        );
+    }
+
+    /// Returns the timer named `name`.
+    pub fn get(&self, name: &str) -> Option<&Timer> {
+        self.timers.get(name).map(|named| &named.timer)
+    }
+
+    /// Returns the timer named `name` mutably, e.g. to pause or reset it.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Timer> {
+        self.timers.get_mut(name).map(|named| &mut named.timer)
+    }
+
+    /// Removes the timer named `name` without it expiring.
+    pub fn cancel(&mut self, name: &str) -> Option<Timer> {
+        self.timers.remove(name).map(|named| named.timer)
+    }
+
+    /// Returns the scale of the time-scale group `group`. Defaults to `1.0`.
+    pub fn group_scale(&self, group: &str) -> f32 {
+        group_scale(&self.group_scales, group)
+    }
+
+    /// Sets the scale of the time-scale group `group`.
+    ///
+    /// Timers of the group advance by `Time::delta_time` multiplied with this scale, so the group
+    /// scale applies on top of `Time::time_scale`. A scale of `0.0` pauses the whole group,
+    /// negative scales are clamped to `0.0`.
+    pub fn set_group_scale<S: Into<String>>(&mut self, group: S, scale: f32) {
+        self.group_scales.insert(group.into(), scale.max(0.0));
+    }
+
+    /// Runs the callbacks of the timers which expired since the last call.
+    ///
+    /// The application calls this once per frame if the `Timers` resource is present.
+    pub fn run_callbacks(world: &mut World) {
+        let ready = match world.try_fetch_mut::<Timers>() {
+            Some(mut timers) => timers.ready.drain(..).collect::<Vec<_>>(),
+            None => return,
+        };
+        for callback in ready {
+            callback(world);
+        }
+    }
+}
+
+fn group_scale(group_scales: &HashMap<String, f32>, group: &str) -> f32 {
+    group_scales.get(group).cloned().unwrap_or(1.0)
+}
+
+fn scaled_delta(group_scales: &HashMap<String, f32>, delta: Duration, timer: &Timer) -> Duration {
+    match timer.group() {
+        Some(group) => delta.mul_f32(group_scale(group_scales, group)),
+        None => delta,
+    }
+}
+
+/// Ticks the `Timer` components and the timers of the `Timers` resource.
+#[derive(Debug)]
+pub struct TimerSystem;
+
+impl<'a> System<'a> for TimerSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Write<'a, Timers>,
+        WriteStorage<'a, Timer>,
+        Write<'a, EventChannel<TimerFinished>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, mut timers, mut components, mut finished): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("timer_system");
+
+        let delta = time.delta_time();
+        let Timers {
+            timers,
+            group_scales,
+            ready,
+        } = &mut *timers;
+
+        for (entity, timer) in (&entities, &mut components).join() {
+            let times = timer.tick(scaled_delta(group_scales, delta, timer));
+            for _ in 0..times {
+                finished.single_write(TimerFinished {
+                    source: TimerSource::Entity(entity),
+                });
+            }
+        }
+
+        let mut expired = Vec::new();
+        for (name, named) in timers.iter_mut() {
+            let times = named
+                .timer
+                .tick(scaled_delta(group_scales, delta, &named.timer));
+            for _ in 0..times {
+                finished.single_write(TimerFinished {
+                    source: TimerSource::Named(name.clone()),
+                });
+                if let Some(callback) = &named.callback {
+                    ready.push(callback.clone());
+                }
+            }
+            if named.timer.is_finished() {
+                expired.push(name.clone());
+            }
+        }
+        for name in expired {
+            timers.remove(&name);
+        }
+    }
+}
+
+/// Adds the `TimerSystem`.
+#[derive(Default, Debug)]
+pub struct TimersBundle;
+
+impl<'a, 'b> SystemBundle<'a, 'b> for TimersBundle {
+    fn build(
+        self,
+        _world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(TimerSystem, "timer_system", &[]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use amethyst_core::ecs::{Builder, RunNow, WorldExt};
+
+    use super::*;
+
+    fn run_frame(world: &mut World, delta: Duration) {
+        world.write_resource::<Time>().set_delta_time(delta);
+        TimerSystem.run_now(world);
+        world.maintain();
+        Timers::run_callbacks(world);
+    }
+
+    #[test]
+    fn repeating_and_once_timers_expire() {
+        let mut world = World::new();
+        world.register::<Timer>();
+        world.insert(Time::default());
+        world.insert(Timers::default());
+        world.insert(EventChannel::<TimerFinished>::new());
+        let mut reader = world
+            .write_resource::<EventChannel<TimerFinished>>()
+            .register_reader();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        {
+            let mut timers = world.write_resource::<Timers>();
+            timers.start_with_callback("wave", Timer::once(Duration::from_secs(2)), move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+            timers.start(
+                "slow",
+                Timer::repeating(Duration::from_secs(1)).with_group("slow"),
+            );
+            timers.set_group_scale("slow", 0.5);
+        }
+        let entity = world
+            .create_entity()
+            .with(Timer::repeating(Duration::from_secs(1)))
+            .build();
+
+        run_frame(&mut world, Duration::from_millis(1500));
+        let events = world
+            .read_resource::<EventChannel<TimerFinished>>()
+            .read(&mut reader)
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![TimerFinished {
+                source: TimerSource::Entity(entity),
+            }]
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        run_frame(&mut world, Duration::from_millis(1500));
+        let mut events = world
+            .read_resource::<EventChannel<TimerFinished>>()
+            .read(&mut reader)
+            .map(|event| event.source.clone())
+            .collect::<Vec<_>>();
+        events.sort_by_key(|source| format!("{:?}", source));
+        assert_eq!(
+            events,
+            vec![
+                TimerSource::Entity(entity),
+                TimerSource::Entity(entity),
+                TimerSource::Named("slow".to_string()),
+                TimerSource::Named("wave".to_string()),
+            ]
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(world.read_resource::<Timers>().get("wave").is_none());
+        assert!(world.read_resource::<Timers>().get("slow").is_some());
+    }
+}
@@ -29,6 +29,7 @@ impl WindowSystem {
     /// Builds and spawns a new `Window`, using the provided `DisplayConfig` and `EventsLoop` as
     /// sources. Returns a new `WindowSystem`
     pub fn from_config(world: &mut World, events_loop: &EventsLoop, config: DisplayConfig) -> Self {
+        world.insert(config.clone());
         let window = config
             .into_window_builder(events_loop)
             .build(events_loop)
@@ -1,6 +1,12 @@
 //! The core engine framework.
 
-use std::{env, marker::PhantomData, path::Path, sync::Arc, time::Duration};
+use std::{
+    env,
+    marker::PhantomData,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use crate::shred::Resource;
 use derivative::Derivative;
@@ -19,15 +25,23 @@ use crate::{
     core::{
         frame_limiter::{FrameLimiter, FrameRateLimitConfig, FrameRateLimitStrategy},
         shrev::{EventChannel, ReaderId},
-        timing::{Stopwatch, Time},
-        ArcThreadPool, EventReader, Named,
+        timing::{Stopwatch, Time, TimeSource},
+        ArcThreadPool, EventReader, Named, Rng, RngConfig,
     },
     ecs::prelude::{Component, Read, World, WorldExt, Write},
     error::Error,
     game_data::{DataDispose, DataInit},
+    logger::{LogBuffer, LoggerHandles},
     state::{State, StateData, StateMachine, TransEvent},
     state_event::{StateEvent, StateEventReader},
+    tasks::Tasks,
     ui::UiEvent,
+    utils::timers::Timers,
+};
+#[cfg(feature = "crash_report")]
+use crate::{
+    crash_report::{CrashContext, CrashReporter},
+    logger::LoggerConfig,
 };
 
 /// `CoreApplication` is the application implementation for the game engine. This is fully generic
@@ -60,9 +74,65 @@ where
     trans_reader_id: ReaderId<TransEvent<T, E>>,
     states: StateMachine<'a, T, E>,
     ignore_window_close: bool,
+    time_source: TimeSource,
+    #[cfg(feature = "crash_report")]
+    #[derivative(Debug = "ignore")]
+    crash_context: Option<CrashContext>,
     data: T,
 }
 
+/// Statistics about a single frame of the main loop.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameStats {
+    /// The frame number, as reported by `Time::frame_number` while the frame was running.
+    pub frame_number: u64,
+    /// The delta time the frame was simulated with.
+    pub delta_time: Duration,
+    /// The real time it took to run the frame, excluding frame limiting.
+    pub frame_time: Duration,
+    /// The number of fixed updates that ran during the frame.
+    pub fixed_updates: u32,
+}
+
+/// Per-frame statistics of a run started with
+/// [`run_frames`](struct.CoreApplication.html#method.run_frames) or
+/// [`run_until`](struct.CoreApplication.html#method.run_until).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunReport {
+    /// Statistics for every frame that was run, in order.
+    pub frames: Vec<FrameStats>,
+}
+
+impl RunReport {
+    /// The number of frames that were run.
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The total real time spent running frames.
+    pub fn total_frame_time(&self) -> Duration {
+        self.frames.iter().map(|f| f.frame_time).sum()
+    }
+
+    /// The average real time spent per frame, zero if no frame was run.
+    pub fn average_frame_time(&self) -> Duration {
+        if self.frames.is_empty() {
+            Duration::from_secs(0)
+        } else {
+            self.total_frame_time() / self.frames.len() as u32
+        }
+    }
+
+    /// The longest real time spent on a single frame, zero if no frame was run.
+    pub fn max_frame_time(&self) -> Duration {
+        self.frames
+            .iter()
+            .map(|f| f.frame_time)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
 /// An Application is the root object of the game engine. It binds the OS
 /// event loop, state machines, timers and other core components in a central place.
 ///
@@ -239,24 +309,105 @@ where
         self.initialize();
         self.world.write_resource::<Stopwatch>().start();
         while self.states.is_running() {
-            self.advance_frame();
-            {
-                #[cfg(feature = "profiler")]
-                profile_scope!("frame_limiter wait");
-                self.world.write_resource::<FrameLimiter>().wait();
-            }
-            {
-                let elapsed = self.world.read_resource::<Stopwatch>().elapsed();
-                let mut time = self.world.write_resource::<Time>();
-                time.increment_frame_number();
-                time.set_delta_time(elapsed);
+            self.run_frame();
+        }
+
+        self.shutdown();
+    }
+
+    /// Runs exactly `frames` frames, unless the game state stops the application earlier.
+    ///
+    /// Afterwards all states are stopped and the application is shut down, as with
+    /// [`run`](#method.run). Combined with
+    /// [`ApplicationBuilder::with_time_source`](struct.ApplicationBuilder.html#method.with_time_source)
+    /// and `TimeSource::Fixed` this runs a deterministic simulation as fast as possible.
+    ///
+    /// Returns the statistics of every frame that was run.
+    pub fn run_frames(&mut self, frames: u64) -> RunReport
+    where
+        for<'b> R: EventReader<'b, Event = E>,
+    {
+        let mut remaining = frames;
+        self.run_until(move |_| {
+            if remaining == 0 {
+                true
+            } else {
+                remaining -= 1;
+                false
             }
-            let mut stopwatch = self.world.write_resource::<Stopwatch>();
-            stopwatch.stop();
-            stopwatch.restart();
+        })
+    }
+
+    /// Runs frames until `predicate` returns `true`, or the game state stops the application.
+    ///
+    /// The predicate is checked before every frame. Afterwards all states are stopped and the
+    /// application is shut down, as with [`run`](#method.run).
+    ///
+    /// Returns the statistics of every frame that was run.
+    pub fn run_until<F>(&mut self, mut predicate: F) -> RunReport
+    where
+        F: FnMut(&World) -> bool,
+        for<'b> R: EventReader<'b, Event = E>,
+    {
+        let mut report = RunReport::default();
+
+        self.initialize();
+        self.world.write_resource::<Stopwatch>().start();
+        while self.states.is_running() && !predicate(&self.world) {
+            report.frames.push(self.run_frame());
         }
 
+        self.states
+            .stop(StateData::new(&mut self.world, &mut self.data));
         self.shutdown();
+        report
+    }
+
+    /// Runs a single frame, waits for the frame limiter and updates `Time`.
+    fn run_frame(&mut self) -> FrameStats
+    where
+        for<'b> R: EventReader<'b, Event = E>,
+    {
+        let frame_start = Instant::now();
+        #[cfg(feature = "crash_report")]
+        {
+            if let Some(crash_context) = &self.crash_context {
+                crash_context.update(&self.world, &self.states);
+            }
+        }
+        let frame_number = self.world.read_resource::<Time>().frame_number();
+        let fixed_updates = self.advance_frame();
+        let frame_time = frame_start.elapsed();
+
+        let delta_time = match self.time_source {
+            TimeSource::WallClock => {
+                {
+                    #[cfg(feature = "profiler")]
+                    profile_scope!("frame_limiter wait");
+                    self.world.write_resource::<FrameLimiter>().wait();
+                }
+                self.world.read_resource::<Stopwatch>().elapsed()
+            }
+            TimeSource::Fixed(delta_time) => delta_time,
+        };
+        {
+            let mut time = self.world.write_resource::<Time>();
+            time.increment_frame_number();
+            time.set_delta_time(delta_time);
+            if let Some(log_buffer) = self.world.try_fetch::<LogBuffer>() {
+                log_buffer.set_frame_number(time.frame_number());
+            }
+        }
+        let mut stopwatch = self.world.write_resource::<Stopwatch>();
+        stopwatch.stop();
+        stopwatch.restart();
+
+        FrameStats {
+            frame_number,
+            delta_time,
+            frame_time,
+            fixed_updates,
+        }
     }
 
     /// Sets up the application.
@@ -302,8 +453,8 @@ where
         }
     }
 
-    /// Advances the game world by one tick.
-    fn advance_frame(&mut self)
+    /// Advances the game world by one tick, returning the number of fixed updates that ran.
+    fn advance_frame(&mut self) -> u32
     where
         for<'b> R: EventReader<'b, Event = E>,
     {
@@ -340,6 +491,13 @@ where
             }
         }
 
+        // Only games using the `TimersBundle` have timer callbacks to run.
+        if self.world.has_value::<Timers>() {
+            #[cfg(feature = "profiler")]
+            profile_scope!("run_timer_callbacks");
+            Timers::run_callbacks(&mut self.world);
+        }
+
         {
             #[cfg(feature = "profiler")]
             profile_scope!("handle_event");
@@ -357,6 +515,7 @@ where
                 }
             }
         }
+        let mut fixed_updates = 0;
         {
             #[cfg(feature = "profiler")]
             profile_scope!("fixed_update");
@@ -365,8 +524,10 @@ where
                 self.world.write_resource::<Time>().start_fixed_update();
             }
             while { self.world.write_resource::<Time>().step_fixed_update() } {
+                fixed_updates += 1;
                 self.states
                     .fixed_update(StateData::new(&mut self.world, &mut self.data));
+                self.data.fixed_update(&self.world);
             }
             {
                 self.world.write_resource::<Time>().finish_fixed_update();
@@ -382,6 +543,7 @@ where
         #[cfg(feature = "profiler")]
         profile_scope!("maintain");
         self.world.maintain();
+        fixed_updates
     }
 
     /// Cleans up after the quit signal is received.
@@ -417,6 +579,11 @@ pub struct ApplicationBuilder<S, T, E, R> {
     /// Used by bundles to access the world directly
     pub world: World,
     ignore_window_close: bool,
+    time_source: TimeSource,
+    #[cfg(feature = "crash_report")]
+    crash_reporter: Option<CrashReporter>,
+    #[cfg(feature = "crash_report")]
+    logger_config: Option<LoggerConfig>,
     phantom: PhantomData<(T, E, R)>,
 }
 
@@ -550,6 +717,11 @@ where
         world.insert(Stopwatch::default());
         world.insert(Time::default());
         world.insert(CallbackQueue::default());
+        let tasks = Tasks::new(
+            world.read_resource::<Loader>().pool().clone(),
+            world.read_resource::<CallbackQueue>().send_handle(),
+        );
+        world.insert(tasks);
 
         world.register::<Named>();
 
@@ -557,6 +729,11 @@ where
             initial_state,
             world,
             ignore_window_close: false,
+            time_source: TimeSource::default(),
+            #[cfg(feature = "crash_report")]
+            crash_reporter: None,
+            #[cfg(feature = "crash_report")]
+            logger_config: None,
             phantom: PhantomData,
         })
     }
@@ -824,6 +1001,54 @@ where
         self
     }
 
+    /// Seeds the `Rng` resource, so the game draws the same random numbers on every run.
+    ///
+    /// Without a seed a random one is chosen when the application is built, and logged.
+    ///
+    /// # Parameters
+    ///
+    /// `seed`: The seed all random number streams are derived from.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    pub fn with_rng_seed(mut self, seed: u64) -> Self {
+        self.world.insert(Rng::new(seed));
+        self
+    }
+
+    /// Seeds the `Rng` resource from the given config, which may come from a file or the command
+    /// line.
+    ///
+    /// # Parameters
+    ///
+    /// `config`: The seed config, a random seed is chosen if it has none.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use amethyst::{core::RngConfig, prelude::*};
+    ///
+    /// struct NullState;
+    /// impl EmptyState for NullState {}
+    ///
+    /// # fn main() -> amethyst::Result<()> {
+    /// let assets_dir = "assets/";
+    /// let mut game = Application::build(assets_dir, NullState)?
+    ///     .with_rng_config(RngConfig::from_args(std::env::args())?)
+    ///     .build(())?;
+    /// #     Ok(())
+    /// # }
+    /// ~~~
+    pub fn with_rng_config(mut self, config: RngConfig) -> Self {
+        self.world.insert(Rng::from_config(&config));
+        self
+    }
+
     /// Sets the duration between fixed updates, defaults to one sixtieth of a second.
     ///
     /// # Parameters
@@ -838,6 +1063,128 @@ where
         self
     }
 
+    /// Sets where the main loop takes the frame delta time from, defaults to
+    /// `TimeSource::WallClock`.
+    ///
+    /// With `TimeSource::Fixed` every frame advances `Time` by the same duration and the
+    /// `FrameLimiter` is skipped, so the loop runs as fast as possible and the same dispatcher
+    /// produces the same results on every run. This needs neither a window nor a GPU.
+    ///
+    /// # Parameters
+    ///
+    /// `time_source`: The source of frame delta times.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use amethyst::{core::TimeSource, prelude::*};
+    /// use std::time::Duration;
+    ///
+    /// struct NullState;
+    /// impl EmptyState for NullState {}
+    ///
+    /// # fn main() -> amethyst::Result<()> {
+    /// let assets_dir = "assets/";
+    /// let mut game = Application::build(assets_dir, NullState)?
+    ///     .with_time_source(TimeSource::Fixed(Duration::from_millis(16)))
+    ///     .build(())?;
+    /// let report = game.run_frames(600);
+    /// println!("Average frame time: {:?}", report.average_frame_time());
+    /// #     Ok(())
+    /// # }
+    /// ~~~
+    pub fn with_time_source(mut self, time_source: TimeSource) -> Self {
+        self.time_source = time_source;
+        self
+    }
+
+    /// Inserts the `LogLevels` and `LogBuffer` of a started logger as resources, so systems can
+    /// change the level filters and read recent log records.
+    ///
+    /// # Parameters
+    ///
+    /// `handles`: The handles returned by `start_logger` or `Logger::start`.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use amethyst::{prelude::*, LoggerConfig};
+    ///
+    /// struct NullState;
+    /// impl EmptyState for NullState {}
+    ///
+    /// # fn main() -> amethyst::Result<()> {
+    /// let logger = amethyst::start_logger(LoggerConfig {
+    ///     log_buffer_capacity: 256,
+    ///     ..Default::default()
+    /// });
+    ///
+    /// let assets_dir = "assets/";
+    /// let mut builder = Application::build(assets_dir, NullState)?;
+    /// if let Some(logger) = logger {
+    ///     builder = builder.with_logger(logger);
+    /// }
+    /// let mut game = builder.build(())?;
+    /// #     Ok(())
+    /// # }
+    /// ~~~
+    pub fn with_logger(mut self, handles: LoggerHandles) -> Self {
+        self.world.insert(handles.levels);
+        if let Some(log_buffer) = handles.buffer {
+            self.world.insert(log_buffer);
+        }
+        #[cfg(feature = "crash_report")]
+        {
+            self.logger_config = handles.config;
+        }
+        self
+    }
+
+    /// Installs a panic hook which writes a crash report into a file before the process exits.
+    ///
+    /// The report contains the state stack, the frame number, recent log records, asset counts
+    /// and the configs in use. See [`CrashReporter`] for details.
+    ///
+    /// Only available with the `crash_report` feature.
+    ///
+    /// # Parameters
+    ///
+    /// `crash_reporter`: Where to write reports, and which asset storages to include.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use amethyst::{prelude::*, CrashReporter};
+    ///
+    /// struct NullState;
+    /// impl EmptyState for NullState {}
+    ///
+    /// # fn main() -> amethyst::Result<()> {
+    /// let assets_dir = "assets/";
+    /// let mut game = Application::build(assets_dir, NullState)?
+    ///     .with_crash_reporter(CrashReporter::new("crash_reports"))
+    ///     .build(())?;
+    /// #     Ok(())
+    /// # }
+    /// ~~~
+    #[cfg(feature = "crash_report")]
+    pub fn with_crash_reporter(mut self, crash_reporter: CrashReporter) -> Self {
+        self.crash_reporter = Some(crash_reporter);
+        self
+    }
+
     /// Tells the resulting application window to ignore close events if ignore is true.
     /// This will make your game window unresponsive to operating system close commands.
     /// Use with caution.
@@ -889,6 +1236,9 @@ where
         #[cfg(feature = "profiler")]
         profile_scope!("new");
 
+        // Without a configured seed the `Rng` logs the random seed it picks.
+        self.world.entry::<Rng>().or_insert_with(Rng::from_entropy);
+
         let mut reader = X::default();
         reader.setup(&mut self.world);
         let data = init.build(&mut self.world);
@@ -900,15 +1250,145 @@ where
             .world
             .exec(|mut ev: Write<'_, EventChannel<TransEvent<T, E>>>| ev.register_reader());
 
+        #[cfg(feature = "crash_report")]
+        let crash_context = {
+            let world = &self.world;
+            let logger_config = self.logger_config.as_ref();
+            self.crash_reporter
+                .take()
+                .map(|crash_reporter| crash_reporter.install(world, logger_config))
+        };
+
         Ok(CoreApplication {
             world: self.world,
             states: StateMachine::new(self.initial_state),
             reader,
             events: Vec::new(),
             ignore_window_close: self.ignore_window_close,
+            time_source: self.time_source,
+            #[cfg(feature = "crash_report")]
+            crash_context,
             data,
             event_reader_id,
             trans_reader_id,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{core::Time, prelude::*, GameData};
+
+    /// Quits after `updates` updates, if given.
+    struct Quitter {
+        updates: Option<u32>,
+    }
+
+    impl SimpleState for Quitter {
+        fn update(&mut self, _: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+            match &mut self.updates {
+                Some(0) => Trans::Quit,
+                Some(updates) => {
+                    *updates -= 1;
+                    Trans::None
+                }
+                None => Trans::None,
+            }
+        }
+    }
+
+    fn application(
+        updates: Option<u32>,
+        time_source: TimeSource,
+    ) -> CoreApplication<'static, GameData<'static, 'static>> {
+        Application::build(".", Quitter { updates })
+            .unwrap()
+            .with_time_source(time_source)
+            .build(GameDataBuilder::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn run_frames_runs_requested_frames() {
+        let mut game = application(None, TimeSource::default());
+        let report = game.run_frames(5);
+
+        assert_eq!(report.frame_count(), 5);
+        let frame_numbers = report
+            .frames
+            .iter()
+            .map(|frame| frame.frame_number)
+            .collect::<Vec<_>>();
+        assert_eq!(frame_numbers, vec![0, 1, 2, 3, 4]);
+        assert_eq!(game.world.read_resource::<Time>().frame_number(), 5);
+        assert!(!game.states.is_running());
+    }
+
+    #[test]
+    fn run_frames_stops_with_state() {
+        let mut game = application(Some(2), TimeSource::default());
+        assert_eq!(game.run_frames(10).frame_count(), 3);
+    }
+
+    #[test]
+    fn run_until_checks_predicate_before_every_frame() {
+        let mut game = application(None, TimeSource::default());
+        let report = game.run_until(|world| world.read_resource::<Time>().frame_number() == 3);
+        assert_eq!(report.frame_count(), 3);
+
+        let mut game = application(None, TimeSource::default());
+        assert_eq!(game.run_until(|_| true).frame_count(), 0);
+    }
+
+    #[test]
+    fn fixed_time_source_is_deterministic() {
+        let delta_time = Duration::from_millis(250);
+        let mut game = application(None, TimeSource::Fixed(delta_time));
+        let report = game.run_frames(4);
+
+        assert!(report.frames.iter().all(|f| f.delta_time == delta_time));
+        let time = game.world.read_resource::<Time>();
+        assert_eq!(time.delta_time(), delta_time);
+        assert_eq!(time.absolute_time(), delta_time * 4);
+
+        let frame_times = report.frames.iter().map(|f| f.frame_time);
+        assert_eq!(report.total_frame_time(), frame_times.clone().sum());
+        assert_eq!(report.max_frame_time(), frame_times.max().unwrap());
+        assert_eq!(report.average_frame_time(), report.total_frame_time() / 4);
+    }
+
+    #[test]
+    fn empty_report() {
+        let report = RunReport::default();
+        assert_eq!(report.frame_count(), 0);
+        assert_eq!(report.average_frame_time(), Duration::from_secs(0));
+        assert_eq!(report.max_frame_time(), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn logger_handles_are_inserted() {
+        let levels = crate::LogLevels::new(crate::LogLevelFilter::Warn);
+        let handles = LoggerHandles {
+            levels: levels.clone(),
+            buffer: Some(LogBuffer::new(4)),
+            config: None,
+        };
+        let game = Application::build(".", Quitter { updates: None })
+            .unwrap()
+            .with_logger(handles)
+            .build(GameDataBuilder::default())
+            .unwrap();
+
+        levels.set_level_for("game", crate::LogLevelFilter::Debug);
+        assert_eq!(
+            game.world
+                .read_resource::<crate::LogLevels>()
+                .level_for("game"),
+            crate::LogLevelFilter::Debug
+        );
+        assert!(game.world.has_value::<LogBuffer>());
+    }
+}
@@ -0,0 +1,237 @@
+//! Config resources that are reloaded when their file changes.
+
+use std::{
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use log::error;
+
+use crate::{
+    config::Config,
+    core::{
+        ecs::prelude::{DispatcherBuilder, System, World, Write, WriteExpect},
+        shrev::EventChannel,
+        SystemBundle,
+    },
+    error::{format_err, Error, ResultExt},
+};
+
+/// Event emitted after the config resource `T` has been reloaded from `path`.
+#[derive(Debug)]
+pub struct ConfigReloaded<T> {
+    /// The file the config was reloaded from.
+    pub path: PathBuf,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> ConfigReloaded<T> {
+    fn new(path: PathBuf) -> Self {
+        ConfigReloaded {
+            path,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Loads the config `T` from a file, inserts it as a resource and reloads it whenever the file
+/// is modified.
+///
+/// Each successful reload replaces the resource and writes a `ConfigReloaded<T>` event. If the
+/// modified file fails to parse, the error is logged and the last good value is kept.
+///
+/// ## Errors
+///
+/// Returns an error if the config cannot be loaded initially.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// #[derive(Default, Deserialize, Serialize)]
+/// struct Tuning {
+///     jump_height: f32,
+/// }
+///
+/// let game_data = GameDataBuilder::default()
+///     .with_bundle(ConfigReloadBundle::<Tuning>::new("config/tuning.ron"))?;
+/// ```
+#[derive(Debug)]
+pub struct ConfigReloadBundle<T> {
+    path: PathBuf,
+    interval: Duration,
+    marker: PhantomData<T>,
+}
+
+impl<T> ConfigReloadBundle<T> {
+    /// Creates a new bundle loading the config from `path`.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        ConfigReloadBundle {
+            path: path.into(),
+            interval: Duration::from_secs(1),
+            marker: PhantomData,
+        }
+    }
+
+    /// Sets how often the modification time of the file is checked. Defaults to one second.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl<'a, 'b, T> SystemBundle<'a, 'b> for ConfigReloadBundle<T>
+where
+    T: Config + Send + Sync + 'static,
+{
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        let modified = modified_time(&self.path);
+        let config = T::load(&self.path)
+            .with_context(|_| format_err!("Failed loading config {}", self.path.display()))?;
+        world.insert(config);
+        world
+            .entry::<EventChannel<ConfigReloaded<T>>>()
+            .or_insert_with(EventChannel::new);
+
+        builder.add(
+            ConfigReloadSystem::<T> {
+                path: self.path,
+                interval: self.interval,
+                modified,
+                last_check: Instant::now(),
+                marker: PhantomData,
+            },
+            "",
+            &[],
+        );
+        Ok(())
+    }
+}
+
+/// Reloads the config resource `T` when its file is modified.
+///
+/// Added by the `ConfigReloadBundle`.
+#[derive(Debug)]
+pub struct ConfigReloadSystem<T> {
+    path: PathBuf,
+    interval: Duration,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T> System<'a> for ConfigReloadSystem<T>
+where
+    T: Config + Send + Sync + 'static,
+{
+    type SystemData = (
+        WriteExpect<'a, T>,
+        Write<'a, EventChannel<ConfigReloaded<T>>>,
+    );
+
+    fn run(&mut self, (mut config, mut reloaded): Self::SystemData) {
+        if self.last_check.elapsed() < self.interval {
+            return;
+        }
+        self.last_check = Instant::now();
+
+        let modified = modified_time(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return;
+        }
+        // Remember the modification even if parsing fails, so the error is only reported once.
+        self.modified = modified;
+
+        match T::load(&self.path) {
+            Ok(value) => {
+                *config = value;
+                reloaded.single_write(ConfigReloaded::new(self.path.clone()));
+            }
+            Err(e) => error!(
+                "Failed reloading config {}, keeping the previous value: {}",
+                self.path.display(),
+                e
+            ),
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::core::ecs::prelude::WorldExt;
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Tuning {
+        value: u32,
+    }
+
+    fn write_config(path: &Path, content: &str) {
+        // Some file systems only store modification times with a resolution of seconds.
+        let previous = modified_time(path);
+        loop {
+            fs::write(path, content).unwrap();
+            if modified_time(path) != previous {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn reloads_on_change_and_keeps_last_good_value() {
+        let path =
+            std::env::temp_dir().join(format!("amethyst_config_reload_{}.ron", std::process::id()));
+        write_config(&path, "(value: 1)");
+
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new();
+        ConfigReloadBundle::<Tuning>::new(&path)
+            .with_interval(Duration::from_secs(0))
+            .build(&mut world, &mut dispatcher)
+            .unwrap();
+        let mut dispatcher = dispatcher.build();
+        dispatcher.setup(&mut world);
+        let mut reader = world
+            .write_resource::<EventChannel<ConfigReloaded<Tuning>>>()
+            .register_reader();
+        assert_eq!(world.read_resource::<Tuning>().value, 1);
+
+        write_config(&path, "(value: 2)");
+        dispatcher.dispatch(&world);
+        assert_eq!(world.read_resource::<Tuning>().value, 2);
+        assert_eq!(
+            world
+                .read_resource::<EventChannel<ConfigReloaded<Tuning>>>()
+                .read(&mut reader)
+                .count(),
+            1
+        );
+
+        write_config(&path, "(value: ");
+        dispatcher.dispatch(&world);
+        assert_eq!(world.read_resource::<Tuning>().value, 2);
+        assert_eq!(
+            world
+                .read_resource::<EventChannel<ConfigReloaded<Tuning>>>()
+                .read(&mut reader)
+                .count(),
+            0
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,425 @@
+//! Crash reports written when the application panics.
+
+use std::{
+    cell::Cell,
+    fmt::Write as _,
+    fs,
+    panic::{self, PanicInfo, UnwindSafe},
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard, TryLockError},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    assets::{Asset, AssetCounts},
+    config::ConfigFormat,
+    core::{ecs::World, timing::Time},
+    ecs::prelude::WorldExt,
+    logger::{LogBuffer, LoggerConfig},
+    state::StateMachine,
+    window::DisplayConfig,
+};
+
+type PanicHook = Box<dyn Fn(&PanicInfo<'_>) + Send + Sync + 'static>;
+
+thread_local! {
+    /// The number of `catch_unwind` calls running on this thread.
+    static CATCHING: Cell<usize> = Cell::new(0);
+}
+
+/// Like `std::panic::catch_unwind`, but no crash report is written for panics caught by it.
+pub(crate) fn catch_unwind<F, R>(f: F) -> thread::Result<R>
+where
+    F: FnOnce() -> R + UnwindSafe,
+{
+    CATCHING.with(|catching| catching.set(catching.get() + 1));
+    let result = panic::catch_unwind(f);
+    CATCHING.with(|catching| catching.set(catching.get() - 1));
+    result
+}
+
+fn is_catching() -> bool {
+    CATCHING.with(|catching| catching.get() > 0)
+}
+
+/// Writes a crash report file when the application panics.
+///
+/// The report contains the panic message, the state stack, the frame number, the most recent log
+/// records from the `LogBuffer`, the number of loaded assets of every `AssetStorage` in
+/// `AssetCounts` and the `DisplayConfig` and `LoggerConfig` in use. It is written from the panic
+/// hook, before the previous hook runs and the process exits. Panics caught by `Tasks` don't
+/// produce a report.
+///
+/// The log records and `LoggerConfig` are those of the logger passed to
+/// `ApplicationBuilder::with_logger`.
+///
+/// Storages with a `Processor` are registered in `AssetCounts` when it is set up, other storages
+/// can be added with `with_asset_storage`.
+///
+/// The previous panic hook is restored when the application is dropped, unless another hook
+/// replaced the crash reporter's in the meantime.
+/// Installed with `ApplicationBuilder::with_crash_reporter`, requires the `crash_report` feature.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// let game = Application::build(assets_dir, LoadingState)?
+///     .with_crash_reporter(
+///         CrashReporter::new("crash_reports").with_asset_storage::<SpriteSheet>(),
+///     )
+///     .build(game_data)?;
+/// ```
+#[derive(Debug)]
+pub struct CrashReporter {
+    directory: PathBuf,
+    asset_storages: AssetCounts,
+}
+
+impl CrashReporter {
+    /// Creates a crash reporter writing reports into `directory`.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        CrashReporter {
+            directory: directory.into(),
+            asset_storages: AssetCounts::default(),
+        }
+    }
+
+    /// Includes the number of loaded assets in `AssetStorage<A>` in the report, for storages
+    /// which are not in `AssetCounts`.
+    pub fn with_asset_storage<A: Asset>(mut self) -> Self {
+        self.asset_storages.register::<A>();
+        self
+    }
+
+    /// Installs the panic hook. The returned context must be updated every frame, dropping it
+    /// restores the previous panic hook.
+    pub(crate) fn install(
+        self,
+        world: &World,
+        logger_config: Option<&LoggerConfig>,
+    ) -> CrashContext {
+        let mut configs = Vec::new();
+        if let Some(display_config) = world.try_fetch::<DisplayConfig>() {
+            configs.push(("DisplayConfig", serialize_config(&*display_config)));
+        }
+        if let Some(logger_config) = logger_config {
+            configs.push(("LoggerConfig", serialize_config(logger_config)));
+        }
+        let log_buffer = world
+            .try_fetch::<LogBuffer>()
+            .map(|buffer| LogBuffer::clone(&buffer));
+
+        let inner = Arc::new(ContextInner {
+            directory: self.directory,
+            asset_storages: self.asset_storages,
+            configs,
+            log_buffer,
+            snapshot: Mutex::new(Snapshot::default()),
+        });
+        let previous_hook = Arc::new(Mutex::new(Some(panic::take_hook())));
+
+        let hook: PanicHook = {
+            let inner = inner.clone();
+            let previous_hook = previous_hook.clone();
+            Box::new(move |info| {
+                if !is_catching() {
+                    match inner.write_report(info) {
+                        Ok(path) => eprintln!("Crash report written to {}", path.display()),
+                        Err(e) => eprintln!("Failed writing crash report: {}", e),
+                    }
+                }
+                if let Some(previous_hook) = &*lock(&previous_hook) {
+                    previous_hook(info);
+                }
+            })
+        };
+        let hook_address = hook_address(&hook);
+        panic::set_hook(hook);
+
+        CrashContext {
+            inner,
+            previous_hook,
+            hook_address,
+        }
+    }
+}
+
+/// Identifies a panic hook, as long as it is installed.
+fn hook_address(hook: &PanicHook) -> usize {
+    &**hook as *const _ as *const () as usize
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn serialize_config<C: serde::Serialize>(config: &C) -> String {
+    ConfigFormat::Ron
+        .serialize(config)
+        .unwrap_or_else(|e| format!("<failed serializing: {}>", e))
+}
+
+#[derive(Debug, Default)]
+struct Snapshot {
+    frame_number: u64,
+    states: Vec<&'static str>,
+    asset_counts: Vec<(&'static str, Option<usize>)>,
+}
+
+struct ContextInner {
+    directory: PathBuf,
+    asset_storages: AssetCounts,
+    configs: Vec<(&'static str, String)>,
+    log_buffer: Option<LogBuffer>,
+    snapshot: Mutex<Snapshot>,
+}
+
+/// Engine state shared with the panic hook of a `CrashReporter`.
+///
+/// The `World` cannot be accessed while panicking, so the application updates this every frame.
+pub(crate) struct CrashContext {
+    inner: Arc<ContextInner>,
+    /// The hook replaced by the crash reporter, called after writing the report.
+    previous_hook: Arc<Mutex<Option<PanicHook>>>,
+    /// The address of the crash reporter hook, see `hook_address`.
+    hook_address: usize,
+}
+
+impl CrashContext {
+    /// Records the current frame number, state stack and asset counts.
+    pub(crate) fn update<T: 'static, E>(&self, world: &World, states: &StateMachine<'_, T, E>)
+    where
+        E: Send + Sync + 'static,
+    {
+        let frame_number = world.read_resource::<Time>().frame_number();
+        let mut asset_counts = world
+            .try_fetch::<AssetCounts>()
+            .map(|counts| counts.counts(world))
+            .unwrap_or_default();
+        for (name, count) in self.inner.asset_storages.counts(world) {
+            if asset_counts.iter().all(|(counted, _)| *counted != name) {
+                asset_counts.push((name, count));
+            }
+        }
+
+        let mut snapshot = lock(&self.inner.snapshot);
+        snapshot.frame_number = frame_number;
+        snapshot.states = states.state_names();
+        snapshot.asset_counts = asset_counts;
+    }
+}
+
+impl Drop for CrashContext {
+    fn drop(&mut self) {
+        // The hook can't be changed while panicking, and the report is still wanted then.
+        if thread::panicking() {
+            return;
+        }
+        if lock(&self.previous_hook).is_none() {
+            return;
+        }
+        let current_hook = panic::take_hook();
+        if hook_address(&current_hook) != self.hook_address {
+            // Another hook replaced the crash reporter's, it may still call it.
+            panic::set_hook(current_hook);
+            return;
+        }
+        // Drops the crash reporter hook.
+        drop(current_hook);
+        if let Some(previous_hook) = lock(&self.previous_hook).take() {
+            panic::set_hook(previous_hook);
+        }
+    }
+}
+
+impl ContextInner {
+    fn write_report(&self, info: &PanicInfo<'_>) -> std::io::Result<PathBuf> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let thread = thread::current();
+        let panic = format!(
+            "{} (thread '{}')",
+            info,
+            thread.name().unwrap_or("<unnamed>")
+        );
+
+        fs::create_dir_all(&self.directory)?;
+        let path = self
+            .directory
+            .join(format!("crash-{}-{}.txt", timestamp, std::process::id()));
+        fs::write(&path, self.render(&panic, timestamp))?;
+        Ok(path)
+    }
+
+    fn render(&self, panic: &str, timestamp: u64) -> String {
+        let mut report = String::new();
+        // Writing to a `String` cannot fail.
+        let _ = writeln!(report, "Amethyst crash report");
+        let _ = writeln!(report, "Time: {} (seconds since UNIX epoch)", timestamp);
+        let _ = writeln!(report, "Panic: {}", panic);
+
+        // The panic may have happened while the snapshot was updated, don't wait for it.
+        let snapshot = match self.snapshot.try_lock() {
+            Ok(snapshot) => Some(snapshot),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        };
+        match snapshot {
+            Some(snapshot) => {
+                let _ = writeln!(report, "Frame number: {}", snapshot.frame_number);
+                let _ = writeln!(report, "\nState stack (bottom to top):");
+                for state in &snapshot.states {
+                    let _ = writeln!(report, "  {}", state);
+                }
+                let _ = writeln!(report, "\nLoaded assets:");
+                for (name, count) in &snapshot.asset_counts {
+                    match count {
+                        Some(count) => {
+                            let _ = writeln!(report, "  {}: {}", name, count);
+                        }
+                        None => {
+                            let _ = writeln!(report, "  {}: <no storage>", name);
+                        }
+                    }
+                }
+            }
+            None => {
+                let _ = writeln!(report, "Frame number, states and assets are unavailable");
+            }
+        }
+
+        if let Some(log_buffer) = &self.log_buffer {
+            let _ = writeln!(report, "\nRecent log records:");
+            for record in log_buffer.records() {
+                let _ = writeln!(
+                    report,
+                    "  [frame {}][{}][{}] {}",
+                    record.frame_number, record.level, record.target, record.message
+                );
+            }
+        }
+
+        for (name, config) in &self.configs {
+            let _ = writeln!(report, "\n{}:\n{}", name, config);
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Log;
+
+    use super::*;
+    use crate::{
+        assets::{Handle, Processor},
+        ecs::prelude::{System, VecStorage},
+        EmptyState, StateEvent,
+    };
+
+    struct Menu;
+
+    impl EmptyState for Menu {}
+
+    struct Sprite;
+
+    impl Asset for Sprite {
+        const NAME: &'static str = "Sprite";
+        type Data = Self;
+        type HandleStorage = VecStorage<Handle<Self>>;
+    }
+
+    struct Missing;
+
+    impl Asset for Missing {
+        const NAME: &'static str = "Missing";
+        type Data = Self;
+        type HandleStorage = VecStorage<Handle<Self>>;
+    }
+
+    #[test]
+    fn report_contains_snapshot() {
+        let mut world = World::new();
+        world.insert(Time::default());
+        world.insert(DisplayConfig::default());
+        let log_buffer = LogBuffer::new(4);
+        log_buffer.log(
+            &log::Record::builder()
+                .level(log::Level::Warn)
+                .target("game")
+                .args(format_args!("low health"))
+                .build(),
+        );
+        world.insert(log_buffer);
+
+        let mut asset_storages = AssetCounts::default();
+        asset_storages.register::<Missing>();
+        let context = CrashContext {
+            inner: Arc::new(ContextInner {
+                directory: PathBuf::new(),
+                asset_storages,
+                configs: vec![("DisplayConfig", "(title: \"Game\")".to_string())],
+                log_buffer: Some(LogBuffer::clone(&world.read_resource::<LogBuffer>())),
+                snapshot: Mutex::new(Snapshot::default()),
+            }),
+            previous_hook: Arc::new(Mutex::new(None)),
+            hook_address: 0,
+        };
+        let states = StateMachine::<(), StateEvent>::new(Menu);
+        context.update(&world, &states);
+
+        let report = context.inner.render("panicked at 'boom'", 0);
+        assert!(report.contains("Panic: panicked at 'boom'"));
+        assert!(report.contains("Frame number: 0"));
+        assert!(report.contains("crash_report::tests::Menu"));
+        assert!(report.contains("crash_report::tests::Missing: <no storage>"));
+        assert!(report.contains("[frame 0][WARN][game] low health"));
+        assert!(report.contains("DisplayConfig:\n(title: \"Game\")"));
+    }
+
+    #[test]
+    fn installed_hook_writes_report() {
+        let directory =
+            std::env::temp_dir().join(format!("amethyst_crash_report_{}", std::process::id()));
+        let mut world = World::new();
+        world.insert(Time::default());
+        Processor::<Sprite>::new().setup(&mut world);
+
+        let context = CrashReporter::new(&directory)
+            .with_asset_storage::<Missing>()
+            .with_asset_storage::<Sprite>()
+            .install(&world, Some(&LoggerConfig::default()));
+        context.update(&world, &StateMachine::<(), StateEvent>::new(Menu));
+        assert!(panic::catch_unwind(|| panic!("boom")).is_err());
+
+        // A hook set after the crash reporter's is kept when the context is dropped.
+        let quiet = String::from("quiet");
+        let replacement: PanicHook = Box::new(move |_| {
+            let _ = quiet.len();
+        });
+        let replacement_address = hook_address(&replacement);
+        panic::set_hook(replacement);
+        drop(context);
+        assert_eq!(hook_address(&panic::take_hook()), replacement_address);
+
+        let reports = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .filter(|report| report.contains("boom"))
+            .collect::<Vec<_>>();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert!(report.contains("crash_report::tests::Menu"));
+        assert!(report.contains("crash_report::tests::Sprite: 0"));
+        assert!(report.contains("crash_report::tests::Missing: <no storage>"));
+        assert_eq!(report.matches("tests::Sprite").count(), 1);
+        assert!(report.contains("LoggerConfig:"));
+    }
+}
@@ -1,4 +1,4 @@
-use std::marker::PhantomData;
+use std::{collections::HashMap, marker::PhantomData};
 
 use crate::{
     core::{
@@ -10,6 +10,7 @@ use crate::{
         ArcThreadPool, RunNowDesc, SystemBundle, SystemDesc,
     },
     error::Error,
+    system_metrics::{MeteredSystem, MeteredSystemDesc, MeteredThreadLocal, SystemMetrics},
 };
 
 /// Initialise trait for game data
@@ -22,6 +23,12 @@ pub trait DataInit<T> {
 pub trait DataDispose {
     /// Perform disposal
     fn dispose(&mut self, world: &mut World);
+
+    /// Runs one fixed update step of the game data.
+    ///
+    /// Called by the application once per fixed step, after the `fixed_update` of the states.
+    /// Does nothing by default.
+    fn fixed_update(&mut self, _world: &World) {}
 }
 
 /// Default game data.
@@ -31,6 +38,7 @@ pub trait DataDispose {
 #[allow(missing_debug_implementations)]
 pub struct GameData<'a, 'b> {
     dispatcher: Option<Dispatcher<'a, 'b>>,
+    fixed_dispatcher: Option<Dispatcher<'a, 'b>>,
 }
 
 impl<'a, 'b> GameData<'a, 'b> {
@@ -38,6 +46,18 @@ impl<'a, 'b> GameData<'a, 'b> {
     pub fn new(dispatcher: Dispatcher<'a, 'b>) -> Self {
         GameData {
             dispatcher: Some(dispatcher),
+            fixed_dispatcher: None,
+        }
+    }
+
+    /// Create new game data with a dispatcher for the fixed update
+    pub fn with_fixed(
+        dispatcher: Dispatcher<'a, 'b>,
+        fixed_dispatcher: Dispatcher<'a, 'b>,
+    ) -> Self {
+        GameData {
+            dispatcher: Some(dispatcher),
+            fixed_dispatcher: Some(fixed_dispatcher),
         }
     }
 
@@ -48,11 +68,21 @@ impl<'a, 'b> GameData<'a, 'b> {
         }
     }
 
-    /// Dispose game data, dropping the dispatcher
+    /// Run the fixed update dispatcher, if there is one
+    pub fn fixed_update(&mut self, world: &World) {
+        if let Some(dispatcher) = &mut self.fixed_dispatcher {
+            dispatcher.dispatch(&world);
+        }
+    }
+
+    /// Dispose game data, dropping the dispatchers
     pub fn dispose(&mut self, mut world: &mut World) {
         if let Some(dispatcher) = self.dispatcher.take() {
             dispatcher.dispose(&mut world);
         }
+        if let Some(dispatcher) = self.fixed_dispatcher.take() {
+            dispatcher.dispose(&mut world);
+        }
     }
 }
 
@@ -64,13 +94,20 @@ impl DataDispose for GameData<'_, '_> {
     fn dispose(&mut self, world: &mut World) {
         self.dispose(world);
     }
+
+    fn fixed_update(&mut self, world: &World) {
+        GameData::fixed_update(self, world);
+    }
 }
 
 /// Builder for default game data
 #[allow(missing_debug_implementations)]
 pub struct GameDataBuilder<'a, 'b> {
     dispatcher_operations: Vec<Box<dyn DispatcherOperation<'a, 'b>>>,
+    fixed_dispatcher_operations: Vec<Box<dyn DispatcherOperation<'a, 'b>>>,
     disp_builder: DispatcherBuilder<'a, 'b>,
+    /// Number of metered systems per metrics name, `None` if metrics are disabled.
+    metric_names: Option<HashMap<String, usize>>,
 }
 
 impl<'a, 'b> Default for GameDataBuilder<'a, 'b> {
@@ -84,7 +121,130 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     pub fn new() -> Self {
         GameDataBuilder {
             dispatcher_operations: Vec::new(),
+            fixed_dispatcher_operations: Vec::new(),
             disp_builder: DispatcherBuilder::new(),
+            metric_names: None,
+        }
+    }
+
+    /// Records the run time of the systems added after this call in the `SystemMetrics`
+    /// resource.
+    ///
+    /// Systems added with `with`, `with_system_desc`, `with_thread_local`, `with_fixed` and
+    /// `with_fixed_system_desc` are recorded under the name they were registered with. Unnamed
+    /// systems are recorded as `system#1`, `system#2` and so on, thread local systems as
+    /// `thread_local#1`, `thread_local#2` and so on. If a name is used twice, for example in
+    /// both the per-frame and the fixed update dispatcher, the later system is recorded as
+    /// `name#2`.
+    ///
+    /// # Bundles
+    ///
+    /// Systems added by bundles through `with_bundle` and `with_fixed_bundle`, and thread local
+    /// systems added with `with_thread_local_desc`, are **not** recorded. Bundles add their
+    /// systems directly to the dispatcher, so the builder can't wrap them. To record such a
+    /// system, add it with `with` or `with_system_desc` instead of through its bundle.
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use amethyst::core::SystemDesc;
+    /// use amethyst::derive::SystemDesc;
+    /// use amethyst::prelude::*;
+    /// use amethyst::ecs::prelude::{System, SystemData, World};
+    ///
+    /// #[derive(SystemDesc)]
+    /// struct NopSystem;
+    /// impl<'a> System<'a> for NopSystem {
+    ///     type SystemData = ();
+    ///     fn run(&mut self, _: Self::SystemData) {}
+    /// }
+    ///
+    /// GameDataBuilder::default()
+    ///     .with_system_metrics()
+    ///     // Recorded as "foo".
+    ///     .with(NopSystem, "foo", &[])
+    ///     // Recorded as "system#1".
+    ///     .with(NopSystem, "", &[]);
+    /// ~~~
+    pub fn with_system_metrics(mut self) -> Self {
+        self.metric_names.get_or_insert_with(HashMap::new);
+        self
+    }
+
+    /// Returns the unique metrics name of a system registered as `name`, or `None` if metrics
+    /// are disabled.
+    fn metric_name(&mut self, name: &str, unnamed: &str) -> Option<String> {
+        let names = self.metric_names.as_mut()?;
+        let base = if name.is_empty() { unnamed } else { name };
+        let count = names.entry(base.to_string()).or_insert(0);
+        *count += 1;
+        if *count == 1 && !name.is_empty() {
+            Some(base.to_string())
+        } else {
+            Some(format!("{}#{}", base, count))
+        }
+    }
+
+    fn system_operation<S, N>(
+        &mut self,
+        system: S,
+        name: N,
+        dependencies: &[N],
+    ) -> Box<dyn DispatcherOperation<'a, 'b>>
+    where
+        S: for<'c> System<'c> + 'static + Send,
+        N: Into<String> + Clone,
+    {
+        let name = Into::<String>::into(name);
+        let dependencies = dependencies
+            .iter()
+            .map(Clone::clone)
+            .map(Into::<String>::into)
+            .collect::<Vec<String>>();
+        match self.metric_name(&name, "system") {
+            Some(metric_name) => Box::new(AddSystem {
+                system: MeteredSystem::new(system, metric_name),
+                name,
+                dependencies,
+            }),
+            None => Box::new(AddSystem {
+                system,
+                name,
+                dependencies,
+            }),
+        }
+    }
+
+    fn system_desc_operation<SD, S, N>(
+        &mut self,
+        system_desc: SD,
+        name: N,
+        dependencies: &[N],
+    ) -> Box<dyn DispatcherOperation<'a, 'b>>
+    where
+        SD: SystemDesc<'a, 'b, S> + 'static,
+        S: for<'c> System<'c> + 'static + Send,
+        N: Into<String> + Clone,
+    {
+        let name = Into::<String>::into(name);
+        let dependencies = dependencies
+            .iter()
+            .map(Clone::clone)
+            .map(Into::<String>::into)
+            .collect::<Vec<String>>();
+        match self.metric_name(&name, "system") {
+            Some(metric_name) => Box::new(AddSystemDesc {
+                system_desc: MeteredSystemDesc::new(system_desc, metric_name),
+                name,
+                dependencies,
+                marker: PhantomData::<MeteredSystem<S>>,
+            }),
+            None => Box::new(AddSystemDesc {
+                system_desc,
+                name,
+                dependencies,
+                marker: PhantomData::<S>,
+            }),
         }
     }
 
@@ -158,6 +318,10 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     /// If a dependency is referenced (by name), but has not previously been added this
     /// function will panic.
     ///
+    /// The run time of the system can be recorded, see [`with_system_metrics`].
+    ///
+    /// [`with_system_metrics`]: #method.with_system_metrics
+    ///
     /// # Examples
     ///
     /// ~~~no_run
@@ -187,17 +351,7 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
         S: for<'c> System<'c> + 'static + Send,
         N: Into<String> + Clone,
     {
-        let name = Into::<String>::into(name);
-        let dependencies = dependencies
-            .iter()
-            .map(Clone::clone)
-            .map(Into::<String>::into)
-            .collect::<Vec<String>>();
-        let dispatcher_operation = Box::new(AddSystem {
-            system,
-            name,
-            dependencies,
-        }) as Box<dyn DispatcherOperation<'a, 'b> + 'static>;
+        let dispatcher_operation = self.system_operation(system, name, dependencies);
         self.dispatcher_operations.push(dispatcher_operation);
         self
     }
@@ -272,18 +426,7 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
         S: for<'c> System<'c> + 'static + Send,
         N: Into<String> + Clone,
     {
-        let name = Into::<String>::into(name);
-        let dependencies = dependencies
-            .iter()
-            .map(Clone::clone)
-            .map(Into::<String>::into)
-            .collect::<Vec<String>>();
-        let dispatcher_operation = Box::new(AddSystemDesc {
-            system_desc,
-            name,
-            dependencies,
-            marker: PhantomData::<S>,
-        }) as Box<dyn DispatcherOperation<'a, 'b> + 'static>;
+        let dispatcher_operation = self.system_desc_operation(system_desc, name, dependencies);
         self.dispatcher_operations.push(dispatcher_operation);
         self
     }
@@ -333,8 +476,13 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     where
         S: for<'c> RunNow<'c> + 'static,
     {
-        self.dispatcher_operations
-            .push(Box::new(AddThreadLocal { system }));
+        let dispatcher_operation = match self.metric_name("", "thread_local") {
+            Some(metric_name) => Box::new(AddThreadLocal {
+                system: MeteredThreadLocal::new(system, metric_name),
+            }) as Box<dyn DispatcherOperation<'a, 'b>>,
+            None => Box::new(AddThreadLocal { system }),
+        };
+        self.dispatcher_operations.push(dispatcher_operation);
         self
     }
 
@@ -394,7 +542,12 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
 
     /// Add a given ECS bundle to the game loop.
     ///
-    /// A bundle is a container for registering a bunch of ECS systems at once.
+    /// A bundle is a container for registering a bunch of ECS systems at once. It can also add
+    /// systems to the fixed update, see `SystemBundle::build_with_fixed`.
+    ///
+    /// The run time of systems added by bundles is not recorded, see [`with_system_metrics`].
+    ///
+    /// [`with_system_metrics`]: #method.with_system_metrics
     ///
     /// # Parameters
     ///
@@ -421,6 +574,92 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
         Ok(self)
     }
 
+    /// Adds a given system to the fixed update.
+    ///
+    /// Fixed update systems run in a separate dispatcher, once for every fixed step of
+    /// `Time::step_fixed_update`, right after the `fixed_update` of the states.
+    /// Use it for systems which need a constant time step, like physics.
+    ///
+    /// Names and dependencies refer to the other fixed update systems only. See [`with`] for the
+    /// parameters and panics.
+    ///
+    /// [`with`]: #method.with
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use amethyst::core::SystemDesc;
+    /// use amethyst::derive::SystemDesc;
+    /// use amethyst::prelude::*;
+    /// use amethyst::ecs::prelude::{System, SystemData, World};
+    ///
+    /// #[derive(SystemDesc)]
+    /// struct NopSystem;
+    /// impl<'a> System<'a> for NopSystem {
+    ///     type SystemData = ();
+    ///     fn run(&mut self, _: Self::SystemData) {}
+    /// }
+    ///
+    /// GameDataBuilder::default()
+    ///     .with(NopSystem, "input", &[])
+    ///     .with_fixed(NopSystem, "physics", &[]);
+    /// ~~~
+    pub fn with_fixed<S, N>(mut self, system: S, name: N, dependencies: &[N]) -> Self
+    where
+        S: for<'c> System<'c> + 'static + Send,
+        N: Into<String> + Clone,
+    {
+        let dispatcher_operation = self.system_operation(system, name, dependencies);
+        self.fixed_dispatcher_operations.push(dispatcher_operation);
+        self
+    }
+
+    /// Adds a system descriptor to the fixed update.
+    ///
+    /// See [`with_fixed`] and [`with_system_desc`].
+    ///
+    /// [`with_fixed`]: #method.with_fixed
+    /// [`with_system_desc`]: #method.with_system_desc
+    pub fn with_fixed_system_desc<SD, S, N>(
+        mut self,
+        system_desc: SD,
+        name: N,
+        dependencies: &[N],
+    ) -> Self
+    where
+        SD: SystemDesc<'a, 'b, S> + 'static,
+        S: for<'c> System<'c> + 'static + Send,
+        N: Into<String> + Clone,
+    {
+        let dispatcher_operation = self.system_desc_operation(system_desc, name, dependencies);
+        self.fixed_dispatcher_operations.push(dispatcher_operation);
+        self
+    }
+
+    /// Add a given ECS bundle to the fixed update.
+    ///
+    /// All systems of the bundle are added to the fixed update dispatcher, see [`with_fixed`].
+    /// The same bundle type can be added to the per-frame dispatcher with [`with_bundle`].
+    ///
+    /// Bundles which add some of their systems to the fixed update and others to the per-frame
+    /// dispatcher implement `SystemBundle::build_with_fixed` and are added with
+    /// [`with_bundle`] instead.
+    ///
+    /// [`with_fixed`]: #method.with_fixed
+    /// [`with_bundle`]: #method.with_bundle
+    ///
+    /// # Errors
+    ///
+    /// See each individual bundle for a description of the errors it could produce.
+    pub fn with_fixed_bundle<B>(mut self, bundle: B) -> Result<Self, Error>
+    where
+        B: SystemBundle<'a, 'b> + 'static,
+    {
+        self.fixed_dispatcher_operations
+            .push(Box::new(AddBundle { bundle }));
+        Ok(self)
+    }
+
     // /// Create a basic renderer with a single given `Pass`, and optional support for the `DrawUi` pass.
     // ///
     // /// Will set the clear color to black.
@@ -465,24 +704,186 @@ impl<'a, 'b> DataInit<GameData<'a, 'b>> for GameDataBuilder<'a, 'b> {
         #[cfg(not(no_threading))]
         let pool = (*world.read_resource::<ArcThreadPool>()).clone();
 
+        if self.metric_names.is_some() {
+            world
+                .entry::<SystemMetrics>()
+                .or_insert_with(SystemMetrics::default);
+        }
+
         let mut dispatcher_builder = self.disp_builder;
+        let mut fixed_dispatcher_builder = DispatcherBuilder::new();
 
         self.dispatcher_operations
             .into_iter()
             .try_for_each(|dispatcher_operation| {
-                dispatcher_operation.exec(world, &mut dispatcher_builder)
+                dispatcher_operation.exec_with_fixed(
+                    world,
+                    &mut dispatcher_builder,
+                    &mut fixed_dispatcher_builder,
+                )
             })
             .unwrap_or_else(|e| panic!("Failed to set up dispatcher: {}", e));
 
         #[cfg(not(no_threading))]
-        let mut dispatcher = dispatcher_builder.with_pool(pool).build();
+        let mut dispatcher = dispatcher_builder.with_pool(pool.clone()).build();
         #[cfg(no_threading)]
         let mut dispatcher = dispatcher_builder.build();
         dispatcher.setup(&mut world);
-        GameData::new(dispatcher)
+
+        self.fixed_dispatcher_operations
+            .into_iter()
+            .try_for_each(|dispatcher_operation| {
+                dispatcher_operation.exec(world, &mut fixed_dispatcher_builder)
+            })
+            .unwrap_or_else(|e| panic!("Failed to set up fixed update dispatcher: {}", e));
+
+        #[cfg(not(no_threading))]
+        let mut fixed_dispatcher = fixed_dispatcher_builder.with_pool(pool).build();
+        #[cfg(no_threading)]
+        let mut fixed_dispatcher = fixed_dispatcher_builder.build();
+        fixed_dispatcher.setup(&mut world);
+        GameData::with_fixed(dispatcher, fixed_dispatcher)
     }
 }
 
 impl DataInit<()> for () {
     fn build(self, _: &mut World) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use rayon::ThreadPoolBuilder;
+
+    use super::*;
+    use crate::{
+        core::{Time, TimeSource},
+        prelude::*,
+    };
+
+    #[derive(Clone, Default)]
+    struct Counter(Arc<AtomicUsize>);
+
+    impl Counter {
+        fn get(&self) -> usize {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    impl<'a> System<'a> for Counter {
+        type SystemData = ();
+
+        fn run(&mut self, _: Self::SystemData) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        let pool: ArcThreadPool =
+            Arc::new(ThreadPoolBuilder::new().num_threads(1).build().unwrap());
+        world.insert(pool);
+        world
+    }
+
+    #[test]
+    fn fixed_systems_only_run_on_fixed_update() {
+        let mut world = world();
+        let (frame, fixed) = (Counter::default(), Counter::default());
+        let mut game_data = GameDataBuilder::default()
+            .with(frame.clone(), "frame", &[])
+            .with_fixed(fixed.clone(), "fixed", &[])
+            .build(&mut world);
+
+        for _ in 0..3 {
+            game_data.update(&world);
+        }
+        assert_eq!((frame.get(), fixed.get()), (3, 0));
+
+        game_data.fixed_update(&world);
+        assert_eq!((frame.get(), fixed.get()), (3, 1));
+    }
+
+    struct SplitBundle {
+        frame: Counter,
+        fixed: Counter,
+    }
+
+    impl<'a, 'b> SystemBundle<'a, 'b> for SplitBundle {
+        fn build(
+            self,
+            _world: &mut World,
+            _dispatcher: &mut DispatcherBuilder<'a, 'b>,
+        ) -> Result<(), Error> {
+            panic!("`with_bundle` should call `build_with_fixed`");
+        }
+
+        fn build_with_fixed(
+            self,
+            _world: &mut World,
+            dispatcher: &mut DispatcherBuilder<'a, 'b>,
+            fixed_dispatcher: &mut DispatcherBuilder<'a, 'b>,
+        ) -> Result<(), Error> {
+            dispatcher.add(self.frame, "frame", &[]);
+            fixed_dispatcher.add(self.fixed, "fixed", &[]);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn bundles_add_systems_to_both_dispatchers() {
+        let mut world = world();
+        let (frame, fixed) = (Counter::default(), Counter::default());
+        let mut game_data = GameDataBuilder::default()
+            .with_bundle(SplitBundle {
+                frame: frame.clone(),
+                fixed: fixed.clone(),
+            })
+            .unwrap()
+            .build(&mut world);
+
+        game_data.update(&world);
+        game_data.fixed_update(&world);
+        game_data.fixed_update(&world);
+        assert_eq!((frame.get(), fixed.get()), (1, 2));
+    }
+
+    struct FixedStep;
+
+    impl SimpleState for FixedStep {
+        fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+            data.world.write_resource::<Time>().set_fixed_seconds(0.25);
+        }
+    }
+
+    #[test]
+    fn fixed_systems_run_once_per_fixed_step() {
+        let (frame, fixed) = (Counter::default(), Counter::default());
+        let game_data = GameDataBuilder::default()
+            .with(frame.clone(), "frame", &[])
+            .with_fixed(fixed.clone(), "fixed", &[]);
+        let mut game = Application::build(".", FixedStep)
+            .unwrap()
+            .with_time_source(TimeSource::Fixed(Duration::from_millis(500)))
+            .build(game_data)
+            .unwrap();
+
+        let report = game.run_frames(4);
+
+        // The first frame runs with a delta time of zero, every later frame spans two steps.
+        let steps = report
+            .frames
+            .iter()
+            .map(|frame| frame.fixed_updates)
+            .collect::<Vec<_>>();
+        assert_eq!(steps, vec![0, 2, 2, 2]);
+        assert_eq!((frame.get(), fixed.get()), (4, 6));
+    }
+}
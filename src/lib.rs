@@ -93,18 +93,27 @@ pub use crate::core::{ecs, shred, shrev};
 pub use crate::derive::*;
 
 pub use self::{
-    app::{Application, ApplicationBuilder, CoreApplication},
+    app::{Application, ApplicationBuilder, CoreApplication, FrameStats, RunReport},
     callback_queue::{Callback, CallbackQueue},
+    config_reload::{ConfigReloadBundle, ConfigReloadSystem, ConfigReloaded},
     error::Error,
     game_data::{DataDispose, DataInit, GameData, GameDataBuilder},
-    logger::{start_logger, LevelFilter as LogLevelFilter, Logger, LoggerConfig, StdoutLog},
+    logger::{
+        start_logger, LevelFilter as LogLevelFilter, LogBuffer, LogLevels, LogRecord, Logger,
+        LoggerConfig, LoggerHandles, StdoutLog,
+    },
     state::{
         EmptyState, EmptyTrans, SimpleState, SimpleTrans, State, StateData, StateMachine, Trans,
         TransEvent,
     },
     state_event::{StateEvent, StateEventReader},
+    system_metrics::{SystemMetrics, SystemStats},
+    tasks::{TaskContext, TaskHandle, Tasks},
 };
 
+#[cfg(feature = "crash_report")]
+pub use self::crash_report::CrashReporter;
+
 /// Convenience alias for use in main functions that uses Amethyst.
 pub type Result<T> = std::result::Result<T, error::Error>;
 
@@ -112,7 +121,12 @@ pub mod prelude;
 
 mod app;
 mod callback_queue;
+mod config_reload;
+#[cfg(feature = "crash_report")]
+mod crash_report;
 mod game_data;
 mod logger;
 mod state;
 mod state_event;
+mod system_metrics;
+mod tasks;
@@ -1,9 +1,34 @@
 pub use log::LevelFilter;
 
-use log::debug;
+use log::{debug, Level, Log, Metadata, Record};
 use serde::{Deserialize, Serialize};
 
-use std::{env, fmt, io, path::PathBuf, str::FromStr};
+use std::{
+    collections::VecDeque,
+    env, fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, RwLock,
+    },
+};
+
+/// Handles of a started Amethyst logger, returned by [`Logger::start`] and [`start_logger`].
+///
+/// Pass them to `ApplicationBuilder::with_logger` to insert the `LogLevels` and `LogBuffer` as
+/// resources.
+#[derive(Clone, Debug)]
+pub struct LoggerHandles {
+    /// The level filters of the logger.
+    pub levels: LogLevels,
+    /// The buffer of recent log records, if `LoggerConfig::log_buffer_capacity` is not `0`.
+    pub buffer: Option<LogBuffer>,
+    /// The configuration the logger was created from, after environment variable overrides.
+    pub config: Option<LoggerConfig>,
+}
 
 /// An enum that contains options for logging to the terminal.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
@@ -29,6 +54,23 @@ pub struct LoggerConfig {
     pub allow_env_override: bool,
     /// Sets a different level for gfx_device_gl if Some
     pub log_gfx_device_level: Option<LevelFilter>,
+    /// Level filters for individual modules, overriding `level_filter`.
+    #[serde(default)]
+    pub module_level_filters: Vec<(String, LevelFilter)>,
+    /// If set, the log file is rotated once it grows beyond this many bytes.
+    #[serde(default)]
+    pub log_file_max_size: Option<u64>,
+    /// Number of rotated log files kept next to the log file, named `<log_file>.1`, `.2`, ...
+    #[serde(default = "default_log_file_max_rotated")]
+    pub log_file_max_rotated: usize,
+    /// Number of recent log records kept in the `LogBuffer` resource. `0`, the default,
+    /// disables the buffer.
+    #[serde(default)]
+    pub log_buffer_capacity: usize,
+}
+
+fn default_log_file_max_rotated() -> usize {
+    5
 }
 
 impl Default for LoggerConfig {
@@ -39,7 +81,329 @@ impl Default for LoggerConfig {
             log_file: None,
             allow_env_override: true,
             log_gfx_device_level: Some(LevelFilter::Warn),
+            module_level_filters: Vec::new(),
+            log_file_max_size: None,
+            log_file_max_rotated: default_log_file_max_rotated(),
+            log_buffer_capacity: 0,
+        }
+    }
+}
+
+/// Resource to change the level filters of the Amethyst logger while the game is running.
+///
+/// Cloning yields a handle to the same filters.
+///
+/// # Examples
+///
+/// ```
+/// use amethyst::{LogLevelFilter, LogLevels};
+///
+/// let levels = LogLevels::new(LogLevelFilter::Info);
+/// levels.set_level_for("amethyst_assets", LogLevelFilter::Debug);
+///
+/// assert_eq!(levels.level_for("amethyst_assets::loader"), LogLevelFilter::Debug);
+/// assert_eq!(levels.level_for("amethyst_rendy"), LogLevelFilter::Info);
+/// ```
+#[derive(Clone, Debug)]
+pub struct LogLevels {
+    inner: Arc<LevelsInner>,
+}
+
+#[derive(Debug)]
+struct LevelsInner {
+    installed: AtomicBool,
+    filters: RwLock<LevelFilters>,
+}
+
+#[derive(Debug)]
+struct LevelFilters {
+    default: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl LevelFilters {
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                target == module
+                    || (target.starts_with(module.as_str())
+                        && target[module.len()..].starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+impl LogLevels {
+    /// Creates new level filters, logging everything up to `default` level.
+    pub fn new(default: LevelFilter) -> Self {
+        LogLevels {
+            inner: Arc::new(LevelsInner {
+                installed: AtomicBool::new(false),
+                filters: RwLock::new(LevelFilters {
+                    default,
+                    modules: Vec::new(),
+                }),
+            }),
+        }
+    }
+
+    /// The level filter of modules without a filter of their own.
+    pub fn default_level(&self) -> LevelFilter {
+        self.read().default
+    }
+
+    /// Sets the level filter of modules without a filter of their own.
+    pub fn set_default_level(&self, level: LevelFilter) {
+        self.write().default = level;
+        self.update_max_level();
+    }
+
+    /// The effective level filter for `target`.
+    ///
+    /// The filter of the longest matching module path applies, so a filter for `amethyst_assets`
+    /// also applies to `amethyst_assets::loader`.
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.read().level_for(target)
+    }
+
+    /// Sets the level filter for `module` and its submodules.
+    pub fn set_level_for<M: Into<String>>(&self, module: M, level: LevelFilter) {
+        let module = module.into();
+        {
+            let mut filters = self.write();
+            match filters.modules.iter_mut().find(|(m, _)| *m == module) {
+                Some(entry) => entry.1 = level,
+                None => filters.modules.push((module, level)),
+            }
         }
+        self.update_max_level();
+    }
+
+    /// Removes the level filter for `module`, it uses the default level again.
+    pub fn remove_level_for(&self, module: &str) {
+        self.write().modules.retain(|(m, _)| m != module);
+        self.update_max_level();
+    }
+
+    /// Whether a log record with the given metadata passes the filters.
+    pub fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn install(&self) {
+        self.inner.installed.store(true, Ordering::SeqCst);
+        self.update_max_level();
+    }
+
+    /// Keeps the `log` crate's global maximum in sync, so disabled records are skipped early.
+    fn update_max_level(&self) {
+        if self.inner.installed.load(Ordering::SeqCst) {
+            log::set_max_level(self.read().max_level());
+        }
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, LevelFilters> {
+        self.inner
+            .filters
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, LevelFilters> {
+        self.inner
+            .filters
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A log record kept by the `LogBuffer`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogRecord {
+    /// Level of the record.
+    pub level: Level,
+    /// Target of the record, usually the module path.
+    pub target: String,
+    /// The formatted message.
+    pub message: String,
+    /// The frame number, as reported by `Time::frame_number`, when the record was logged.
+    pub frame_number: u64,
+}
+
+/// Resource holding the most recent log records, e.g. for in-game consoles and crash reports.
+///
+/// Cloning yields a handle to the same buffer.
+#[derive(Clone, Debug)]
+pub struct LogBuffer {
+    inner: Arc<BufferInner>,
+}
+
+#[derive(Debug)]
+struct BufferInner {
+    capacity: usize,
+    frame_number: AtomicU64,
+    records: Mutex<VecDeque<LogRecord>>,
+}
+
+impl LogBuffer {
+    /// Creates a buffer keeping the last `capacity` records.
+    pub fn new(capacity: usize) -> Self {
+        LogBuffer {
+            inner: Arc::new(BufferInner {
+                capacity,
+                frame_number: AtomicU64::new(0),
+                records: Mutex::new(VecDeque::with_capacity(capacity)),
+            }),
+        }
+    }
+
+    /// Maximum number of records kept.
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity
+    }
+
+    /// Returns the buffered records, oldest first.
+    pub fn records(&self) -> Vec<LogRecord> {
+        self.lock().iter().cloned().collect()
+    }
+
+    /// Removes all buffered records.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Sets the frame number attached to records logged from now on.
+    ///
+    /// This is done by the `CoreApplication` at the start of every frame.
+    pub fn set_frame_number(&self, frame_number: u64) {
+        self.inner
+            .frame_number
+            .store(frame_number, Ordering::Relaxed);
+    }
+
+    /// Adds a record, dropping the oldest one if the buffer is full.
+    pub fn push(&self, record: &Record<'_>) {
+        if self.inner.capacity == 0 {
+            return;
+        }
+        let record = LogRecord {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            frame_number: self.inner.frame_number.load(Ordering::Relaxed),
+        };
+        let mut records = self.lock();
+        if records.len() == self.inner.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<LogRecord>> {
+        self.inner
+            .records
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Log for LogBuffer {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        self.push(record);
+    }
+
+    fn flush(&self) {}
+}
+
+/// Log file that is rotated once it grows beyond a maximum size.
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_rotated: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_size: u64, max_rotated: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile {
+            path,
+            max_size,
+            max_rotated,
+            file,
+            size,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+
+    /// Moves `<path>` to `<path>.1`, `<path>.1` to `<path>.2` and so on, dropping the oldest.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_rotated == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            remove_if_exists(&self.rotated_path(self.max_rotated))?;
+            for index in (1..self.max_rotated).rev() {
+                rename_if_exists(&self.rotated_path(index), &self.rotated_path(index + 1))?;
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
     }
 }
 
@@ -65,6 +429,9 @@ impl Default for LoggerConfig {
 #[allow(missing_debug_implementations)]
 pub struct Logger {
     dispatch: fern::Dispatch,
+    levels: LogLevels,
+    buffer: Option<LogBuffer>,
+    config: Option<LoggerConfig>,
 }
 
 impl Logger {
@@ -77,7 +444,16 @@ impl Logger {
                 message = message,
             ))
         });
-        Self { dispatch }
+        Self::from_dispatch(dispatch)
+    }
+
+    fn from_dispatch(dispatch: fern::Dispatch) -> Self {
+        Self {
+            dispatch,
+            levels: LogLevels::new(LevelFilter::Info),
+            buffer: None,
+            config: None,
+        }
     }
 
     /// Create a new Logger with a passed in formatter callback
@@ -88,8 +464,7 @@ impl Logger {
             + Send
             + 'static,
     {
-        let dispatch = fern::Dispatch::new().format(formatter);
-        Self { dispatch }
+        Self::from_dispatch(fern::Dispatch::new().format(formatter))
     }
 
     /// Create a new logger from [`LoggerConfig`] and the Logger it will be added to
@@ -97,8 +472,9 @@ impl Logger {
         if config.allow_env_override {
             env_var_override(&mut config);
         }
+        logger.config = Some(config.clone());
 
-        logger.dispatch = logger.dispatch.level(config.level_filter);
+        logger.levels.set_default_level(config.level_filter);
 
         match config.stdout {
             StdoutLog::Plain => logger.dispatch = logger.dispatch.chain(io::stdout()),
@@ -111,19 +487,31 @@ impl Logger {
         }
 
         if let Some(log_gfx_device_level) = config.log_gfx_device_level {
-            logger.dispatch = logger
-                .dispatch
-                .level_for("gfx_device_gl", log_gfx_device_level);
+            logger
+                .levels
+                .set_level_for("gfx_device_gl", log_gfx_device_level);
+        }
+        for (module, level) in config.module_level_filters {
+            logger.levels.set_level_for(module, level);
         }
 
         if let Some(path) = config.log_file {
-            if let Ok(log_file) = fern::log_file(path) {
+            let log_file = match config.log_file_max_size {
+                Some(max_size) => RotatingFile::open(path, max_size, config.log_file_max_rotated)
+                    .map(|file| fern::Output::from(Box::new(file) as Box<dyn Write + Send>)),
+                None => fern::log_file(path).map(fern::Output::from),
+            };
+            if let Ok(log_file) = log_file {
                 logger.dispatch = logger.dispatch.chain(log_file)
             } else {
                 eprintln!("Unable to access the log file, as such it will not be used")
             }
         }
 
+        if config.log_buffer_capacity > 0 {
+            logger.buffer = Some(LogBuffer::new(config.log_buffer_capacity));
+        }
+
         logger
     }
 
@@ -144,20 +532,44 @@ impl Logger {
     }
 
     /// Set individual log levels for modules.
+    ///
+    /// The levels can be changed later through the `LogLevels` resource.
     pub fn level_for<T: Into<std::borrow::Cow<'static, str>>>(
-        mut self,
+        self,
         module: T,
         level: LevelFilter,
     ) -> Self {
-        self.dispatch = self.dispatch.level_for(module, level);
+        self.levels.set_level_for(module.into().into_owned(), level);
         self
     }
 
     /// Starts [`Logger`] by consuming it.
-    pub fn start(self) {
-        self.dispatch.apply().unwrap_or_else(|_| {
-            debug!("Global logger already set, default Amethyst logger will not be used")
-        });
+    ///
+    /// Returns the handles of the started logger, or `None` if a global logger was already set.
+    pub fn start(self) -> Option<LoggerHandles> {
+        let levels = self.levels.clone();
+        let mut dispatch = fern::Dispatch::new()
+            .filter(move |metadata| levels.enabled(metadata))
+            .chain(self.dispatch);
+        if let Some(buffer) = &self.buffer {
+            // Chained outside of the formatted dispatch to keep the plain message.
+            dispatch = dispatch.chain(Box::new(buffer.clone()) as Box<dyn Log>);
+        }
+
+        match dispatch.apply() {
+            Ok(()) => {
+                self.levels.install();
+                Some(LoggerHandles {
+                    levels: self.levels,
+                    buffer: self.buffer,
+                    config: self.config,
+                })
+            }
+            Err(_) => {
+                debug!("Global logger already set, default Amethyst logger will not be used");
+                None
+            }
+        }
     }
 }
 
@@ -177,8 +589,10 @@ impl Logger {
 ///     * "trace" everything
 /// * `AMETHYST_LOG_FILE_PATH` - if set, enables logging to the file at the path
 ///     * the value is expected to be a path to the logging file
-pub fn start_logger(config: LoggerConfig) {
-    Logger::from_config(config).start();
+///
+/// Returns the handles of the started logger, see [`Logger::start`].
+pub fn start_logger(config: LoggerConfig) -> Option<LoggerHandles> {
+    Logger::from_config(config).start()
 }
 
 fn env_var_override(config: &mut LoggerConfig) {
@@ -230,4 +644,58 @@ mod tests {
 
         assert_eq!(config.stdout, StdoutLog::Plain);
     }
+
+    #[test]
+    fn module_levels_use_longest_prefix() {
+        let levels = LogLevels::new(LevelFilter::Warn);
+        levels.set_level_for("game", LevelFilter::Info);
+        levels.set_level_for("game::ai", LevelFilter::Trace);
+
+        assert_eq!(levels.level_for("game::ai::path"), LevelFilter::Trace);
+        assert_eq!(levels.level_for("game::physics"), LevelFilter::Info);
+        assert_eq!(levels.level_for("gameplay"), LevelFilter::Warn);
+
+        levels.remove_level_for("game::ai");
+        assert_eq!(levels.level_for("game::ai"), LevelFilter::Info);
+    }
+
+    #[test]
+    fn log_buffer_keeps_latest_records() {
+        let buffer = LogBuffer::new(2);
+        for (frame_number, message) in ["one", "two", "three"].iter().enumerate() {
+            buffer.set_frame_number(frame_number as u64);
+            buffer.log(
+                &Record::builder()
+                    .level(Level::Info)
+                    .target("game")
+                    .args(format_args!("{}", message))
+                    .build(),
+            );
+        }
+
+        let records = buffer.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].message, "two");
+        assert_eq!(records[1].message, "three");
+        assert_eq!(records[1].frame_number, 2);
+        assert_eq!(records[1].target, "game");
+    }
+
+    #[test]
+    fn log_file_is_rotated() {
+        let path = env::temp_dir().join(format!("amethyst_rotate_{}.log", std::process::id()));
+        let mut file = RotatingFile::open(path.clone(), 10, 1).unwrap();
+        file.write_all(b"first line\n").unwrap();
+        file.write_all(b"second line\n").unwrap();
+        file.write_all(b"third line\n").unwrap();
+        file.flush().unwrap();
+
+        let rotated = file.rotated_path(1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third line\n");
+        assert_eq!(fs::read_to_string(&rotated).unwrap(), "second line\n");
+        assert!(!file.rotated_path(2).exists());
+
+        fs::remove_file(path).unwrap();
+        fs::remove_file(rotated).unwrap();
+    }
 }
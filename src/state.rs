@@ -4,9 +4,12 @@ use amethyst_input::is_close_requested;
 
 use derivative::Derivative;
 
-use crate::{ecs::World, GameData, StateEvent};
+use crate::{core::ActiveState, ecs::World, GameData, StateEvent};
 
-use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::{
+    any::TypeId,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
@@ -59,7 +62,19 @@ pub enum Trans<T, E> {
     /// Pause the active state and push a new state onto the stack.
     Push(Box<dyn State<T, E>>),
     /// Remove the current state on the stack and insert a different one.
+    ///
+    /// Only the active state is stopped, the states below it are neither resumed nor paused.
     Switch(Box<dyn State<T, E>>),
+    /// Stop and remove all states, then push the given states in order.
+    ///
+    /// The last state becomes the active one, all states before it are paused.
+    NewStack(Vec<Box<dyn State<T, E>>>),
+    /// Perform several transitions in order, within a single frame.
+    ///
+    /// The state machine only stops once the whole sequence has been applied, so
+    /// `Trans::Sequence(vec![Trans::Pop, Trans::Push(state)])` on a single state stack keeps
+    /// the engine running.
+    Sequence(Vec<Trans<T, E>>),
     /// Stop and remove all states and shut down the engine.
     Quit,
 }
@@ -70,6 +85,8 @@ impl<T, E> Debug for Trans<T, E> {
             Trans::Pop => f.write_str("Pop"),
             Trans::Push(_) => f.write_str("Push"),
             Trans::Switch(_) => f.write_str("Switch"),
+            Trans::NewStack(_) => f.write_str("NewStack"),
+            Trans::Sequence(ref sequence) => f.debug_tuple("Sequence").field(sequence).finish(),
             Trans::Quit => f.write_str("Quit"),
         }
     }
@@ -82,6 +99,8 @@ impl<T, E> Debug for Trans<T, E> {
 /// ```
 ///
 /// Transitions will be executed sequentially by Amethyst's `CoreApplication` update loop.
+/// Every kind of `Trans` is supported, including `Trans::Sequence` to apply several
+/// transitions atomically.
 pub type TransEvent<T, E> = Box<dyn Fn() -> Trans<T, E> + Send + Sync + 'static>;
 
 /// An empty `Trans`. Made to be used with `EmptyState`.
@@ -133,6 +152,19 @@ pub trait State<T, E: Send + Sync + 'static> {
     /// even when this is not the active state,
     /// as long as this state is on the [StateMachine](struct.StateMachine.html)'s state-stack.
     fn shadow_update(&mut self, _data: StateData<'_, T>) {}
+
+    /// Name of the state, used in diagnostics such as crash reports.
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Identifies the state for systems created with `SystemExt::in_state`.
+    ///
+    /// Returns `None` by default, so `in_state` systems never run in this state. Opt in by
+    /// returning `Some(TypeId::of::<Self>())`.
+    fn state_type_id(&self) -> Option<TypeId> {
+        None
+    }
 }
 
 /// An empty `State` trait. It contains no `StateData` or custom `StateEvent`.
@@ -183,6 +215,12 @@ pub trait EmptyState {
     /// even when this is not the active state,
     /// as long as this state is on the [StateMachine](struct.StateMachine.html)'s state-stack.
     fn shadow_update(&mut self, _data: StateData<'_, ()>) {}
+
+    /// Identifies the state for systems created with `SystemExt::in_state`, see
+    /// `State::state_type_id`.
+    fn state_type_id(&self) -> Option<TypeId> {
+        None
+    }
 }
 
 impl<T: EmptyState> State<(), StateEvent> for T {
@@ -236,6 +274,14 @@ impl<T: EmptyState> State<(), StateEvent> for T {
     fn shadow_update(&mut self, data: StateData<'_, ()>) {
         self.shadow_update(data);
     }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn state_type_id(&self) -> Option<TypeId> {
+        EmptyState::state_type_id(self)
+    }
 }
 
 /// A simple `State` trait. It contains `GameData` as its `StateData` and no custom `StateEvent`.
@@ -290,6 +336,12 @@ pub trait SimpleState {
     /// even when this is not the active state,
     /// as long as this state is on the [StateMachine](struct.StateMachine.html)'s state-stack.
     fn shadow_update(&mut self, _data: StateData<'_, GameData<'_, '_>>) {}
+
+    /// Identifies the state for systems created with `SystemExt::in_state`, see
+    /// `State::state_type_id`.
+    fn state_type_id(&self) -> Option<TypeId> {
+        None
+    }
 }
 
 impl<T: SimpleState> State<GameData<'static, 'static>, StateEvent> for T {
@@ -351,6 +403,14 @@ impl<T: SimpleState> State<GameData<'static, 'static>, StateEvent> for T {
     fn shadow_update(&mut self, data: StateData<'_, GameData<'_, '_>>) {
         self.shadow_update(data);
     }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn state_type_id(&self) -> Option<TypeId> {
+        SimpleState::state_type_id(self)
+    }
 }
 
 /// A simple stack-based state machine (pushdown automaton).
@@ -376,15 +436,25 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
         self.running
     }
 
+    /// Returns the names of the states on the stack, from the bottom to the active state.
+    pub fn state_names(&self) -> Vec<&'static str> {
+        self.state_stack
+            .iter()
+            .map(|state| state.type_name())
+            .collect()
+    }
+
     /// Initializes the state machine.
     pub fn start(&mut self, data: StateData<'_, T>) -> Result<(), StateError> {
         if !self.running {
+            let StateData { world, data } = data;
             let state = self
                 .state_stack
                 .last_mut()
                 .ok_or(StateError::NoStatesPresent)?;
-            state.on_start(data);
+            state.on_start(StateData { world, data });
             self.running = true;
+            self.update_active_state(world);
         }
         Ok(())
     }
@@ -459,12 +529,46 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
     /// global `EventChannel<TransEvent<T, E>>`. Such `Trans` will be passed to this method
     /// sequentially in the order of insertion.
     pub fn transition(&mut self, request: Trans<T, E>, data: StateData<'_, T>) {
+        if self.running {
+            if let Trans::None = request {
+                return;
+            }
+            let StateData { world, data } = data;
+            self.apply(request, StateData { world, data });
+            if self.state_stack.is_empty() {
+                self.running = false;
+            }
+            self.update_active_state(world);
+        }
+    }
+
+    /// Publishes the type of the active state in the `ActiveState` resource.
+    fn update_active_state(&self, world: &mut World) {
+        let active_state = ActiveState::new(
+            self.state_stack
+                .last()
+                .and_then(|state| state.state_type_id()),
+        );
+        *world
+            .entry::<ActiveState>()
+            .or_insert_with(ActiveState::default) = active_state;
+    }
+
+    /// Applies a single transition without checking whether the stack ran empty.
+    fn apply(&mut self, request: Trans<T, E>, data: StateData<'_, T>) {
         if self.running {
             match request {
                 Trans::None => (),
                 Trans::Pop => self.pop(data),
                 Trans::Push(state) => self.push(state, data),
                 Trans::Switch(state) => self.switch(state, data),
+                Trans::NewStack(states) => self.new_stack(states, data),
+                Trans::Sequence(sequence) => {
+                    let StateData { world, data } = data;
+                    for request in sequence {
+                        self.apply(request, StateData { world, data });
+                    }
+                }
                 Trans::Quit => self.stop(data),
             }
         }
@@ -502,6 +606,20 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
         }
     }
 
+    /// Stops and removes all states, then pushes the given states in order.
+    fn new_stack(&mut self, states: Vec<Box<dyn State<T, E>>>, data: StateData<'_, T>) {
+        if self.running {
+            let StateData { world, data } = data;
+            while let Some(mut state) = self.state_stack.pop() {
+                state.on_stop(StateData { world, data });
+            }
+
+            for state in states {
+                self.push(state, StateData { world, data });
+            }
+        }
+    }
+
     /// Stops and removes the active state and un-pauses the next state on the
     /// stack (if any).
     fn pop(&mut self, data: StateData<'_, T>) {
@@ -513,8 +631,6 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
 
             if let Some(state) = self.state_stack.last_mut() {
                 state.on_resume(StateData { world, data });
-            } else {
-                self.running = false;
             }
         }
     }
@@ -535,6 +651,7 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::{cell::RefCell, rc::Rc};
 
     struct State1(u8);
     struct State2;
@@ -574,4 +691,176 @@ mod tests {
         sm.update(StateData::new(&mut world, &mut ()));
         assert!(!sm.is_running());
     }
+
+    struct Borrowing<'a>(&'a mut u32);
+
+    impl State<(), ()> for Borrowing<'_> {
+        fn update(&mut self, _: StateData<'_, ()>) -> Trans<(), ()> {
+            *self.0 += 1;
+            Trans::None
+        }
+    }
+
+    #[test]
+    fn initial_state_can_borrow() {
+        use crate::ecs::prelude::{World, WorldExt};
+
+        let mut world = World::new();
+        let mut updates = 0;
+        {
+            let mut sm = StateMachine::new(Borrowing(&mut updates));
+            sm.start(StateData::new(&mut world, &mut ())).unwrap();
+            sm.update(StateData::new(&mut world, &mut ()));
+        }
+        assert_eq!(updates, 1);
+    }
+
+    struct Tracked;
+
+    impl State<(), ()> for Tracked {
+        fn state_type_id(&self) -> Option<TypeId> {
+            Some(TypeId::of::<Self>())
+        }
+    }
+
+    #[test]
+    fn active_state_is_published_for_tracked_states() {
+        use crate::ecs::prelude::{World, WorldExt};
+
+        let mut world = World::new();
+        let mut sm = StateMachine::new(Tracked);
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+        assert!(world.read_resource::<ActiveState>().is::<Tracked>());
+
+        sm.transition(
+            Trans::Push(Box::new(State2)),
+            StateData::new(&mut world, &mut ()),
+        );
+        assert_eq!(world.read_resource::<ActiveState>().type_id(), None);
+
+        sm.transition(Trans::Pop, StateData::new(&mut world, &mut ()));
+        assert!(world.read_resource::<ActiveState>().is::<Tracked>());
+    }
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    struct Recorder {
+        name: &'static str,
+        log: Log,
+        next: Option<Trans<(), ()>>,
+    }
+
+    impl Recorder {
+        fn new(name: &'static str, log: &Log) -> Self {
+            Recorder {
+                name,
+                log: log.clone(),
+                next: None,
+            }
+        }
+
+        fn then(mut self, trans: Trans<(), ()>) -> Self {
+            self.next = Some(trans);
+            self
+        }
+
+        fn record(&self, event: &str) {
+            self.log
+                .borrow_mut()
+                .push(format!("{} {}", self.name, event));
+        }
+    }
+
+    impl State<(), ()> for Recorder {
+        fn on_start(&mut self, _: StateData<'_, ()>) {
+            self.record("start");
+        }
+
+        fn on_stop(&mut self, _: StateData<'_, ()>) {
+            self.record("stop");
+        }
+
+        fn on_pause(&mut self, _: StateData<'_, ()>) {
+            self.record("pause");
+        }
+
+        fn on_resume(&mut self, _: StateData<'_, ()>) {
+            self.record("resume");
+        }
+
+        fn update(&mut self, _: StateData<'_, ()>) -> Trans<(), ()> {
+            self.next.take().unwrap_or(Trans::None)
+        }
+    }
+
+    fn run_with_stack(log: &Log, bottom: Recorder, top: Recorder) -> StateMachine<'static, (), ()> {
+        use crate::ecs::prelude::{World, WorldExt};
+
+        let mut world = World::new();
+        let mut sm = StateMachine::new(bottom);
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+        sm.transition(
+            Trans::Push(Box::new(top)),
+            StateData::new(&mut world, &mut ()),
+        );
+        log.borrow_mut().clear();
+        sm.update(StateData::new(&mut world, &mut ()));
+        sm
+    }
+
+    #[test]
+    fn switch_does_not_resume() {
+        let log = Log::default();
+        let top = Recorder::new("b", &log).then(Trans::Switch(Box::new(Recorder::new("c", &log))));
+        let sm = run_with_stack(&log, Recorder::new("a", &log), top);
+
+        assert!(sm.is_running());
+        assert_eq!(*log.borrow(), vec!["b stop", "c start"]);
+    }
+
+    #[test]
+    fn new_stack_clears_all_states() {
+        let log = Log::default();
+        let top = Recorder::new("b", &log).then(Trans::NewStack(vec![
+            Box::new(Recorder::new("c", &log)),
+            Box::new(Recorder::new("d", &log)),
+        ]));
+        let sm = run_with_stack(&log, Recorder::new("a", &log), top);
+
+        assert!(sm.is_running());
+        assert_eq!(
+            *log.borrow(),
+            vec!["b stop", "a stop", "c start", "c pause", "d start"]
+        );
+    }
+
+    #[test]
+    fn sequence_applies_in_order() {
+        let log = Log::default();
+        let top = Recorder::new("b", &log).then(Trans::Sequence(vec![
+            Trans::Pop,
+            Trans::Pop,
+            Trans::Push(Box::new(Recorder::new("c", &log))),
+        ]));
+        let sm = run_with_stack(&log, Recorder::new("a", &log), top);
+
+        assert!(sm.is_running());
+        assert_eq!(
+            *log.borrow(),
+            vec!["b stop", "a resume", "a stop", "c start"]
+        );
+    }
+
+    #[test]
+    fn sequence_stops_on_quit() {
+        let log = Log::default();
+        let top = Recorder::new("b", &log).then(Trans::Sequence(vec![
+            Trans::Quit,
+            Trans::Push(Box::new(Recorder::new("c", &log))),
+        ]));
+        let sm = run_with_stack(&log, Recorder::new("a", &log), top);
+
+        assert!(!sm.is_running());
+        assert_eq!(*log.borrow(), vec!["b stop", "a stop"]);
+    }
 }
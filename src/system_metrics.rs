@@ -0,0 +1,534 @@
+//! Execution metrics for the systems registered with `GameDataBuilder`.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::Write,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{
+    core::{
+        ecs::prelude::{RunNow, System, World},
+        shred::{AccessorCow, RunningTime},
+        SystemDesc,
+    },
+    error::{format_err, Error, ResultExt},
+};
+
+/// Number of runs the rolling average of a system is computed over by default.
+pub const DEFAULT_AVERAGE_WINDOW: usize = 60;
+
+/// Number of runs kept for the Chrome trace export by default.
+pub const DEFAULT_TRACE_CAPACITY: usize = 10_000;
+
+/// Timing statistics of a single system.
+#[derive(Clone, Debug, Default)]
+pub struct SystemStats {
+    calls: u64,
+    total: Duration,
+    last: Duration,
+    max: Duration,
+    window: VecDeque<Duration>,
+}
+
+impl SystemStats {
+    /// Number of times the system was run.
+    pub fn calls(&self) -> u64 {
+        self.calls
+    }
+
+    /// Sum of the run time of all runs.
+    pub fn total_time(&self) -> Duration {
+        self.total
+    }
+
+    /// Run time of the latest run.
+    pub fn last_time(&self) -> Duration {
+        self.last
+    }
+
+    /// Run time of the slowest run.
+    pub fn max_time(&self) -> Duration {
+        self.max
+    }
+
+    /// Average run time over the latest runs, see `SystemMetrics::new`.
+    pub fn average_time(&self) -> Duration {
+        if self.window.is_empty() {
+            return Duration::default();
+        }
+        self.window.iter().sum::<Duration>() / self.window.len() as u32
+    }
+
+    /// Average run time over all runs.
+    pub fn mean_time(&self) -> Duration {
+        if self.calls == 0 {
+            return Duration::default();
+        }
+        Duration::from_secs_f64(self.total.as_secs_f64() / self.calls as f64)
+    }
+
+    fn record(&mut self, duration: Duration, window_size: usize) {
+        self.calls += 1;
+        self.total += duration;
+        self.last = duration;
+        self.max = self.max.max(duration);
+        if window_size > 0 {
+            if self.window.len() == window_size {
+                self.window.pop_front();
+            }
+            self.window.push_back(duration);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TraceEvent {
+    name: Arc<str>,
+    start: Duration,
+    duration: Duration,
+    thread: u64,
+}
+
+#[derive(Debug)]
+struct MetricsData {
+    epoch: Instant,
+    stats: HashMap<Arc<str>, SystemStats>,
+    events: VecDeque<TraceEvent>,
+}
+
+#[derive(Debug)]
+struct MetricsInner {
+    enabled: AtomicBool,
+    window_size: usize,
+    trace_capacity: usize,
+    data: Mutex<MetricsData>,
+}
+
+/// Resource collecting the run time of the systems added to a `GameDataBuilder` after
+/// `GameDataBuilder::with_system_metrics`.
+///
+/// Systems are identified by the name they were registered with, made unique with a `#2`,
+/// `#3`, ... suffix. See `GameDataBuilder::with_system_metrics` for the names of unnamed and
+/// thread local systems.
+///
+/// Cloning the resource yields a handle to the same metrics.
+///
+/// # Examples
+///
+/// ```
+/// use amethyst::SystemMetrics;
+/// use std::time::{Duration, Instant};
+///
+/// let metrics = SystemMetrics::default();
+/// metrics.record("physics", Instant::now(), Duration::from_millis(2));
+/// metrics.record("physics", Instant::now(), Duration::from_millis(4));
+///
+/// let stats = metrics.stats("physics").unwrap();
+/// assert_eq!(stats.calls(), 2);
+/// assert_eq!(stats.average_time(), Duration::from_millis(3));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SystemMetrics {
+    inner: Arc<MetricsInner>,
+}
+
+impl Default for SystemMetrics {
+    fn default() -> Self {
+        SystemMetrics::new(DEFAULT_AVERAGE_WINDOW, DEFAULT_TRACE_CAPACITY)
+    }
+}
+
+impl SystemMetrics {
+    /// Creates new metrics.
+    ///
+    /// # Parameters
+    ///
+    /// - `window_size`: Number of runs the rolling average of each system is computed over.
+    /// - `trace_capacity`: Number of runs, over all systems, kept for the Chrome trace export.
+    ///                     The oldest runs are dropped first.
+    pub fn new(window_size: usize, trace_capacity: usize) -> Self {
+        SystemMetrics {
+            inner: Arc::new(MetricsInner {
+                enabled: AtomicBool::new(true),
+                window_size,
+                trace_capacity,
+                data: Mutex::new(MetricsData {
+                    epoch: Instant::now(),
+                    stats: HashMap::new(),
+                    events: VecDeque::new(),
+                }),
+            }),
+        }
+    }
+
+    /// Whether runs are currently recorded.
+    pub fn is_enabled(&self) -> bool {
+        self.inner.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Enables or disables recording. Collected data is kept.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.inner.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Records a single run of the system `name` which started at `start`.
+    pub fn record(&self, name: &str, start: Instant, duration: Duration) {
+        if !self.is_enabled() {
+            return;
+        }
+        let thread = current_thread_index();
+        let mut data = self.lock();
+        let name = match data.stats.get_key_value(name) {
+            Some((name, _)) => name.clone(),
+            None => Arc::from(name),
+        };
+        data.stats
+            .entry(name.clone())
+            .or_insert_with(SystemStats::default)
+            .record(duration, self.inner.window_size);
+
+        if self.inner.trace_capacity > 0 {
+            if data.events.len() == self.inner.trace_capacity {
+                data.events.pop_front();
+            }
+            let start = start.checked_duration_since(data.epoch).unwrap_or_default();
+            data.events.push_back(TraceEvent {
+                name,
+                start,
+                duration,
+                thread,
+            });
+        }
+    }
+
+    /// Returns the statistics of the system `name`, if it has been run.
+    pub fn stats(&self, name: &str) -> Option<SystemStats> {
+        self.lock().stats.get(name).cloned()
+    }
+
+    /// Returns the statistics of all systems which have been run, sorted by name.
+    pub fn all_stats(&self) -> Vec<(String, SystemStats)> {
+        let mut all = self
+            .lock()
+            .stats
+            .iter()
+            .map(|(name, stats)| (name.to_string(), stats.clone()))
+            .collect::<Vec<_>>();
+        all.sort_by(|a, b| a.0.cmp(&b.0));
+        all
+    }
+
+    /// Discards all collected data.
+    pub fn reset(&self) {
+        let mut data = self.lock();
+        data.epoch = Instant::now();
+        data.stats.clear();
+        data.events.clear();
+    }
+
+    /// Writes the recorded runs as Chrome trace-event JSON.
+    ///
+    /// The output can be opened with `chrome://tracing` or similar trace viewers.
+    pub fn write_chrome_trace<W: Write>(&self, writer: W) -> Result<(), Error> {
+        serde_json::to_writer(writer, &self.chrome_trace())
+            .with_context(|_| format_err!("Failed writing Chrome trace"))
+    }
+
+    /// Returns the recorded runs as Chrome trace-event JSON.
+    pub fn to_chrome_trace(&self) -> String {
+        serde_json::to_string(&self.chrome_trace())
+            .expect("Chrome trace events are always serializable")
+    }
+
+    fn chrome_trace(&self) -> ChromeTrace {
+        let data = self.lock();
+        ChromeTrace {
+            trace_events: data
+                .events
+                .iter()
+                .map(|event| ChromeTraceEvent {
+                    name: event.name.to_string(),
+                    cat: "system",
+                    ph: "X",
+                    ts: event.start.as_nanos() as f64 / 1000.0,
+                    dur: event.duration.as_nanos() as f64 / 1000.0,
+                    pid: 1,
+                    tid: event.thread,
+                })
+                .collect(),
+            display_time_unit: "ms",
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MetricsData> {
+        // Recording never panics while holding the lock, so the data is consistent even if a
+        // panic poisoned it elsewhere.
+        self.inner
+            .data
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChromeTrace {
+    trace_events: Vec<ChromeTraceEvent>,
+    display_time_unit: &'static str,
+}
+
+#[derive(Serialize)]
+struct ChromeTraceEvent {
+    name: String,
+    cat: &'static str,
+    ph: &'static str,
+    ts: f64,
+    dur: f64,
+    pid: u32,
+    tid: u64,
+}
+
+/// Small, stable index of the current thread for the trace output.
+fn current_thread_index() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    thread_local! {
+        static INDEX: u64 = NEXT.fetch_add(1, Ordering::Relaxed);
+    }
+    INDEX.with(|index| *index)
+}
+
+fn fetch_metrics(world: &mut World) -> SystemMetrics {
+    world
+        .entry::<SystemMetrics>()
+        .or_insert_with(SystemMetrics::default)
+        .clone()
+}
+
+/// Wraps a `System`, recording its run time into `SystemMetrics`.
+pub(crate) struct MeteredSystem<S> {
+    system: S,
+    name: String,
+    metrics: Option<SystemMetrics>,
+}
+
+impl<S> MeteredSystem<S> {
+    pub(crate) fn new(system: S, name: String) -> Self {
+        MeteredSystem {
+            system,
+            name,
+            metrics: None,
+        }
+    }
+}
+
+impl<'a, S> System<'a> for MeteredSystem<S>
+where
+    S: System<'a>,
+{
+    type SystemData = S::SystemData;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let start = Instant::now();
+        self.system.run(data);
+        if let Some(metrics) = &self.metrics {
+            metrics.record(&self.name, start, start.elapsed());
+        }
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+
+    fn accessor<'b>(&'b self) -> AccessorCow<'a, 'b, Self> {
+        match self.system.accessor() {
+            AccessorCow::Ref(accessor) => AccessorCow::Ref(accessor),
+            AccessorCow::Owned(accessor) => AccessorCow::Owned(accessor),
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        self.metrics = Some(fetch_metrics(world));
+        System::setup(&mut self.system, world);
+    }
+
+    fn dispose(self, world: &mut World) {
+        System::dispose(self.system, world);
+    }
+}
+
+/// Builds a `MeteredSystem` from the wrapped system's `SystemDesc`.
+pub(crate) struct MeteredSystemDesc<SD, S> {
+    system_desc: SD,
+    name: String,
+    marker: PhantomData<S>,
+}
+
+impl<SD, S> MeteredSystemDesc<SD, S> {
+    pub(crate) fn new(system_desc: SD, name: String) -> Self {
+        MeteredSystemDesc {
+            system_desc,
+            name,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, 'b, SD, S> SystemDesc<'a, 'b, MeteredSystem<S>> for MeteredSystemDesc<SD, S>
+where
+    SD: SystemDesc<'a, 'b, S>,
+    S: for<'c> System<'c>,
+{
+    fn build(self, world: &mut World) -> MeteredSystem<S> {
+        MeteredSystem::new(self.system_desc.build(world), self.name)
+    }
+}
+
+/// Wraps a thread local system, recording its run time into `SystemMetrics`.
+pub(crate) struct MeteredThreadLocal<S> {
+    system: S,
+    name: String,
+    metrics: Option<SystemMetrics>,
+}
+
+impl<S> MeteredThreadLocal<S> {
+    pub(crate) fn new(system: S, name: String) -> Self {
+        MeteredThreadLocal {
+            system,
+            name,
+            metrics: None,
+        }
+    }
+}
+
+impl<'a, S> RunNow<'a> for MeteredThreadLocal<S>
+where
+    S: RunNow<'a>,
+{
+    fn run_now(&mut self, world: &'a World) {
+        let start = Instant::now();
+        self.system.run_now(world);
+        if let Some(metrics) = &self.metrics {
+            metrics.record(&self.name, start, start.elapsed());
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        self.metrics = Some(fetch_metrics(world));
+        RunNow::setup(&mut self.system, world);
+    }
+
+    fn dispose(self: Box<Self>, world: &mut World) {
+        RunNow::dispose(Box::new(self.system), world);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::ThreadPoolBuilder;
+
+    use super::*;
+    use crate::{
+        core::{ecs::prelude::WorldExt, ArcThreadPool},
+        DataInit, GameDataBuilder,
+    };
+
+    struct NopSystem;
+
+    impl<'a> System<'a> for NopSystem {
+        type SystemData = ();
+
+        fn run(&mut self, _: Self::SystemData) {}
+    }
+
+    #[test]
+    fn rolling_average_uses_latest_runs() {
+        let metrics = SystemMetrics::new(2, 0);
+        for millis in &[10, 2, 4] {
+            metrics.record("sys", Instant::now(), Duration::from_millis(*millis));
+        }
+
+        let stats = metrics.stats("sys").unwrap();
+        assert_eq!(stats.calls(), 3);
+        assert_eq!(stats.last_time(), Duration::from_millis(4));
+        assert_eq!(stats.max_time(), Duration::from_millis(10));
+        assert_eq!(stats.average_time(), Duration::from_millis(3));
+        assert_eq!(stats.total_time(), Duration::from_millis(16));
+    }
+
+    #[test]
+    fn chrome_trace_is_bounded() {
+        let metrics = SystemMetrics::new(DEFAULT_AVERAGE_WINDOW, 2);
+        for name in &["a", "b", "c"] {
+            metrics.record(name, Instant::now(), Duration::from_micros(5));
+        }
+
+        let trace: serde_json::Value = serde_json::from_str(&metrics.to_chrome_trace()).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["name"], "b");
+        assert_eq!(events[1]["name"], "c");
+        assert_eq!(events[1]["ph"], "X");
+        assert_eq!(events[1]["dur"], 5.0);
+    }
+
+    #[test]
+    fn game_data_records_systems() {
+        let mut world = World::new();
+        let pool: ArcThreadPool =
+            Arc::new(ThreadPoolBuilder::new().num_threads(1).build().unwrap());
+        world.insert(pool);
+
+        let mut game_data = GameDataBuilder::default()
+            .with(NopSystem, "unmetered", &[])
+            .with_system_metrics()
+            .with(NopSystem, "nop", &[])
+            .with(NopSystem, "", &[])
+            .with(NopSystem, "", &[])
+            .with_thread_local(NopSystem)
+            .with_fixed(NopSystem, "nop", &[])
+            .build(&mut world);
+        game_data.update(&world);
+        game_data.update(&world);
+        game_data.fixed_update(&world);
+
+        let metrics = world.read_resource::<SystemMetrics>();
+        let calls = metrics
+            .all_stats()
+            .into_iter()
+            .map(|(name, stats)| (name, stats.calls()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            calls,
+            vec![
+                ("nop".to_string(), 2),
+                ("nop#2".to_string(), 1),
+                ("system#1".to_string(), 2),
+                ("system#2".to_string(), 2),
+                ("thread_local#1".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn game_data_without_metrics() {
+        let mut world = World::new();
+        let pool: ArcThreadPool =
+            Arc::new(ThreadPoolBuilder::new().num_threads(1).build().unwrap());
+        world.insert(pool);
+
+        let mut game_data = GameDataBuilder::default()
+            .with(NopSystem, "nop", &[])
+            .build(&mut world);
+        game_data.update(&world);
+
+        assert!(!world.has_value::<SystemMetrics>());
+    }
+}
@@ -0,0 +1,487 @@
+//! Background tasks whose results are delivered back to the `World`.
+
+use std::{
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex, MutexGuard, TryLockError, Weak,
+    },
+    task::{Context, Poll},
+};
+
+use crossbeam_channel::Sender;
+use futures::task::{waker_ref, ArcWake};
+
+#[cfg(feature = "crash_report")]
+use crate::crash_report::catch_unwind;
+use crate::{
+    callback_queue::Callback,
+    core::{ecs::World, ArcThreadPool},
+    error::{format_err, Error},
+};
+#[cfg(not(feature = "crash_report"))]
+use std::panic::catch_unwind;
+
+/// Runs futures and blocking jobs on the thread pool shared with the asset `Loader`.
+///
+/// Every spawned task returns a `TaskHandle`, which can be polled from systems or states, or
+/// turned into a callback that runs on the `World` through the `CallbackQueue`.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// let handle = world
+///     .read_resource::<Tasks>()
+///     .spawn_blocking(|context| {
+///         context.set_progress(0.5);
+///         expensive_computation()
+///     });
+///
+/// // Later, e.g. in `State::update`:
+/// if let Some(result) = handle.poll() {
+///     println!("Computed {:?}", result?);
+/// }
+/// ```
+#[allow(missing_debug_implementations)]
+pub struct Tasks {
+    pool: ArcThreadPool,
+    callbacks: Sender<Callback>,
+}
+
+impl Tasks {
+    /// Creates a new task executor running on `pool`.
+    ///
+    /// Completion callbacks are sent to `callbacks`, usually obtained from
+    /// `CallbackQueue::send_handle`.
+    pub fn new(pool: ArcThreadPool, callbacks: Sender<Callback>) -> Self {
+        Tasks { pool, callbacks }
+    }
+
+    /// Runs a blocking job on the thread pool.
+    ///
+    /// The job receives a `TaskContext` to report progress and to check for cancellation.
+    pub fn spawn_blocking<T, F>(&self, job: F) -> TaskHandle<T>
+    where
+        F: FnOnce(&TaskContext) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let shared = Arc::new(Shared::new(self.callbacks.clone()));
+        let context = TaskContext {
+            control: shared.control.clone(),
+        };
+        let completion = shared.clone();
+        self.pool.spawn(move || {
+            if context.is_cancelled() {
+                return;
+            }
+            let result = catch_unwind(AssertUnwindSafe(|| job(&context)))
+                .map_err(|_| format_err!("Task panicked"));
+            completion.complete(result);
+        });
+        TaskHandle {
+            shared,
+            future: None,
+        }
+    }
+
+    /// Runs a future on the thread pool.
+    ///
+    /// The future is created by `task`, which receives a `TaskContext` to report progress.
+    /// Cancelled futures are dropped right away, or after the current poll if they are being
+    /// polled.
+    pub fn spawn<T, F, Fut>(&self, task: F) -> TaskHandle<T>
+    where
+        F: FnOnce(TaskContext) -> Fut,
+        Fut: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let shared = Arc::new(Shared::new(self.callbacks.clone()));
+        let mut future = Box::pin(task(TaskContext {
+            control: shared.control.clone(),
+        }));
+        let completion = shared.clone();
+        let poll = move |cx: &mut Context<'_>| {
+            if completion.control.is_cancelled() {
+                return Poll::Ready(());
+            }
+            match catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+                Ok(Poll::Pending) => Poll::Pending,
+                Ok(Poll::Ready(value)) => {
+                    completion.complete(Ok(value));
+                    Poll::Ready(())
+                }
+                Err(_) => {
+                    completion.complete(Err(format_err!("Task panicked")));
+                    Poll::Ready(())
+                }
+            }
+        };
+        let task = Arc::new(FutureTask {
+            poll: Mutex::new(Some(Box::new(poll))),
+            control: shared.control.clone(),
+            pool: self.pool.clone(),
+        });
+        FutureTask::schedule(&task);
+        TaskHandle {
+            shared,
+            future: Some(Arc::downgrade(&task)),
+        }
+    }
+}
+
+/// Passed to running tasks to report progress and check for cancellation.
+#[derive(Clone, Debug)]
+pub struct TaskContext {
+    control: Arc<Control>,
+}
+
+impl TaskContext {
+    /// Sets the progress of the task, clamped to `0.0..=1.0`.
+    pub fn set_progress(&self, progress: f32) {
+        self.control.set_progress(progress);
+    }
+
+    /// Whether the task has been cancelled. Long running jobs should check this regularly and
+    /// return early.
+    pub fn is_cancelled(&self) -> bool {
+        self.control.is_cancelled()
+    }
+}
+
+/// Handle to a task spawned through `Tasks`.
+///
+/// Dropping the handle detaches the task: it keeps running, but its result is discarded.
+#[allow(missing_debug_implementations)]
+pub struct TaskHandle<T> {
+    shared: Arc<Shared<T>>,
+    /// The future of tasks created with `Tasks::spawn`, to drop it when cancelled.
+    future: Option<Weak<FutureTask>>,
+}
+
+impl<T> TaskHandle<T>
+where
+    T: Send + 'static,
+{
+    /// Takes the result of the task once it has finished.
+    ///
+    /// Returns `None` while the task is running, and after the result has been taken. Cancelled
+    /// and panicked tasks finish with an error.
+    pub fn poll(&self) -> Option<Result<T, Error>> {
+        let mut state = self.shared.lock();
+        match &*state {
+            TaskState::Done(_) => match std::mem::replace(&mut *state, TaskState::Taken) {
+                TaskState::Done(result) => Some(result),
+                _ => unreachable!(),
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether the task has finished, including when its result has already been taken.
+    pub fn is_finished(&self) -> bool {
+        match &*self.shared.lock() {
+            TaskState::Running => false,
+            _ => true,
+        }
+    }
+
+    /// Progress last reported by the task, between `0.0` and `1.0`.
+    pub fn progress(&self) -> f32 {
+        self.shared.control.progress()
+    }
+
+    /// Requests cancellation of the task.
+    ///
+    /// The task finishes immediately with an error. Blocking jobs that already started keep
+    /// running until they check `TaskContext::is_cancelled`, their result is discarded.
+    pub fn cancel(&self) {
+        self.shared.control.cancel();
+        self.shared.complete(Err(format_err!("Task was cancelled")));
+        if let Some(task) = self.future.as_ref().and_then(Weak::upgrade) {
+            task.drop_future();
+        }
+    }
+
+    /// Whether `cancel` has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.control.is_cancelled()
+    }
+
+    /// Runs `callback` on the `World` through the `CallbackQueue` once the task has finished.
+    ///
+    /// If the task has already finished, the callback is queued right away. Nothing is queued if
+    /// the result has already been taken with `poll`.
+    pub fn on_complete<F>(self, callback: F)
+    where
+        F: FnOnce(&mut World, Result<T, Error>) + Send + 'static,
+    {
+        let mut state = self.shared.lock();
+        match std::mem::replace(&mut *state, TaskState::Taken) {
+            TaskState::Running => *state = TaskState::Callback(Box::new(callback)),
+            TaskState::Done(result) => self.shared.send(callback, result),
+            other => *state = other,
+        }
+    }
+}
+
+type CompletionCallback<T> = Box<dyn FnOnce(&mut World, Result<T, Error>) + Send>;
+
+enum TaskState<T> {
+    Running,
+    Callback(CompletionCallback<T>),
+    Done(Result<T, Error>),
+    Taken,
+}
+
+#[derive(Debug, Default)]
+struct Control {
+    cancelled: AtomicBool,
+    progress: AtomicU32,
+}
+
+impl Control {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn progress(&self) -> f32 {
+        f32::from_bits(self.progress.load(Ordering::Relaxed))
+    }
+
+    fn set_progress(&self, progress: f32) {
+        let progress = if progress.is_nan() {
+            0.0
+        } else {
+            progress.max(0.0).min(1.0)
+        };
+        self.progress.store(progress.to_bits(), Ordering::Relaxed);
+    }
+}
+
+struct Shared<T> {
+    control: Arc<Control>,
+    state: Mutex<TaskState<T>>,
+    callbacks: Sender<Callback>,
+}
+
+impl<T> Shared<T>
+where
+    T: Send + 'static,
+{
+    fn new(callbacks: Sender<Callback>) -> Self {
+        Shared {
+            control: Arc::new(Control::default()),
+            state: Mutex::new(TaskState::Running),
+            callbacks,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TaskState<T>> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Stores the result, or hands it to the completion callback. Only the first result counts.
+    fn complete(&self, result: Result<T, Error>) {
+        if result.is_ok() {
+            self.control.set_progress(1.0);
+        }
+        let mut state = self.lock();
+        match std::mem::replace(&mut *state, TaskState::Taken) {
+            TaskState::Running => *state = TaskState::Done(result),
+            TaskState::Callback(callback) => self.send(callback, result),
+            other => *state = other,
+        }
+    }
+
+    fn send<F>(&self, callback: F, result: Result<T, Error>)
+    where
+        F: FnOnce(&mut World, Result<T, Error>) + Send + 'static,
+    {
+        // `Callback` is `Fn`, the result can only be moved out of it once.
+        let pending = Mutex::new(Some((callback, result)));
+        let callback = move |world: &mut World| {
+            let pending = pending
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .take();
+            if let Some((callback, result)) = pending {
+                callback(world, result);
+            }
+        };
+        if self.callbacks.send(Box::new(callback)).is_err() {
+            log::warn!("Task completed after the `CallbackQueue` was dropped");
+        }
+    }
+}
+
+type PollFn = Box<dyn FnMut(&mut Context<'_>) -> Poll<()> + Send>;
+
+/// A future waiting to be polled on the thread pool.
+struct FutureTask {
+    poll: Mutex<Option<PollFn>>,
+    control: Arc<Control>,
+    pool: ArcThreadPool,
+}
+
+impl FutureTask {
+    fn schedule(task: &Arc<Self>) {
+        let task = task.clone();
+        task.pool.clone().spawn(move || task.run());
+    }
+
+    fn run(self: Arc<Self>) {
+        // A wake up during polling waits here until the current poll is done.
+        let mut slot = self
+            .poll
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(mut poll) = slot.take() {
+            let waker = waker_ref(&self);
+            let mut cx = Context::from_waker(&waker);
+            if poll(&mut cx).is_pending() && !self.control.is_cancelled() {
+                *slot = Some(poll);
+            }
+        }
+        drop(slot);
+        // `cancel` can't drop the future while it is being polled, so it's dropped here.
+        if self.control.is_cancelled() {
+            self.drop_future();
+        }
+    }
+
+    /// Drops the future, unless it is being polled right now.
+    fn drop_future(&self) {
+        let poll = match self.poll.try_lock() {
+            Ok(mut slot) => slot.take(),
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().take(),
+            Err(TryLockError::WouldBlock) => None,
+        };
+        drop(poll);
+    }
+}
+
+impl ArcWake for FutureTask {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        FutureTask::schedule(arc_self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use crossbeam_channel::Receiver;
+    use rayon::ThreadPoolBuilder;
+
+    use super::*;
+    use crate::{callback_queue::CallbackQueue, core::ecs::prelude::WorldExt};
+
+    fn tasks() -> (Tasks, Receiver<Callback>) {
+        let pool = Arc::new(ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+        let queue = CallbackQueue::new();
+        let receiver = queue.receiver.clone();
+        (Tasks::new(pool, queue.send_handle()), receiver)
+    }
+
+    fn wait<T: Send + 'static>(handle: &TaskHandle<T>) -> Result<T, Error> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Some(result) = handle.poll() {
+                return result;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("Task did not finish within 5 seconds");
+    }
+
+    #[test]
+    fn blocking_job_reports_result_and_progress() {
+        let (tasks, _receiver) = tasks();
+        let handle = tasks.spawn_blocking(|context| {
+            context.set_progress(2.0);
+            21 * 2
+        });
+        assert_eq!(wait(&handle).unwrap(), 42);
+        assert_eq!(handle.progress(), 1.0);
+        assert!(handle.is_finished());
+        assert!(handle.poll().is_none());
+    }
+
+    #[test]
+    fn future_is_driven_to_completion() {
+        let (tasks, _receiver) = tasks();
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        let handle = tasks.spawn(|_| async move { receiver.await.unwrap() + 1 });
+        assert!(handle.poll().is_none());
+
+        sender.send(1).unwrap();
+        assert_eq!(wait(&handle).unwrap(), 2);
+    }
+
+    #[test]
+    fn panic_and_cancel_are_errors() {
+        let (tasks, _receiver) = tasks();
+        let panicked = tasks.spawn_blocking(|_| -> u32 { panic!("boom") });
+        assert!(wait(&panicked).is_err());
+
+        let (_sender, receiver) = futures::channel::oneshot::channel::<u32>();
+        let cancelled = tasks.spawn(|_| receiver);
+        cancelled.cancel();
+        assert!(cancelled.is_cancelled());
+        assert!(wait(&cancelled).is_err());
+    }
+
+    #[test]
+    fn cancelled_future_is_dropped_without_wake_up() {
+        struct Dropped(Arc<AtomicBool>);
+
+        impl Drop for Dropped {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let (tasks, _receiver) = tasks();
+        let dropped = Arc::new(AtomicBool::new(false));
+        let guard = Dropped(dropped.clone());
+        // Never woken, since the sender is kept alive.
+        let (_sender, receiver) = futures::channel::oneshot::channel::<u32>();
+        let handle = tasks.spawn(move |_| async move {
+            let _guard = guard;
+            receiver.await.unwrap()
+        });
+        handle.cancel();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !dropped.load(Ordering::SeqCst) {
+            assert!(
+                Instant::now() < deadline,
+                "Cancelled future was not dropped"
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn callback_runs_on_world() {
+        let (tasks, receiver) = tasks();
+        tasks
+            .spawn_blocking(|_| 7)
+            .on_complete(|world, result| world.insert(result.unwrap()));
+
+        let callback = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        let mut world = World::new();
+        callback(&mut world);
+        assert_eq!(*world.fetch::<i32>(), 7);
+    }
+}